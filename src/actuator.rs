@@ -0,0 +1,236 @@
+//! Common interface of the actuator drivers of this crate
+//!
+//! Controllers such as [`threshold::Controller`](crate::threshold::Controller) drive any
+//! actuator implementing these traits, so the same control logic works for an LED,
+//! a plain relay or a protected relay.
+
+use embedded_hal::{
+    digital::{InputPin, OutputPin},
+    pwm::SetDutyCycle,
+};
+use embedded_timers::clock::Clock;
+
+use crate::{dc_relay, led};
+
+/// On/off actuator
+pub trait Switch {
+    /// Error of the underlying driver
+    type Error;
+
+    /// Turn the actuator on or off
+    ///
+    /// Actuators with protection may defer the change, see [`is_on`](Self::is_on)
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error>;
+
+    /// Whether the actuator is actually on
+    fn is_on(&self) -> bool;
+
+    /// Called periodically by controllers, for actuators applying deferred or timed changes
+    fn update(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<T: Switch> Switch for &mut T {
+    type Error = T::Error;
+
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error> {
+        T::switch(self, on)
+    }
+
+    fn is_on(&self) -> bool {
+        T::is_on(self)
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        T::update(self)
+    }
+}
+
+impl<P: OutputPin> Switch for led::Driver<P> {
+    type Error = P::Error;
+
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error> {
+        if on {
+            self.on()
+        } else {
+            self.off()
+        }
+    }
+
+    fn is_on(&self) -> bool {
+        self.is_on()
+    }
+}
+
+impl<P: OutputPin> Switch for dc_relay::Driver<P> {
+    type Error = P::Error;
+
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error> {
+        self.set(on)
+    }
+
+    fn is_on(&self) -> bool {
+        self.is_on()
+    }
+}
+
+impl<P: OutputPin, F: InputPin> Switch for dc_relay::FeedbackDriver<P, F> {
+    type Error = dc_relay::FeedbackDriverError<P, F>;
+
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error> {
+        self.set(on)
+    }
+
+    fn is_on(&self) -> bool {
+        self.is_on()
+    }
+}
+
+impl<C: Clock, P: OutputPin> Switch for dc_relay::ProtectedDriver<'_, C, P> {
+    type Error = dc_relay::ProtectedDriverError<P>;
+
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error> {
+        if on {
+            self.on()
+        } else {
+            self.off()
+        }
+    }
+
+    fn is_on(&self) -> bool {
+        self.is_on()
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        // 应用等待中的切换
+        self.update().map(|_| ())
+    }
+}
+
+impl<C: Clock, P: OutputPin> Switch for dc_relay::TimedDriver<'_, C, P> {
+    type Error = P::Error;
+
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error> {
+        if on {
+            self.on()
+        } else {
+            self.off()
+        }
+    }
+
+    fn is_on(&self) -> bool {
+        self.is_on()
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        // 应用到期的定时切换
+        self.update().map(|_| ())
+    }
+}
+
+impl<C: Clock, P: SetDutyCycle> Switch for dc_relay::EconomizerDriver<'_, C, P> {
+    type Error = P::Error;
+
+    fn switch(&mut self, on: bool) -> Result<(), Self::Error> {
+        if on {
+            self.on()
+        } else {
+            self.off()
+        }
+    }
+
+    fn is_on(&self) -> bool {
+        self.is_on()
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        // 吸合时间结束后降低到保持占空比
+        self.update().map(|_| ())
+    }
+}
+
+/// Actuator with a variable duty cycle
+pub trait Duty {
+    /// Error of the underlying driver
+    type Error;
+
+    /// Set the duty cycle as a fraction, clamped to 0.0 (off) ~ 1.0 (fully on)
+    fn set_duty(&mut self, duty: f32) -> Result<(), Self::Error>;
+
+    /// Called periodically by controllers, for actuators modulating the output in software
+    fn update(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<T: Duty> Duty for &mut T {
+    type Error = T::Error;
+
+    fn set_duty(&mut self, duty: f32) -> Result<(), Self::Error> {
+        T::set_duty(self, duty)
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        T::update(self)
+    }
+}
+
+/// Convert a duty cycle fraction to the duty cycle value of a PWM channel
+fn duty_cycle(duty: f32, max_duty_cycle: u16) -> u16 {
+    // 四舍五入到最近的占空比值，NaN视为关闭
+    let duty = if duty.is_nan() {
+        0.0
+    } else {
+        duty.clamp(0.0, 1.0)
+    };
+    (duty * max_duty_cycle as f32 + 0.5) as u16
+}
+
+impl<P: SetDutyCycle> Duty for led::PwmDriver<P> {
+    type Error = P::Error;
+
+    fn set_duty(&mut self, duty: f32) -> Result<(), Self::Error> {
+        self.set_duty_cycle(duty_cycle(duty, self.max_duty_cycle()))
+    }
+}
+
+impl<P: SetDutyCycle> Duty for dc_relay::PwmDriver<P> {
+    type Error = P::Error;
+
+    fn set_duty(&mut self, duty: f32) -> Result<(), Self::Error> {
+        self.set_duty_cycle(duty_cycle(duty, self.max_duty_cycle()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{duty_cycle, Duty, Switch};
+    use crate::{
+        dc_relay, led,
+        mock::{OutputState, PwmState},
+    };
+
+    #[test]
+    fn duty_cycle_is_rounded_and_clamped() {
+        assert_eq!(duty_cycle(0.5, 255), 128);
+        assert_eq!(duty_cycle(-1.0, 255), 0);
+        assert_eq!(duty_cycle(2.0, 255), 255);
+        assert_eq!(duty_cycle(f32::NAN, 255), 0);
+        assert_eq!(duty_cycle(1.0, u16::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn drivers_implement_actuator_traits() {
+        let state = OutputState::new();
+        let mut relay = dc_relay::Driver::new(state.pin(), led::PinState::High, false).unwrap();
+        Switch::switch(&mut &mut relay, true).unwrap();
+        assert!(state.is_high());
+        assert!(relay.is_on());
+
+        let pwm = PwmState::new(1000);
+        let mut driver = dc_relay::PwmDriver::new(pwm.pwm());
+        driver.set_duty(0.25).unwrap();
+        assert_eq!(pwm.duty(), 250);
+    }
+}
@@ -0,0 +1,196 @@
+//! Barometric altitude from the BME280 pressure
+//!
+//! Uses the international barometric formula of the ICAO standard atmosphere, valid in the
+//! troposphere (below 11km). Pressures are in Pa as returned by `bme280::Driver::read`,
+//! altitudes in meters.
+
+use embedded_timers::{clock::Clock, instant::Instant};
+use libm::powf;
+
+pub use crate::psychrometrics::STANDARD_PRESSURE;
+
+/// Altitude scale of the barometric formula (m)
+const ALTITUDE_SCALE: f32 = 44_330.77;
+
+/// Exponent of the barometric formula, R·L / (g·M)
+const EXPONENT: f32 = 0.190_263;
+
+/// Barometric altitude (m)
+///
+/// - sea_level_pressure: Reference pressure at sea level (QNH) in Pa,
+///   use [`STANDARD_PRESSURE`] for the pressure altitude
+pub fn altitude(pressure: f32, sea_level_pressure: f32) -> f32 {
+    ALTITUDE_SCALE * (1.0 - powf(pressure / sea_level_pressure, EXPONENT))
+}
+
+/// Sea-level-equivalent pressure (QNH) in Pa, given the altitude the pressure was measured at
+pub fn sea_level_pressure(pressure: f32, altitude: f32) -> f32 {
+    pressure / powf(1.0 - altitude / ALTITUDE_SCALE, 1.0 / EXPONENT)
+}
+
+/// Altimeter holding the reference sea level pressure
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Altimeter {
+    /// Reference pressure at sea level (Pa)
+    sea_level_pressure: f32,
+}
+
+impl Default for Altimeter {
+    fn default() -> Self {
+        Self::new(STANDARD_PRESSURE)
+    }
+}
+
+impl Altimeter {
+    /// Create an altimeter from a known sea level pressure (QNH) in Pa
+    pub fn new(sea_level_pressure: f32) -> Self {
+        Self { sea_level_pressure }
+    }
+
+    /// Calibrate the reference from a reading taken at a known altitude
+    ///
+    /// Afterwards [`altitude`](Self::altitude) returns `known_altitude` for `pressure`.
+    /// Call it at the launch site or on the ground before take-off.
+    pub fn calibrate(&mut self, pressure: f32, known_altitude: f32) {
+        self.sea_level_pressure = sea_level_pressure(pressure, known_altitude);
+    }
+
+    /// Reference sea level pressure (Pa)
+    pub fn sea_level_pressure(&self) -> f32 {
+        self.sea_level_pressure
+    }
+
+    /// Altitude (m) of a pressure reading
+    pub fn altitude(&self, pressure: f32) -> f32 {
+        altitude(pressure, self.sea_level_pressure)
+    }
+}
+
+/// Vertical speed estimator
+///
+/// Differentiates consecutive altitudes over the clock time between them.
+/// Barometric altitude is noisy, so the speed is smoothed with an exponential moving average.
+pub struct VerticalSpeed<'a, C: Clock> {
+    /// Smoothing factor (0.0 ~ 1.0], 1.0 disables smoothing
+    smoothing: f32,
+    /// Previous altitude and the time it was taken
+    last: Option<(f32, C::Instant)>,
+    /// Smoothed vertical speed (m/s)
+    speed: f32,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock> VerticalSpeed<'a, C> {
+    /// Create a vertical speed estimator
+    ///
+    /// - smoothing: Weight of the newest speed sample, clamped to (0.0 ~ 1.0].
+    ///   Smaller values filter more noise but react slower
+    pub fn new(clock: &'a C, smoothing: f32) -> Self {
+        Self {
+            smoothing: smoothing.clamp(f32::EPSILON, 1.0),
+            last: None,
+            speed: 0.0,
+            clock_impl: clock,
+        }
+    }
+
+    /// Feed a new altitude (m) and get the vertical speed (m/s), positive when climbing
+    ///
+    /// Note: The first altitude only sets the starting point and returns 0.
+    /// Readings taken at the same instant are ignored.
+    pub fn update(&mut self, altitude: f32) -> f32 {
+        let now = self.clock_impl.now();
+        match self.last {
+            None => self.last = Some((altitude, now)),
+            Some((last_altitude, last_time)) => {
+                let elapsed = now.duration_since(last_time).as_secs_f32();
+                if elapsed > 0.0 {
+                    let speed = (altitude - last_altitude) / elapsed;
+                    // 指数滑动平均
+                    self.speed += self.smoothing * (speed - self.speed);
+                    self.last = Some((altitude, now));
+                }
+            }
+        }
+        self.speed
+    }
+
+    /// Latest vertical speed (m/s)
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Forget the previous altitude and speed
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.speed = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{altitude, sea_level_pressure, Altimeter, VerticalSpeed, STANDARD_PRESSURE};
+    use crate::mock::{assert_close, MockClock};
+
+    #[test]
+    fn standard_atmosphere_vectors() {
+        assert_close(altitude(STANDARD_PRESSURE, STANDARD_PRESSURE), 0.0, 0.01);
+        // 标准大气: 1000m -> 89874.6Pa, 5000m -> 54019.9Pa
+        assert_close(altitude(89_874.6, STANDARD_PRESSURE), 1000.0, 0.5);
+        assert_close(altitude(54_019.9, STANDARD_PRESSURE), 5000.0, 2.0);
+        assert_close(sea_level_pressure(89_874.6, 1000.0), STANDARD_PRESSURE, 2.0);
+    }
+
+    #[test]
+    fn qnh_round_trips() {
+        let qnh = sea_level_pressure(90_000.0, 500.0);
+        assert_close(qnh, 95_528.7, 2.0);
+        assert_close(altitude(90_000.0, qnh), 500.0, 0.1);
+    }
+
+    #[test]
+    fn altimeter_calibrates_to_known_altitude() {
+        let mut altimeter = Altimeter::default();
+        assert_eq!(altimeter.sea_level_pressure(), STANDARD_PRESSURE);
+
+        altimeter.calibrate(98_000.0, 120.0);
+        assert_close(altimeter.altitude(98_000.0), 120.0, 0.1);
+        // 气压下降100Pa约对应高度升高8.6m
+        assert_close(altimeter.altitude(97_900.0), 128.6, 0.5);
+    }
+
+    #[test]
+    fn vertical_speed_follows_climb() {
+        let clock = MockClock::manual();
+        let mut estimator = VerticalSpeed::new(&clock, 1.0);
+
+        assert_eq!(estimator.update(100.0), 0.0);
+        clock.advance(Duration::from_millis(500));
+        assert_close(estimator.update(102.5), 5.0, 0.001);
+        clock.advance(Duration::from_secs(2));
+        assert_close(estimator.update(98.5), -2.0, 0.001);
+
+        // 同一时刻的读数被忽略
+        assert_close(estimator.update(0.0), -2.0, 0.001);
+
+        estimator.reset();
+        assert_eq!(estimator.speed(), 0.0);
+    }
+
+    #[test]
+    fn vertical_speed_is_smoothed() {
+        let clock = MockClock::manual();
+        let mut estimator = VerticalSpeed::new(&clock, 0.5);
+
+        estimator.update(0.0);
+        clock.advance(Duration::from_secs(1));
+        assert_close(estimator.update(4.0), 2.0, 0.001);
+        clock.advance(Duration::from_secs(1));
+        assert_close(estimator.update(8.0), 3.0, 0.001);
+    }
+}
@@ -0,0 +1,690 @@
+//! Command line tool reading the sensors of this crate on Linux
+//!
+//! I2C sensors are accessed through i2c-dev (`/dev/i2c-*`), GPIO sensors through the GPIO
+//! character devices (`/dev/gpiochip*`). With `--sim` the simulated devices of the crate are
+//! used instead, so the tool can be tried without hardware.
+//!
+//! ```text
+//! sensor-hal bme280 --bus /dev/i2c-1 --addr 0x76 --interval 1s --format json
+//! sensor-hal hx711 --chip /dev/gpiochip0 --sck 5 --dout 6 --gain a128
+//! sensor-hal --sim dht11 --count 3
+//! sensor-hal scan --bus /dev/i2c-1
+//! ```
+
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use embedded_hal::{
+    digital::{InputPin, OutputPin},
+    i2c::{self, I2c, Operation, SevenBitAddress},
+};
+use embedded_timers::{clock::Clock, instant::Instant64};
+use linux_embedded_hal::{
+    gpio_cdev::{Chip, LineRequestFlags},
+    i2cdev::linux::LinuxI2CError,
+    CdevPin, I2CError, I2cdev,
+};
+use sensor_hal::{
+    aht30, bme280, dht11, hx711,
+    probe::{self, Kind},
+    sim::{Aht30Device, Bme280Device, Dht11Device, Hx711Device, SimBus, SimClock, Waveform},
+};
+
+/// Consumer label of the requested GPIO lines
+const CONSUMER: &str = "sensor-hal";
+
+/// Microsecond clock following the host monotonic clock
+struct HostClock(Instant);
+
+impl HostClock {
+    /// Create a clock starting now
+    fn new() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Clock for HostClock {
+    type Instant = Instant64<1_000_000>;
+
+    fn now(&self) -> Self::Instant {
+        Instant64::new(self.0.elapsed().as_micros() as u64)
+    }
+}
+
+/// Request a line of a GPIO character device such as `/dev/gpiochip0`
+fn request_line(
+    chip: &Path,
+    line: u32,
+    flags: LineRequestFlags,
+    default: u8,
+) -> Result<CdevPin, String> {
+    let handle = Chip::new(chip)
+        .and_then(|mut chip| chip.get_line(line))
+        .and_then(|line| line.request(flags, default, CONSUMER))
+        .map_err(describe)?;
+    CdevPin::new(handle).map_err(describe)
+}
+
+/// i2c-dev bus used for scanning
+///
+/// Several adapter drivers (e.g. i2c-bcm2835) report an unacknowledged address with
+/// EREMOTEIO instead of ENXIO, which `linux-embedded-hal` does not treat as a NACK.
+struct ScanBus(I2cdev);
+
+/// Error of the [`ScanBus`]
+#[derive(Debug)]
+struct ScanError(I2CError);
+
+impl i2c::Error for ScanError {
+    fn kind(&self) -> i2c::ErrorKind {
+        // EREMOTEIO(121)
+        let errno = match self.0.inner() {
+            LinuxI2CError::Errno(errno) => Some(*errno),
+            LinuxI2CError::Io(err) => err.raw_os_error(),
+        };
+        match errno {
+            Some(121) => i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Unknown),
+            _ => i2c::Error::kind(&self.0),
+        }
+    }
+}
+
+impl i2c::ErrorType for ScanBus {
+    type Error = ScanError;
+}
+
+impl I2c<SevenBitAddress> for ScanBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.0.transaction(address, operations).map_err(ScanError)
+    }
+}
+
+/// Read a sensor periodically and print the measurements
+#[derive(Parser)]
+#[command(name = "sensor-hal", version)]
+struct Cli {
+    /// Sensor to read
+    #[command(subcommand)]
+    sensor: Sensor,
+
+    /// Time between two readings, e.g. 500ms, 1s, 2m
+    #[arg(long, global = true, default_value = "1s", value_parser = parse_duration)]
+    interval: Duration,
+
+    /// Number of readings, 0 reads until interrupted
+    #[arg(long, global = true, default_value_t = 0)]
+    count: u64,
+
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Read the simulated device instead of the hardware
+    #[arg(long, global = true)]
+    sim: bool,
+}
+
+#[derive(Subcommand)]
+enum Sensor {
+    /// AHT30 temperature and humidity sensor (I2C)
+    Aht30 {
+        /// i2c-dev device
+        #[arg(long, default_value = "/dev/i2c-1")]
+        bus: PathBuf,
+        /// 7bit address
+        #[arg(long, default_value = "0x38", value_parser = parse_address)]
+        addr: u8,
+    },
+    /// BME280 temperature, pressure and humidity sensor (I2C)
+    Bme280 {
+        /// i2c-dev device
+        #[arg(long, default_value = "/dev/i2c-1")]
+        bus: PathBuf,
+        /// 7bit address
+        #[arg(long, default_value = "0x76", value_parser = parse_address)]
+        addr: u8,
+    },
+    /// DHT11 temperature and humidity sensor (1-Wire on a GPIO line)
+    ///
+    /// The line is driven open drain. Reading the bit timing from user space is sensitive to
+    /// scheduling, occasional checksum errors are expected.
+    Dht11 {
+        /// GPIO character device
+        #[arg(long, default_value = "/dev/gpiochip0")]
+        chip: PathBuf,
+        /// Line offset of the data pin, required unless `--sim` is given
+        #[arg(long)]
+        line: Option<u32>,
+    },
+    /// HX711 24 bit ADC for load cells (two GPIO lines)
+    Hx711 {
+        /// GPIO character device
+        #[arg(long, default_value = "/dev/gpiochip0")]
+        chip: PathBuf,
+        /// Line offset of the clock (PD_SCK) pin, required unless `--sim` is given
+        #[arg(long)]
+        sck: Option<u32>,
+        /// Line offset of the data (DOUT) pin, required unless `--sim` is given
+        #[arg(long)]
+        dout: Option<u32>,
+        /// Channel and gain
+        #[arg(long, value_enum, default_value_t = Gain::A128)]
+        gain: Gain,
+    },
+    /// Scan an I2C bus and identify the supported sensors
+    Scan {
+        /// i2c-dev device
+        #[arg(long, default_value = "/dev/i2c-1")]
+        bus: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// One line of `name=value` pairs per reading
+    Text,
+    /// One JSON object per reading
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Gain {
+    /// Channel A, gain 128
+    A128,
+    /// Channel A, gain 64
+    A64,
+    /// Channel B, gain 32
+    B32,
+}
+
+impl From<Gain> for hx711::ChannelGain {
+    fn from(gain: Gain) -> Self {
+        match gain {
+            Gain::A128 => Self::ChannelA128,
+            Gain::A64 => Self::ChannelA64,
+            Gain::B32 => Self::ChannelB32,
+        }
+    }
+}
+
+/// Parse a duration such as `250ms`, `1s`, `1.5s` or `2m`, a bare number is in seconds
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", value))?;
+    let seconds = match unit.trim() {
+        "us" => number / 1_000_000.0,
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        unit => return Err(format!("unknown duration unit `{}`", unit)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
+
+/// Parse a 7bit I2C address, decimal or hexadecimal with a `0x` prefix
+fn parse_address(value: &str) -> Result<u8, String> {
+    let address = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|_| format!("invalid address `{}`", value))?;
+    if address > 0x7F {
+        return Err(format!("address `{}` is not a 7bit address", value));
+    }
+    Ok(address)
+}
+
+/// One reading: quantity name, unit and value
+type Reading = Vec<(&'static str, &'static str, f32)>;
+
+/// A sensor driver together with the peripherals it needs
+trait Probe {
+    /// Name of the sensor
+    fn name(&self) -> &'static str;
+    /// Take one reading
+    fn read(&mut self) -> Result<Reading, String>;
+}
+
+fn describe(err: impl Debug) -> String {
+    format!("{:?}", err)
+}
+
+struct Aht30Probe<'a, C: Clock, B> {
+    driver: aht30::Driver<'a, C>,
+    bus: B,
+}
+
+impl<'a, C: Clock, B: I2c<SevenBitAddress>> Aht30Probe<'a, C, B>
+where
+    B::Error: Debug,
+{
+    fn open(clock: &'a C, mut bus: B, address: u8) -> Result<Self, String> {
+        let driver = aht30::Driver::new(clock, &mut bus, Some(address)).map_err(describe)?;
+        Ok(Self { driver, bus })
+    }
+}
+
+impl<C: Clock, B: I2c<SevenBitAddress>> Probe for Aht30Probe<'_, C, B>
+where
+    B::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "aht30"
+    }
+
+    fn read(&mut self) -> Result<Reading, String> {
+        let (temperature, humidity) = self.driver.read(&mut self.bus).map_err(describe)?;
+        Ok(vec![
+            ("temperature", "°C", temperature),
+            ("humidity", "%RH", humidity),
+        ])
+    }
+}
+
+struct Bme280Probe<'a, C: Clock, B> {
+    driver: bme280::Driver<'a, C>,
+    bus: B,
+}
+
+impl<'a, C: Clock, B: I2c<SevenBitAddress>> Bme280Probe<'a, C, B>
+where
+    B::Error: Debug,
+{
+    fn open(clock: &'a C, mut bus: B, address: u8) -> Result<Self, String> {
+        let driver = bme280::Driver::new(clock, &mut bus, Some(address)).map_err(describe)?;
+        Ok(Self { driver, bus })
+    }
+}
+
+impl<C: Clock, B: I2c<SevenBitAddress>> Probe for Bme280Probe<'_, C, B>
+where
+    B::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "bme280"
+    }
+
+    fn read(&mut self) -> Result<Reading, String> {
+        let (temperature, pressure, humidity) =
+            self.driver.read(&mut self.bus).map_err(describe)?;
+        Ok(vec![
+            ("temperature", "°C", temperature),
+            ("pressure", "Pa", pressure),
+            ("humidity", "%RH", humidity),
+        ])
+    }
+}
+
+struct Dht11Probe<'a, C: Clock, P: InputPin + OutputPin> {
+    driver: dht11::Driver<'a, C, P>,
+}
+
+impl<C: Clock, P: InputPin + OutputPin> Probe for Dht11Probe<'_, C, P>
+where
+    P::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "dht11"
+    }
+
+    fn read(&mut self) -> Result<Reading, String> {
+        let (temperature, humidity) = self.driver.read().map_err(describe)?;
+        Ok(vec![
+            ("temperature", "°C", temperature),
+            ("humidity", "%RH", humidity),
+        ])
+    }
+}
+
+struct Hx711Probe<'a, C: Clock, I: InputPin, O: OutputPin> {
+    driver: hx711::Driver<'a, C, I, O>,
+}
+
+impl<C: Clock, I: InputPin, O: OutputPin> Probe for Hx711Probe<'_, C, I, O>
+where
+    I::Error: Debug,
+    O::Error: Debug,
+{
+    fn name(&self) -> &'static str {
+        "hx711"
+    }
+
+    fn read(&mut self) -> Result<Reading, String> {
+        // 等待转换完成，10SPS模式下最长100ms
+        for _ in 0..200 {
+            if self.driver.is_ready().map_err(describe)? {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        let value = self.driver.read().map_err(describe)?;
+        Ok(vec![("value", "", value as f32)])
+    }
+}
+
+/// Open the sensor on the hardware
+fn open_hardware<'a>(clock: &'a HostClock, sensor: &Sensor) -> Result<Box<dyn Probe + 'a>, String> {
+    Ok(match sensor {
+        Sensor::Aht30 { bus, addr } => {
+            let bus = I2cdev::new(bus).map_err(describe)?;
+            Box::new(Aht30Probe::open(clock, bus, *addr)?)
+        }
+        Sensor::Bme280 { bus, addr } => {
+            let bus = I2cdev::new(bus).map_err(describe)?;
+            Box::new(Bme280Probe::open(clock, bus, *addr)?)
+        }
+        Sensor::Dht11 { chip, line } => {
+            let line = line.ok_or("--line is required")?;
+            // 开漏输出，释放总线时可以读回数据线电平
+            let flags = LineRequestFlags::OUTPUT | LineRequestFlags::OPEN_DRAIN;
+            let pin = request_line(chip, line, flags, 1)?;
+            let driver = dht11::Driver::new(clock, pin).map_err(describe)?;
+            Box::new(Dht11Probe { driver })
+        }
+        Sensor::Hx711 {
+            chip,
+            sck,
+            dout,
+            gain,
+        } => {
+            let sck = sck.ok_or("--sck is required")?;
+            let dout = dout.ok_or("--dout is required")?;
+            let sck = request_line(chip, sck, LineRequestFlags::OUTPUT, 0)?;
+            let dout = request_line(chip, dout, LineRequestFlags::INPUT, 0)?;
+            let driver = hx711::Driver::new(clock, sck, dout, (*gain).into()).map_err(describe)?;
+            Box::new(Hx711Probe { driver })
+        }
+        Sensor::Scan { .. } => unreachable!("scan is handled by scan_bus"),
+    })
+}
+
+/// Open the simulated sensor, the measurements slowly follow a daily like cycle
+fn open_simulated<'a>(clock: &'a SimClock, sensor: &Sensor) -> Result<Box<dyn Probe + 'a>, String> {
+    let temperature = Waveform::Sine {
+        offset: 22.0,
+        amplitude: 3.0,
+        period: Duration::from_secs(600),
+    }
+    .with_noise(0.05, 1);
+    let humidity = Waveform::Sine {
+        offset: 50.0,
+        amplitude: -10.0,
+        period: Duration::from_secs(600),
+    }
+    .with_noise(0.2, 2);
+    Ok(match sensor {
+        Sensor::Aht30 { addr, .. } => {
+            let device = Aht30Device::new(clock);
+            device.set_address(*addr);
+            device.set_temperature(temperature);
+            device.set_humidity(humidity);
+            Box::new(Aht30Probe::open(clock, device, *addr)?)
+        }
+        Sensor::Bme280 { addr, .. } => {
+            let device = Bme280Device::new(clock);
+            device.set_address(*addr);
+            device.set_temperature(temperature);
+            device.set_humidity(humidity);
+            device.set_pressure(
+                Waveform::Sine {
+                    offset: 101_325.0,
+                    amplitude: 150.0,
+                    period: Duration::from_secs(900),
+                }
+                .with_noise(2.0, 3),
+            );
+            Box::new(Bme280Probe::open(clock, device, *addr)?)
+        }
+        Sensor::Dht11 { .. } => {
+            let device = Dht11Device::new(clock);
+            device.set_temperature(temperature);
+            device.set_humidity(humidity);
+            device.measure();
+            let driver = dht11::Driver::new(clock, device).map_err(describe)?;
+            Box::new(Dht11Probe { driver })
+        }
+        Sensor::Hx711 { gain, .. } => {
+            let device = Hx711Device::new(clock);
+            device.set_scale(8_388, 420.0);
+            device.set_weight(Waveform::Square {
+                low: 0.0,
+                high: 500.0,
+                period: Duration::from_secs(20),
+            });
+            let driver =
+                hx711::Driver::new(clock, device.clock_pin(), device.data_pin(), (*gain).into())
+                    .map_err(describe)?;
+            Box::new(Hx711Probe { driver })
+        }
+        Sensor::Scan { .. } => unreachable!("scan is handled by scan_bus"),
+    })
+}
+
+/// Scan the bus and print every acknowledging address with the identified part
+fn scan_bus<B: I2c<SevenBitAddress>>(bus: &mut B, format: Format) -> Result<(), String>
+where
+    B::Error: Debug,
+{
+    let found = probe::scan(bus).map_err(describe)?;
+    let identified = probe::identify(bus).map_err(describe)?;
+    for address in found.iter() {
+        let part = match identified.iter().find(|device| device.address == address) {
+            Some(device) => match device.kind {
+                Kind::Aht30 => "aht30",
+                Kind::Bme280 => "bme280",
+                Kind::Bmp280 => "bmp280",
+            },
+            None => "unknown",
+        };
+        match format {
+            Format::Text => println!("0x{:02x} {}", address, part),
+            Format::Json => println!(
+                "{{\"address\":{},\"device\":{}}}",
+                address,
+                json_string(part)
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Escape a string for a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Format a value as a JSON number, `null` for NaN and infinity which JSON cannot represent
+fn json_number(value: f32) -> String {
+    match value.is_finite() {
+        true => value.to_string(),
+        false => String::from("null"),
+    }
+}
+
+/// Format one reading or error
+fn format_line(
+    format: Format,
+    sensor: &str,
+    time: f64,
+    reading: &Result<Reading, String>,
+) -> String {
+    match (format, reading) {
+        (Format::Text, Ok(reading)) => {
+            let values: Vec<String> = reading
+                .iter()
+                .map(|(name, unit, value)| match value.fract() == 0.0 {
+                    true => format!("{}={}{}", name, value, unit),
+                    false => format!("{}={:.2}{}", name, value, unit),
+                })
+                .collect();
+            format!("{:.3} {} {}", time, sensor, values.join(" "))
+        }
+        (Format::Text, Err(err)) => format!("{:.3} {} error: {}", time, sensor, err),
+        (Format::Json, Ok(reading)) => {
+            let values: Vec<String> = reading
+                .iter()
+                .map(|(name, _, value)| format!("{}:{}", json_string(name), json_number(*value)))
+                .collect();
+            format!(
+                "{{\"sensor\":{},\"time\":{:.3},{}}}",
+                json_string(sensor),
+                time,
+                values.join(",")
+            )
+        }
+        (Format::Json, Err(err)) => format!(
+            "{{\"sensor\":{},\"time\":{:.3},\"error\":{}}}",
+            json_string(sensor),
+            time,
+            json_string(err)
+        ),
+    }
+}
+
+/// Read the sensor until the requested number of readings was taken
+///
+/// The virtual clock of the simulated devices is advanced by the interval after every reading.
+fn run(probe: &mut dyn Probe, cli: &Cli, sim_clock: Option<&SimClock>) {
+    let mut taken = 0;
+    loop {
+        let reading = probe.read();
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let line = format_line(cli.format, probe.name(), time, &reading);
+        if reading.is_ok() {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
+        }
+        taken += 1;
+        if cli.count != 0 && taken >= cli.count {
+            break;
+        }
+        thread::sleep(cli.interval);
+        if let Some(clock) = sim_clock {
+            clock.advance(cli.interval);
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let host_clock = HostClock::new();
+    // 模拟设备使用虚拟时钟，避免主机调度抖动影响DHT11等对时序敏感的设备
+    let sim_clock = SimClock::virtual_time();
+    if let Sensor::Scan { bus } = &cli.sensor {
+        let result = if cli.sim {
+            let mut bus = SimBus::new();
+            bus.attach(Aht30Device::new(&sim_clock))
+                .attach(Bme280Device::new(&sim_clock));
+            scan_bus(&mut bus, cli.format)
+        } else {
+            I2cdev::new(bus)
+                .map_err(describe)
+                .and_then(|bus| scan_bus(&mut ScanBus(bus), cli.format))
+        };
+        return match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("sensor-hal: failed to scan the bus: {}", err);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let probe = if cli.sim {
+        open_simulated(&sim_clock, &cli.sensor)
+    } else {
+        open_hardware(&host_clock, &cli.sensor)
+    };
+    match probe {
+        Ok(mut probe) => {
+            run(probe.as_mut(), &cli, cli.sim.then_some(&sim_clock));
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("sensor-hal: failed to open the sensor: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_line, parse_address, parse_duration, Format};
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("1s"), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("1h").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(parse_address("0x76"), Ok(0x76));
+        assert_eq!(parse_address("56"), Ok(0x38));
+        assert!(parse_address("0x80").is_err());
+        assert!(parse_address("0xZZ").is_err());
+    }
+
+    #[test]
+    fn formats_json_lines() {
+        let reading = Ok(vec![("temperature", "°C", 21.5), ("humidity", "%RH", 40.0)]);
+        assert_eq!(
+            format_line(Format::Json, "aht30", 12.0, &reading),
+            r#"{"sensor":"aht30","time":12.000,"temperature":21.5,"humidity":40}"#
+        );
+        let error = Err(String::from("The AHT30 sensor is \"busy\"."));
+        assert_eq!(
+            format_line(Format::Json, "aht30", 1.5, &error),
+            r#"{"sensor":"aht30","time":1.500,"error":"The AHT30 sensor is \"busy\"."}"#
+        );
+    }
+
+    #[test]
+    fn formats_non_finite_values_as_json_null() {
+        let reading = Ok(vec![
+            ("temperature", "°C", f32::NAN),
+            ("pressure", "Pa", f32::INFINITY),
+            ("humidity", "%RH", -0.5),
+        ]);
+        assert_eq!(
+            format_line(Format::Json, "bme280", 2.0, &reading),
+            r#"{"sensor":"bme280","time":2.000,"temperature":null,"pressure":null,"humidity":-0.5}"#
+        );
+    }
+}
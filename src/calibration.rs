@@ -0,0 +1,207 @@
+//! User calibration of the environmental sensors
+//!
+//! Sensors installed in enclosures often read a constant offset away from a reference
+//! instrument. A [`Record`] holds one linear [`Correction`] per quantity. Give it to a driver
+//! (e.g. `aht30::Driver::set_user_calibration`) and every `read` returns corrected values.
+//! [`Scale`] converts the raw HX711 readings of a load cell to a weight.
+
+/// Linear correction, `corrected = raw * gain + offset`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Correction {
+    /// Scale factor
+    pub gain: f32,
+    /// Added after scaling
+    pub offset: f32,
+}
+
+impl Default for Correction {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Correction {
+    /// Correction leaving the values unchanged
+    pub const IDENTITY: Self = Self {
+        gain: 1.0,
+        offset: 0.0,
+    };
+
+    /// Create a correction from a gain and an offset
+    pub fn new(gain: f32, offset: f32) -> Self {
+        Self { gain, offset }
+    }
+
+    /// Create an offset-only correction, e.g. -1.5 for a sensor reading 1.5°C too warm
+    pub fn from_offset(offset: f32) -> Self {
+        Self { gain: 1.0, offset }
+    }
+
+    /// Two-point calibration from two `(raw, reference)` pairs
+    ///
+    /// Take the pairs as far apart as possible, e.g. at both ends of the working range.
+    /// Returns `None` when both raw values are equal.
+    pub fn two_point(low: (f32, f32), high: (f32, f32)) -> Option<Self> {
+        let (raw_low, reference_low) = low;
+        let (raw_high, reference_high) = high;
+        let span = raw_high - raw_low;
+        if span == 0.0 || !span.is_finite() {
+            return None;
+        }
+        let gain = (reference_high - reference_low) / span;
+        Some(Self {
+            gain,
+            offset: reference_low - raw_low * gain,
+        })
+    }
+
+    /// Correct a raw value
+    pub fn apply(&self, raw: f32) -> f32 {
+        raw * self.gain + self.offset
+    }
+}
+
+/// Calibration record of one sensor
+///
+/// The default record leaves all readings unchanged.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record {
+    /// Temperature correction (°C)
+    pub temperature: Correction,
+    /// Relative humidity correction (%RH)
+    pub humidity: Correction,
+    /// Pressure correction (Pa)
+    pub pressure: Correction,
+}
+
+impl Record {
+    /// Correct a temperature (°C)
+    pub fn temperature(&self, raw: f32) -> f32 {
+        self.temperature.apply(raw)
+    }
+
+    /// Correct a relative humidity, limited to 0 ~ 100%RH
+    pub fn humidity(&self, raw: f32) -> f32 {
+        self.humidity.apply(raw).clamp(0.0, 100.0)
+    }
+
+    /// Correct a pressure (Pa)
+    pub fn pressure(&self, raw: f32) -> f32 {
+        self.pressure.apply(raw)
+    }
+}
+
+/// Load cell calibration of the HX711, `weight = (raw - offset) / factor`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scale {
+    /// Raw reading of the empty scale
+    pub offset: i32,
+    /// Raw counts per unit of weight
+    pub factor: f32,
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            factor: 1.0,
+        }
+    }
+}
+
+impl Scale {
+    /// Create a scale calibration
+    pub fn new(offset: i32, factor: f32) -> Self {
+        Self { offset, factor }
+    }
+
+    /// Zero the scale with the raw reading of the empty scale
+    pub fn tare(&mut self, raw: i32) {
+        self.offset = raw;
+    }
+
+    /// Calibrate the factor with the raw reading of a known weight on the tared scale
+    ///
+    /// Returns `false` and keeps the factor when the reading equals the tare or the weight is 0
+    pub fn calibrate(&mut self, raw: i32, known_weight: f32) -> bool {
+        let counts = raw.wrapping_sub(self.offset) as f32;
+        if counts == 0.0 || known_weight == 0.0 || !known_weight.is_finite() {
+            return false;
+        }
+        self.factor = counts / known_weight;
+        true
+    }
+
+    /// Convert a raw reading to a weight
+    pub fn weight(&self, raw: i32) -> f32 {
+        raw.wrapping_sub(self.offset) as f32 / self.factor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Correction, Record, Scale};
+    use crate::mock::assert_close;
+
+    #[test]
+    fn default_record_is_identity() {
+        let record = Record::default();
+        assert_eq!(record.temperature(23.4), 23.4);
+        assert_eq!(record.humidity(45.0), 45.0);
+        assert_eq!(record.pressure(101_325.0), 101_325.0);
+    }
+
+    #[test]
+    fn two_point_maps_raw_to_reference() {
+        // 冰水混合物读数0.8°C，沸水读数98.6°C
+        let correction = Correction::two_point((0.8, 0.0), (98.6, 100.0)).unwrap();
+        assert_close(correction.apply(0.8), 0.0, 1e-4);
+        assert_close(correction.apply(98.6), 100.0, 1e-4);
+        assert_close(correction.apply(49.7), 50.0, 1e-3);
+
+        assert!(Correction::two_point((20.0, 21.0), (20.0, 25.0)).is_none());
+    }
+
+    #[test]
+    fn humidity_is_clamped() {
+        let record = Record {
+            humidity: Correction::from_offset(5.0),
+            ..Record::default()
+        };
+        assert_eq!(record.humidity(50.0), 55.0);
+        assert_eq!(record.humidity(98.0), 100.0);
+    }
+
+    #[test]
+    fn scale_tares_and_calibrates() {
+        let mut scale = Scale::default();
+        scale.tare(8_388);
+        assert_eq!(scale.weight(8_388), 0.0);
+
+        // 放上500g砝码
+        assert!(scale.calibrate(218_388, 500.0));
+        assert_close(scale.factor, 420.0, 1e-3);
+        assert_close(scale.weight(50_388), 100.0, 1e-3);
+
+        assert!(!scale.calibrate(8_388, 500.0));
+        assert!(!scale.calibrate(218_388, 0.0));
+        assert_close(scale.factor, 420.0, 1e-3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn record_round_trips_through_serde() {
+        let record = Record {
+            temperature: Correction::new(1.02, -1.5),
+            ..Record::default()
+        };
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+    }
+}
@@ -0,0 +1,471 @@
+//! Allocation-free filters for smoothing sensor readings
+//!
+//! Every filter implements [`Filter`] and can be chained with [`Filter::chain`], e.g. a
+//! spike rejection followed by a moving average:
+//!
+//! ```
+//! use sensor_hal::filter::{Filter, MovingAverage, OutlierRejection};
+//!
+//! let mut filter = OutlierRejection::<5>::new(2.0).chain(MovingAverage::<4>::new());
+//! for value in [20.0, 20.2, 95.0, 20.1] {
+//!     let smoothed = filter.update(value);
+//!     assert!(smoothed < 21.0);
+//! }
+//! ```
+//!
+//! Windowed filters keep their history in a `[f32; N]` buffer, so `N` sets the capacity at
+//! compile time. A window of 0 passes the values through unchanged.
+
+/// Filter over a stream of readings
+pub trait Filter {
+    /// Feed a new reading and get the filtered value
+    fn update(&mut self, value: f32) -> f32;
+
+    /// Forget the history, the next reading starts the filter again
+    fn reset(&mut self);
+
+    /// Feed the output of this filter into `next`
+    fn chain<F: Filter>(self, next: F) -> Chain<Self, F>
+    where
+        Self: Sized,
+    {
+        Chain { first: self, next }
+    }
+}
+
+/// Two filters applied one after another, created by [`Filter::chain`]
+pub struct Chain<A: Filter, B: Filter> {
+    /// Filter applied first
+    first: A,
+    /// Filter applied to the output of `first`
+    next: B,
+}
+
+impl<A: Filter, B: Filter> Chain<A, B> {
+    /// Split the chain into its filters
+    pub fn release(self) -> (A, B) {
+        (self.first, self.next)
+    }
+}
+
+impl<A: Filter, B: Filter> Filter for Chain<A, B> {
+    fn update(&mut self, value: f32) -> f32 {
+        self.next.update(self.first.update(value))
+    }
+
+    fn reset(&mut self) {
+        self.first.reset();
+        self.next.reset();
+    }
+}
+
+/// Fixed-capacity ring buffer of the latest readings
+struct Window<const N: usize> {
+    /// Readings, valid up to `len`
+    values: [f32; N],
+    /// Index the next reading is written to
+    next: usize,
+    /// Number of valid readings
+    len: usize,
+}
+
+impl<const N: usize> Window<N> {
+    /// Create an empty window
+    const fn new() -> Self {
+        Self {
+            values: [0.0; N],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Add a reading, overwriting the oldest one when full
+    fn push(&mut self, value: f32) {
+        self.values[self.next] = value;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Valid readings, not in arrival order
+    fn values(&self) -> &[f32] {
+        &self.values[..self.len]
+    }
+
+    /// Mean of the valid readings
+    fn mean(&self) -> f32 {
+        self.values().iter().sum::<f32>() / self.len as f32
+    }
+
+    /// Median of the valid readings
+    // is_multiple_of 需要 Rust 1.87，保持对旧工具链的支持
+    #[allow(clippy::manual_is_multiple_of)]
+    fn median(&self) -> f32 {
+        // 在栈上复制一份再排序，不改变环形缓冲区的顺序
+        let mut sorted = [0.0; N];
+        let sorted = &mut sorted[..self.len];
+        sorted.copy_from_slice(self.values());
+        sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+
+        let middle = self.len / 2;
+        if self.len % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        }
+    }
+
+    /// Drop all readings
+    fn clear(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+}
+
+/// Simple moving average over the latest `N` readings
+///
+/// Until `N` readings have arrived, the average of the readings so far is returned.
+pub struct MovingAverage<const N: usize> {
+    /// Latest readings
+    window: Window<N>,
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Create a moving average filter
+    pub const fn new() -> Self {
+        Self {
+            window: Window::new(),
+        }
+    }
+}
+
+impl<const N: usize> Filter for MovingAverage<N> {
+    fn update(&mut self, value: f32) -> f32 {
+        if N == 0 {
+            return value;
+        }
+        self.window.push(value);
+        self.window.mean()
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Exponential moving average (first-order low-pass)
+pub struct Exponential {
+    /// Weight of the newest reading (0.0 ~ 1.0]
+    alpha: f32,
+    /// Filtered value, `None` before the first reading
+    state: Option<f32>,
+}
+
+impl Exponential {
+    /// Create an exponential filter
+    ///
+    /// - alpha: Weight of the newest reading, clamped to (0.0 ~ 1.0].
+    ///   Smaller values filter more noise but react slower
+    pub fn new(alpha: f32) -> Self {
+        Self {
+            alpha: alpha.clamp(f32::EPSILON, 1.0),
+            state: None,
+        }
+    }
+}
+
+impl Filter for Exponential {
+    fn update(&mut self, value: f32) -> f32 {
+        let state = match self.state {
+            // 第一个读数直接作为初始值
+            None => value,
+            Some(state) => state + self.alpha * (value - state),
+        };
+        self.state = Some(state);
+        state
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Median of the latest `N` readings
+///
+/// Removes short spikes completely while keeping edges sharp. An odd `N` is recommended,
+/// with an even number of readings the mean of the two middle readings is returned.
+pub struct Median<const N: usize> {
+    /// Latest readings
+    window: Window<N>,
+}
+
+impl<const N: usize> Default for Median<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Median<N> {
+    /// Create a median filter
+    pub const fn new() -> Self {
+        Self {
+            window: Window::new(),
+        }
+    }
+}
+
+impl<const N: usize> Filter for Median<N> {
+    fn update(&mut self, value: f32) -> f32 {
+        if N == 0 {
+            return value;
+        }
+        self.window.push(value);
+        self.window.median()
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// One-dimensional Kalman filter for a constant value with random drift
+pub struct Kalman {
+    /// Process noise variance, how much the true value drifts between readings
+    process_noise: f32,
+    /// Measurement noise variance of the sensor
+    measurement_noise: f32,
+    /// Estimate and its error variance, `None` before the first reading
+    state: Option<(f32, f32)>,
+}
+
+impl Kalman {
+    /// Create a Kalman filter
+    ///
+    /// - process_noise: Variance of the true value change between readings
+    /// - measurement_noise: Variance of the sensor noise, e.g. the squared datasheet RMS noise
+    pub fn new(process_noise: f32, measurement_noise: f32) -> Self {
+        Self {
+            process_noise,
+            measurement_noise,
+            state: None,
+        }
+    }
+
+    /// Error variance of the current estimate
+    pub fn variance(&self) -> Option<f32> {
+        self.state.map(|(_, variance)| variance)
+    }
+}
+
+impl Filter for Kalman {
+    fn update(&mut self, value: f32) -> f32 {
+        let (estimate, variance) = match self.state {
+            // 第一个读数的误差即为测量噪声
+            None => (value, self.measurement_noise),
+            Some((estimate, variance)) => {
+                // 预测
+                let variance = variance + self.process_noise;
+                // 更新
+                let gain = variance / (variance + self.measurement_noise);
+                (
+                    estimate + gain * (value - estimate),
+                    (1.0 - gain) * variance,
+                )
+            }
+        };
+        self.state = Some((estimate, variance));
+        estimate
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Limits how much the output may change per reading
+pub struct RateLimiter {
+    /// Maximum change per reading
+    max_step: f32,
+    /// Last output, `None` before the first reading
+    state: Option<f32>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter
+    ///
+    /// - max_step: Maximum change of the output per reading, in the unit of the readings
+    pub fn new(max_step: f32) -> Self {
+        Self {
+            max_step: max_step.abs(),
+            state: None,
+        }
+    }
+}
+
+impl Filter for RateLimiter {
+    fn update(&mut self, value: f32) -> f32 {
+        let output = match self.state {
+            None => value,
+            Some(last) => value.clamp(last - self.max_step, last + self.max_step),
+        };
+        self.state = Some(output);
+        output
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Outlier (spike) rejection against the median of the latest `N` readings
+///
+/// A reading further than `threshold` from the median is replaced by the median.
+/// Rejected readings still enter the window, so a real step change is accepted once it
+/// makes up the majority of the window.
+pub struct OutlierRejection<const N: usize> {
+    /// Maximum distance from the median
+    threshold: f32,
+    /// Latest readings, including rejected ones
+    window: Window<N>,
+    /// Number of rejected readings
+    rejected: u32,
+}
+
+impl<const N: usize> OutlierRejection<N> {
+    /// Create an outlier rejection filter
+    ///
+    /// - threshold: Maximum distance from the median, in the unit of the readings
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold: threshold.abs(),
+            window: Window::new(),
+            rejected: 0,
+        }
+    }
+
+    /// Number of readings rejected so far
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+}
+
+impl<const N: usize> Filter for OutlierRejection<N> {
+    fn update(&mut self, value: f32) -> f32 {
+        if N == 0 {
+            return value;
+        }
+        self.window.push(value);
+        let median = self.window.median();
+        if (value - median).abs() > self.threshold {
+            self.rejected = self.rejected.saturating_add(1);
+            median
+        } else {
+            value
+        }
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+        self.rejected = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Exponential, Filter, Kalman, Median, MovingAverage, OutlierRejection, RateLimiter,
+    };
+    use crate::mock::assert_close;
+
+    #[test]
+    fn moving_average_fills_then_slides() {
+        let mut filter = MovingAverage::<3>::new();
+        assert_eq!(filter.update(3.0), 3.0);
+        assert_eq!(filter.update(6.0), 4.5);
+        assert_eq!(filter.update(9.0), 6.0);
+        assert_eq!(filter.update(12.0), 9.0);
+
+        filter.reset();
+        assert_eq!(filter.update(1.0), 1.0);
+
+        // 窗口为0时直接透传
+        assert_eq!(MovingAverage::<0>::new().update(7.0), 7.0);
+    }
+
+    #[test]
+    fn exponential_converges() {
+        let mut filter = Exponential::new(0.5);
+        assert_eq!(filter.update(10.0), 10.0);
+        assert_eq!(filter.update(20.0), 15.0);
+        assert_eq!(filter.update(20.0), 17.5);
+    }
+
+    #[test]
+    fn median_removes_spikes() {
+        let mut filter = Median::<3>::new();
+        filter.update(1.0);
+        assert_eq!(filter.update(3.0), 2.0);
+        assert_eq!(filter.update(100.0), 3.0);
+        assert_eq!(filter.update(2.0), 3.0);
+        assert_eq!(filter.update(2.0), 2.0);
+    }
+
+    #[test]
+    fn kalman_settles_on_constant_value() {
+        let mut filter = Kalman::new(0.001, 1.0);
+        let mut estimate = 0.0;
+        for i in 0..200 {
+            // 在真实值25.0附近交替的噪声
+            let noise = if i % 2 == 0 { 0.8 } else { -0.8 };
+            estimate = filter.update(25.0 + noise);
+        }
+        assert_close(estimate, 25.0, 0.1);
+        assert!(filter.variance().unwrap() < 0.1);
+    }
+
+    #[test]
+    fn rate_limiter_limits_steps() {
+        let mut filter = RateLimiter::new(2.0);
+        assert_eq!(filter.update(0.0), 0.0);
+        assert_eq!(filter.update(10.0), 2.0);
+        assert_eq!(filter.update(10.0), 4.0);
+        assert_eq!(filter.update(3.0), 3.0);
+        assert_eq!(filter.update(-10.0), 1.0);
+    }
+
+    #[test]
+    fn outlier_rejection_accepts_real_steps() {
+        let mut filter = OutlierRejection::<5>::new(1.0);
+        for _ in 0..5 {
+            filter.update(20.0);
+        }
+        assert_eq!(filter.update(80.0), 20.0);
+        assert_eq!(filter.update(20.5), 20.5);
+        assert_eq!(filter.rejected(), 1);
+
+        // 持续的阶跃变化在占据窗口多数后被接受
+        assert_eq!(filter.update(30.0), 20.5);
+        assert_eq!(filter.update(30.0), 30.0);
+        assert_eq!(filter.rejected(), 2);
+    }
+
+    #[test]
+    fn filters_chain() {
+        let mut filter = Median::<3>::new()
+            .chain(MovingAverage::<2>::new())
+            .chain(RateLimiter::new(100.0));
+        filter.update(10.0);
+        filter.update(10.0);
+        // 尖峰先被中值滤波去除
+        assert_eq!(filter.update(1000.0), 10.0);
+        assert_eq!(filter.update(12.0), 11.0);
+
+        filter.reset();
+        assert_eq!(filter.update(4.0), 4.0);
+    }
+}
@@ -0,0 +1,340 @@
+//! Fixed-capacity history of timestamped readings
+//!
+//! [`History`] keeps the last `N` values of any driver for trend displays and min/max/mean
+//! reports. Statistics are calculated over a time window and long histories can be
+//! [downsampled](History::downsample) to fewer points, all without allocation.
+
+use core::time::Duration;
+
+use embedded_timers::{clock::Clock, instant::Instant};
+use libm::sqrtf;
+
+/// Timestamped value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point<I> {
+    /// Time the value was taken
+    pub timestamp: I,
+    /// Reading
+    pub value: f32,
+}
+
+/// Statistics over the values of a window
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statistics {
+    /// Number of values
+    pub count: usize,
+    /// Smallest value
+    pub min: f32,
+    /// Largest value
+    pub max: f32,
+    /// Arithmetic mean
+    pub mean: f32,
+    /// Population standard deviation
+    pub stddev: f32,
+    /// Least-squares trend in units per second, 0 with less than two distinct timestamps
+    pub slope: f32,
+}
+
+/// Ring buffer of the last `N` timestamped values
+pub struct History<'a, C: Clock, const N: usize> {
+    /// Stored values, `len` of them starting at `head`
+    buffer: [Option<Point<C::Instant>>; N],
+    /// Index of the oldest value
+    head: usize,
+    /// Number of stored values
+    len: usize,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, const N: usize> History<'a, C, N> {
+    /// Create an empty history
+    pub fn new(clock: &'a C) -> Self {
+        Self {
+            buffer: core::array::from_fn(|_| None),
+            head: 0,
+            len: 0,
+            clock_impl: clock,
+        }
+    }
+
+    /// Add a value taken now, the oldest value is dropped when the history is full
+    pub fn push(&mut self, value: f32) {
+        let now = self.clock_impl.now();
+        self.push_at(now, value);
+    }
+
+    /// Add a value with its own timestamp, e.g. from a [`Sample`](crate::scheduler::Sample)
+    ///
+    /// Note: Timestamps are expected in ascending order
+    pub fn push_at(&mut self, timestamp: C::Instant, value: f32) {
+        if N == 0 {
+            return;
+        }
+        let point = Point { timestamp, value };
+        if self.len < N {
+            self.buffer[(self.head + self.len) % N] = Some(point);
+            self.len += 1;
+        } else {
+            // 覆盖最旧的数据
+            self.buffer[self.head] = Some(point);
+            self.head = (self.head + 1) % N;
+        }
+    }
+
+    /// Number of stored values
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no value is stored
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the next push drops the oldest value
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Remove all values
+    pub fn clear(&mut self) {
+        self.buffer = core::array::from_fn(|_| None);
+        self.head = 0;
+        self.len = 0;
+    }
+
+    /// Newest value
+    pub fn latest(&self) -> Option<Point<C::Instant>> {
+        let index = (self.head + self.len.checked_sub(1)?) % N;
+        self.buffer[index]
+    }
+
+    /// Iterate from the oldest to the newest value
+    pub fn iter(&self) -> impl Iterator<Item = Point<C::Instant>> + '_ {
+        (0..self.len).filter_map(move |i| self.buffer[(self.head + i) % N])
+    }
+
+    /// Statistics over the values taken within `window` before now
+    ///
+    /// Pass `Duration::MAX` for all stored values. Returns `None` when the window is empty.
+    pub fn statistics(&self, window: Duration) -> Option<Statistics> {
+        let now = self.clock_impl.now();
+        let points = || {
+            self.iter()
+                .filter(move |point| now.duration_since(point.timestamp) <= window)
+        };
+        let first = points().next()?;
+
+        let mut count = 0;
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+        let mut sum_x = 0.0;
+        let mut sum_y = 0.0;
+        for point in points() {
+            count += 1;
+            min = min.min(point.value);
+            max = max.max(point.value);
+            sum_x += point
+                .timestamp
+                .duration_since(first.timestamp)
+                .as_secs_f32();
+            sum_y += point.value;
+        }
+        let mean_x = sum_x / count as f32;
+        let mean = sum_y / count as f32;
+
+        // 第二遍计算方差和最小二乘斜率，避免大数相减的精度损失
+        let mut sum_xx = 0.0;
+        let mut sum_xy = 0.0;
+        let mut sum_yy = 0.0;
+        for point in points() {
+            let dx = point
+                .timestamp
+                .duration_since(first.timestamp)
+                .as_secs_f32()
+                - mean_x;
+            let dy = point.value - mean;
+            sum_xx += dx * dx;
+            sum_xy += dx * dy;
+            sum_yy += dy * dy;
+        }
+
+        Some(Statistics {
+            count,
+            min,
+            max,
+            mean,
+            stddev: sqrtf(sum_yy / count as f32),
+            slope: if sum_xx > 0.0 { sum_xy / sum_xx } else { 0.0 },
+        })
+    }
+
+    /// Average the values over consecutive intervals, e.g. to fit a trend onto a display
+    ///
+    /// The intervals are aligned to the oldest value, the timestamp of each point is the
+    /// start of its interval. Intervals without values are skipped.
+    pub fn downsample(&self, interval: Duration) -> Downsample<'_, 'a, C, N> {
+        Downsample {
+            history: self,
+            index: 0,
+            interval,
+        }
+    }
+}
+
+/// Iterator returned by [`History::downsample`]
+pub struct Downsample<'h, 'a, C: Clock, const N: usize> {
+    /// Downsampled history
+    history: &'h History<'a, C, N>,
+    /// Position of the next value, counted from the oldest
+    index: usize,
+    /// Interval length
+    interval: Duration,
+}
+
+impl<C: Clock, const N: usize> Iterator for Downsample<'_, '_, C, N> {
+    type Item = Point<C::Instant>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let origin = self.history.iter().next()?.timestamp;
+        let mut points = self.history.iter().skip(self.index);
+        let first = points.next()?;
+
+        // 计算所在区间的起始时间
+        let start = if self.interval.is_zero() {
+            first.timestamp
+        } else {
+            let elapsed = first.timestamp.duration_since(origin);
+            let intervals = (elapsed.as_nanos() / self.interval.as_nanos()) as u32;
+            origin + self.interval * intervals
+        };
+        let end = start + self.interval;
+
+        let mut count = 1;
+        let mut sum = first.value;
+        for point in points.take_while(|point| point.timestamp < end) {
+            count += 1;
+            sum += point.value;
+        }
+        self.index += count;
+
+        Some(Point {
+            timestamp: start,
+            value: sum / count as f32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use embedded_timers::instant::Instant64;
+
+    use super::History;
+    use crate::mock::{assert_close, MockClock};
+
+    fn seconds(s: u64) -> Instant64<1_000_000> {
+        Instant64::new(s * 1_000_000)
+    }
+
+    #[test]
+    fn keeps_the_last_values() {
+        let clock = MockClock::manual();
+        let mut history = History::<_, 3>::new(&clock);
+        assert!(history.is_empty());
+        assert!(history.latest().is_none());
+
+        for value in 1..=5 {
+            history.push(value as f32);
+            clock.advance(Duration::from_secs(1));
+        }
+        assert!(history.is_full());
+        assert_eq!(history.len(), 3);
+        assert!(history.iter().map(|point| point.value).eq([3.0, 4.0, 5.0]));
+
+        let latest = history.latest().unwrap();
+        assert_eq!(latest.timestamp, seconds(4));
+        assert_eq!(latest.value, 5.0);
+
+        history.clear();
+        assert!(history.is_empty());
+        assert!(history.statistics(Duration::MAX).is_none());
+    }
+
+    #[test]
+    fn statistics_over_all_values() {
+        let clock = MockClock::manual();
+        let mut history = History::<_, 8>::new(&clock);
+        for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            history.push(value);
+            clock.advance(Duration::from_secs(1));
+        }
+
+        let stats = history.statistics(Duration::MAX).unwrap();
+        assert_eq!(stats.count, 8);
+        assert_eq!(stats.min, 2.0);
+        assert_eq!(stats.max, 9.0);
+        assert_close(stats.mean, 5.0, 1e-6);
+        assert_close(stats.stddev, 2.0, 1e-6);
+        assert_close(stats.slope, 34.0 / 42.0, 1e-6);
+    }
+
+    #[test]
+    fn statistics_within_window() {
+        let clock = MockClock::manual();
+        let mut history = History::<_, 16>::new(&clock);
+        // 每10秒一个读数, 前半段恒定, 后半段线性上升0.5/s
+        for i in 0..10 {
+            let value = if i < 5 {
+                20.0
+            } else {
+                20.0 + 5.0 * (i - 4) as f32
+            };
+            history.push(value);
+            clock.advance(Duration::from_secs(10));
+        }
+
+        // 当前时间100s, 窗口内为 t=60s ~ 90s
+        let stats = history.statistics(Duration::from_secs(40)).unwrap();
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, 30.0);
+        assert_eq!(stats.max, 45.0);
+        assert_close(stats.slope, 0.5, 1e-5);
+
+        assert!(history.statistics(Duration::from_secs(5)).is_none());
+        let single = history.statistics(Duration::from_secs(10)).unwrap();
+        assert_eq!(single.count, 1);
+        assert_eq!(single.stddev, 0.0);
+        assert_eq!(single.slope, 0.0);
+    }
+
+    #[test]
+    fn downsamples_into_intervals() {
+        let clock = MockClock::manual();
+        let mut history = History::<_, 8>::new(&clock);
+        history.push_at(seconds(10), 1.0);
+        history.push_at(seconds(11), 3.0);
+        history.push_at(seconds(14), 5.0);
+        history.push_at(seconds(15), 7.0);
+        // 20s ~ 25s 区间没有数据
+        history.push_at(seconds(27), 10.0);
+
+        let mut points = history.downsample(Duration::from_secs(5));
+        let point = points.next().unwrap();
+        assert_eq!(point.timestamp, seconds(10));
+        assert_eq!(point.value, 3.0);
+        let point = points.next().unwrap();
+        assert_eq!(point.timestamp, seconds(15));
+        assert_eq!(point.value, 7.0);
+        let point = points.next().unwrap();
+        assert_eq!(point.timestamp, seconds(25));
+        assert_eq!(point.value, 10.0);
+        assert!(points.next().is_none());
+
+        assert_eq!(history.downsample(Duration::ZERO).count(), 5);
+    }
+}
@@ -3,7 +3,25 @@
 #[cfg(feature = "std")]
 extern crate std;
 
+pub mod actuator;
+pub mod altitude;
+pub mod calibration;
+pub mod filter;
+pub mod history;
+pub mod pid;
+pub mod probe;
+pub mod psychrometrics;
+pub mod scheduler;
 mod sensor;
+#[cfg(feature = "std")]
+pub mod sim;
+#[cfg(feature = "embedded-storage")]
+pub mod storage;
+pub mod supervisor;
+pub mod threshold;
+
+#[cfg(test)]
+mod mock;
 
 #[allow(unused)]
 pub use sensor::*;
@@ -0,0 +1,163 @@
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use super::MockError;
+
+/// Scripted AHT30 I2C device
+pub struct Aht30Device {
+    /// 7bit address the device answers on
+    address: u8,
+    /// Whether the calibration bit is set
+    calibrated: bool,
+    /// Whether the initialization command sets the calibration bit
+    calibrate_on_init: bool,
+    /// Report busy in the measurement result
+    busy: bool,
+    /// Corrupt the CRC of the measurement result
+    corrupt_crc: bool,
+    /// Raw 20 bit humidity
+    humidity_raw: u32,
+    /// Raw 20 bit temperature
+    temperature_raw: u32,
+    /// Number of measurement commands received
+    measurements: u32,
+    /// Number of initialization commands received
+    inits: u32,
+}
+
+impl Aht30Device {
+    /// Create a device at the default address 0x38, powered up with the calibration bit set
+    pub fn new() -> Self {
+        Self {
+            address: 0x38,
+            calibrated: true,
+            calibrate_on_init: true,
+            busy: false,
+            corrupt_crc: false,
+            humidity_raw: 0,
+            temperature_raw: 0,
+            measurements: 0,
+            inits: 0,
+        }
+    }
+
+    /// 7bit address the device answers on
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Set the measurement in °C and %RH
+    pub fn set_measurement(&mut self, temperature: f32, humidity: f32) {
+        self.humidity_raw = (humidity / 100.0 * (1u32 << 20) as f32) as u32;
+        self.temperature_raw = ((temperature + 50.0) / 200.0 * (1u32 << 20) as f32) as u32;
+    }
+
+    /// Keep the calibration bit cleared after initialization
+    pub fn fail_calibration(&mut self) {
+        self.calibrate_on_init = false;
+        self.calibrated = false;
+    }
+
+    /// Report busy in the measurement result
+    pub fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+    }
+
+    /// Corrupt the CRC of the measurement result
+    pub fn set_corrupt_crc(&mut self, corrupt: bool) {
+        self.corrupt_crc = corrupt;
+    }
+
+    /// Number of initialization commands received
+    pub fn inits(&self) -> u32 {
+        self.inits
+    }
+
+    /// Number of measurement commands received
+    pub fn measurements(&self) -> u32 {
+        self.measurements
+    }
+
+    fn status(&self, busy: bool) -> u8 {
+        let mut status = 0b0001_0000;
+        if self.calibrated {
+            status |= 0b0000_1000;
+        }
+        if busy {
+            status |= 0b1000_0000;
+        }
+        status
+    }
+
+    fn crc8(data: &[u8]) -> u8 {
+        let mut crc = 0xFFu8;
+        for b in data {
+            crc ^= b;
+            for _ in 0..8 {
+                crc = if crc & 0x80 != 0 {
+                    (crc << 1) ^ 0x31
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+
+    fn handle_write(&mut self, data: &[u8]) {
+        match data {
+            [0xBE, 0x08, 0x00] => {
+                self.inits += 1;
+                self.calibrated = self.calibrate_on_init;
+            }
+            [0xAC, 0x33, 0x00] => self.measurements += 1,
+            _ => panic!("unexpected AHT30 command {:02X?}", data),
+        }
+    }
+
+    fn handle_read(&self, buf: &mut [u8]) {
+        if buf.len() == 1 {
+            buf[0] = self.status(false);
+            return;
+        }
+        let h = self.humidity_raw;
+        let t = self.temperature_raw;
+        let mut data = [
+            self.status(self.busy),
+            (h >> 12) as u8,
+            (h >> 4) as u8,
+            (((h & 0x0F) << 4) | ((t >> 16) & 0x0F)) as u8,
+            (t >> 8) as u8,
+            t as u8,
+            0,
+        ];
+        data[6] = Self::crc8(&data[..6]);
+        if self.corrupt_crc {
+            data[6] ^= 0xFF;
+        }
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+    }
+}
+
+impl ErrorType for Aht30Device {
+    type Error = MockError;
+}
+
+impl I2c<SevenBitAddress> for Aht30Device {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if address != self.address {
+            return Err(MockError::Nack);
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => self.handle_write(data),
+                Operation::Read(buf) => self.handle_read(buf),
+            }
+        }
+        Ok(())
+    }
+}
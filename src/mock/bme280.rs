@@ -0,0 +1,169 @@
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use super::MockError;
+
+/// Calibration used by [`Bme280Device::new`]
+///
+/// Temperature and pressure coefficients are the example values from the Bosch datasheet.
+/// The datasheet has no humidity example, the humidity coefficients are the made up values
+/// of `sim::CALIBRATION`.
+pub const CALIBRATION: Calibration = Calibration {
+    t: (27504, 26435, -1000),
+    p: [36477, -10685, 3024, 2855, 140, -7, 15500, -14600, 6000],
+    h: (75, 362, 0, 313, 50, 30),
+};
+
+/// BME280 calibration coefficients as stored in the NVM
+pub struct Calibration {
+    /// dig_T1 ~ dig_T3
+    pub t: (u16, i16, i16),
+    /// dig_P1 ~ dig_P9 (dig_P1 is unsigned)
+    pub p: [i32; 9],
+    /// dig_H1 ~ dig_H6
+    pub h: (u8, i16, u8, i16, i16, i8),
+}
+
+/// Scripted BME280 I2C device backed by a register map
+pub struct Bme280Device {
+    /// 7bit address the device answers on
+    address: u8,
+    /// Register map
+    registers: [u8; 256],
+    /// Register pointer
+    pointer: u8,
+    /// Number of soft resets received
+    resets: u32,
+}
+
+impl Bme280Device {
+    /// Create a device at the default address 0x76 with [`CALIBRATION`]
+    pub fn new() -> Self {
+        let mut this = Self {
+            address: 0x76,
+            registers: [0; 256],
+            pointer: 0,
+            resets: 0,
+        };
+        this.registers[0xD0] = 0x60;
+        this.set_calibration(&CALIBRATION);
+        this
+    }
+
+    /// 7bit address the device answers on
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Change the address the device answers on
+    pub fn set_address(&mut self, address: u8) {
+        self.address = address;
+    }
+
+    /// Set the chip ID register (0xD0)
+    pub fn set_chip_id(&mut self, id: u8) {
+        self.registers[0xD0] = id;
+    }
+
+    /// Write the calibration coefficients into the NVM registers
+    pub fn set_calibration(&mut self, calib: &Calibration) {
+        let r = &mut self.registers;
+        r[0x88..0x8A].copy_from_slice(&calib.t.0.to_le_bytes());
+        r[0x8A..0x8C].copy_from_slice(&calib.t.1.to_le_bytes());
+        r[0x8C..0x8E].copy_from_slice(&calib.t.2.to_le_bytes());
+        r[0x8E..0x90].copy_from_slice(&(calib.p[0] as u16).to_le_bytes());
+        for (i, p) in calib.p.iter().enumerate().skip(1) {
+            let addr = 0x8E + i * 2;
+            r[addr..addr + 2].copy_from_slice(&(*p as i16).to_le_bytes());
+        }
+        let (h1, h2, h3, h4, h5, h6) = calib.h;
+        r[0xA1] = h1;
+        r[0xE1..0xE3].copy_from_slice(&h2.to_le_bytes());
+        r[0xE3] = h3;
+        r[0xE4] = (h4 >> 4) as u8;
+        r[0xE5] = ((h4 & 0x0F) as u8) | (((h5 & 0x0F) as u8) << 4);
+        r[0xE6] = (h5 >> 4) as u8;
+        r[0xE7] = h6 as u8;
+    }
+
+    /// Set the raw ADC values in the data registers
+    pub fn set_raw(&mut self, adc_p: u32, adc_t: u32, adc_h: u16) {
+        let r = &mut self.registers;
+        r[0xF7] = (adc_p >> 12) as u8;
+        r[0xF8] = (adc_p >> 4) as u8;
+        r[0xF9] = ((adc_p & 0x0F) << 4) as u8;
+        r[0xFA] = (adc_t >> 12) as u8;
+        r[0xFB] = (adc_t >> 4) as u8;
+        r[0xFC] = ((adc_t & 0x0F) << 4) as u8;
+        r[0xFD] = (adc_h >> 8) as u8;
+        r[0xFE] = adc_h as u8;
+    }
+
+    /// Set the status register (0xF3)
+    pub fn set_status(&mut self, status: u8) {
+        self.registers[0xF3] = status;
+    }
+
+    /// Read a register
+    pub fn register(&self, reg: u8) -> u8 {
+        self.registers[reg as usize]
+    }
+
+    /// Number of soft resets received
+    pub fn resets(&self) -> u32 {
+        self.resets
+    }
+
+    fn handle_write(&mut self, data: &[u8]) {
+        let Some((reg, values)) = data.split_first() else {
+            return;
+        };
+        self.pointer = *reg;
+        // BME280的写操作为“寄存器地址 + 数据”成对出现
+        let mut reg = *reg;
+        for (i, value) in values.iter().enumerate() {
+            if i % 2 == 1 {
+                reg = *value;
+                continue;
+            }
+            if reg == 0xE0 && *value == 0xB6 {
+                self.resets += 1;
+                // 软重置后控制寄存器恢复为上电值 (睡眠模式)
+                for reg in [0xF2, 0xF4, 0xF5] {
+                    self.registers[reg] = 0x00;
+                }
+            } else {
+                self.registers[reg as usize] = *value;
+            }
+        }
+    }
+
+    fn handle_read(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b = self.registers[self.pointer as usize];
+            self.pointer = self.pointer.wrapping_add(1);
+        }
+    }
+}
+
+impl ErrorType for Bme280Device {
+    type Error = MockError;
+}
+
+impl I2c<SevenBitAddress> for Bme280Device {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if address != self.address {
+            return Err(MockError::Nack);
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => self.handle_write(data),
+                Operation::Read(buf) => self.handle_read(buf),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use super::{Aht30Device, Bme280Device, MockError};
+
+/// I2C bus with an optional AHT30 and up to two BME280, routed by address
+pub struct MockBus {
+    /// AHT30 on the bus
+    pub aht30: Option<Aht30Device>,
+    /// BME280 devices on the bus
+    pub bme280: [Option<Bme280Device>; 2],
+    /// Fail every transaction with an I/O error
+    pub fail: bool,
+}
+
+impl MockBus {
+    /// Create a bus without devices
+    pub fn new() -> Self {
+        Self {
+            aht30: None,
+            bme280: [None, None],
+            fail: false,
+        }
+    }
+}
+
+impl ErrorType for MockBus {
+    type Error = MockError;
+}
+
+impl I2c<SevenBitAddress> for MockBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if self.fail {
+            return Err(MockError::Io);
+        }
+        if let Some(device) = self.aht30.as_mut().filter(|d| d.address() == address) {
+            return device.transaction(address, operations);
+        }
+        for device in self.bme280.iter_mut().flatten() {
+            if device.address() == address {
+                return device.transaction(address, operations);
+            }
+        }
+        Err(MockError::Nack)
+    }
+}
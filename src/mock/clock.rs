@@ -0,0 +1,52 @@
+use core::{cell::Cell, time::Duration};
+
+use embedded_timers::{clock::Clock, instant::Instant64};
+
+/// Mock microsecond clock
+///
+/// The blocking delays of the drivers spin on [`Clock::now`], so by default every call
+/// advances the time by 1us. A manual clock only moves when [`advance`](Self::advance) is called.
+pub struct MockClock {
+    /// Current time in microseconds
+    now: Cell<u64>,
+    /// Microseconds added on every call to `now`
+    step: u64,
+}
+
+impl MockClock {
+    /// Create a clock advancing 1us on every call to `now`
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(0),
+            step: 1,
+        }
+    }
+
+    /// Create a clock only advanced by [`advance`](Self::advance)
+    pub fn manual() -> Self {
+        Self {
+            now: Cell::new(0),
+            step: 0,
+        }
+    }
+
+    /// Advance the time
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration.as_micros() as u64);
+    }
+
+    /// Current time in microseconds, without advancing the clock
+    pub fn micros(&self) -> u64 {
+        self.now.get()
+    }
+}
+
+impl Clock for MockClock {
+    type Instant = Instant64<1_000_000>;
+
+    fn now(&self) -> Self::Instant {
+        let now = self.now.get();
+        self.now.set(now + self.step);
+        Instant64::new(now)
+    }
+}
@@ -0,0 +1,125 @@
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+use super::{MockClock, MockError};
+
+/// Simulated DHT11 1-Wire data line
+///
+/// When the host holds the line low for at least 18ms and releases it, the sensor answers with
+/// the response signal followed by the 40 data bits, timed against the [`MockClock`].
+pub struct Dht11Device<'a> {
+    /// Clock the waveform is timed against
+    clock: &'a MockClock,
+    /// Data bytes sent by the sensor (humidity, humidity decimal, temperature, temperature decimal, checksum)
+    data: [u8; 5],
+    /// Whether the sensor answers the start signal
+    respond: bool,
+    /// Time the host pulled the line low
+    host_low_since: Option<u64>,
+    /// Time the sensor started answering
+    response_start: Option<u64>,
+}
+
+impl<'a> Dht11Device<'a> {
+    /// Create a sensor reporting 0°C and 0%RH
+    pub fn new(clock: &'a MockClock) -> Self {
+        let mut this = Self {
+            clock,
+            data: [0; 5],
+            respond: true,
+            host_low_since: None,
+            response_start: None,
+        };
+        this.set_measurement(0, 0, 0);
+        this
+    }
+
+    /// Set the measurement with a correct checksum
+    ///
+    /// - temperature_decimal: Tenths of °C, bit 7 marks a negative temperature
+    pub fn set_measurement(&mut self, humidity: u8, temperature: u8, temperature_decimal: u8) {
+        self.data = [humidity, 0, temperature, temperature_decimal, 0];
+        self.data[4] = self.data[..4]
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_add(*b));
+    }
+
+    /// Overwrite the checksum byte
+    pub fn set_checksum(&mut self, checksum: u8) {
+        self.data[4] = checksum;
+    }
+
+    /// Do not answer the start signal
+    pub fn set_respond(&mut self, respond: bool) {
+        self.respond = respond;
+    }
+
+    /// Line level driven by the sensor at the given time
+    fn sensor_level(&self, now: u64) -> bool {
+        let Some(start) = self.response_start else {
+            return true;
+        };
+        // 响应信号：83us低电平 + 87us高电平
+        let mut t = now - start;
+        if t < 83 {
+            return false;
+        }
+        if t < 170 {
+            return true;
+        }
+        t -= 170;
+        // 数据位：54us低电平 + 26us(0)或70us(1)高电平
+        for byte in self.data {
+            for bit in (0..8).rev() {
+                let high = if byte & (1 << bit) != 0 { 70 } else { 26 };
+                if t < 54 {
+                    return false;
+                }
+                if t < 54 + high {
+                    return true;
+                }
+                t -= 54 + high;
+            }
+        }
+        // 结束信号：54us低电平后释放总线
+        t >= 54
+    }
+
+    fn level(&self) -> bool {
+        if self.host_low_since.is_some() {
+            return false;
+        }
+        self.sensor_level(self.clock.micros())
+    }
+}
+
+impl ErrorType for Dht11Device<'_> {
+    type Error = MockError;
+}
+
+impl OutputPin for Dht11Device<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.host_low_since = Some(self.clock.micros());
+        self.response_start = None;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        // 主机释放总线，起始信号足够长时传感器开始响应
+        if let Some(since) = self.host_low_since.take() {
+            if self.respond && self.clock.micros() - since >= 18_000 {
+                self.response_start = Some(self.clock.micros());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InputPin for Dht11Device<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.level())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.level())
+    }
+}
@@ -0,0 +1,134 @@
+use embedded_storage::{
+    nor_flash::{
+        check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+    },
+    ReadStorage, Storage,
+};
+
+use super::MockError;
+
+/// In-memory NOR flash of 1KiB
+///
+/// Reads and writes are aligned to 4 bytes, erase sectors are 256 bytes. Like real NOR flash
+/// a write can only clear bits, so overwriting requires an erase first.
+pub struct MockFlash {
+    /// Flash contents
+    data: [u8; 1024],
+    /// Number of erase calls
+    erases: u32,
+    /// Fail every read
+    fail_reads: bool,
+}
+
+impl MockFlash {
+    /// Create an erased flash
+    pub fn new() -> Self {
+        Self {
+            data: [0xFF; 1024],
+            erases: 0,
+            fail_reads: false,
+        }
+    }
+
+    /// Number of erase calls
+    pub fn erases(&self) -> u32 {
+        self.erases
+    }
+
+    /// Inject a read failure
+    pub fn set_fail_reads(&mut self, fail: bool) {
+        self.fail_reads = fail;
+    }
+}
+
+impl ErrorType for MockFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for MockFlash {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        if self.fail_reads {
+            return Err(NorFlashErrorKind::Other);
+        }
+        let start = offset as usize;
+        bytes.copy_from_slice(&self.data[start..start + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for MockFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = 256;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        self.data[from as usize..to as usize].fill(0xFF);
+        self.erases += 1;
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        let start = offset as usize;
+        for (cell, byte) in self.data[start..start + bytes.len()].iter_mut().zip(bytes) {
+            // 写入只能把位从1变为0
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
+
+/// In-memory byte addressable EEPROM of `N` bytes, erased to 0xFF
+pub struct MockEeprom<const N: usize> {
+    /// EEPROM contents
+    data: [u8; N],
+}
+
+impl<const N: usize> MockEeprom<N> {
+    /// Create an erased EEPROM
+    pub fn new() -> Self {
+        Self { data: [0xFF; N] }
+    }
+
+    /// EEPROM contents
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl<const N: usize> ReadStorage for MockEeprom<N> {
+    type Error = MockError;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let start = offset as usize;
+        let data = self
+            .data
+            .get(start..start + bytes.len())
+            .ok_or(MockError::Io)?;
+        bytes.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> Storage for MockEeprom<N> {
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let start = offset as usize;
+        let data = self
+            .data
+            .get_mut(start..start + bytes.len())
+            .ok_or(MockError::Io)?;
+        data.copy_from_slice(bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use core::cell::Cell;
+
+use embedded_hal::{
+    digital::{ErrorType as DigitalErrorType, InputPin, OutputPin},
+    pwm::{ErrorType as PwmErrorType, SetDutyCycle},
+};
+
+use super::MockError;
+
+/// Observable state of a [`MockOutputPin`]
+pub struct OutputState {
+    /// Current level
+    high: Cell<bool>,
+    /// Number of times the pin was set
+    writes: Cell<u32>,
+    /// Fail every write
+    fail: Cell<bool>,
+}
+
+impl OutputState {
+    /// Create the state of a pin starting low
+    pub fn new() -> Self {
+        Self {
+            high: Cell::new(false),
+            writes: Cell::new(0),
+            fail: Cell::new(false),
+        }
+    }
+
+    /// Whether the pin is high
+    pub fn is_high(&self) -> bool {
+        self.high.get()
+    }
+
+    /// Number of times the pin was set
+    pub fn writes(&self) -> u32 {
+        self.writes.get()
+    }
+
+    /// Inject a write failure
+    pub fn set_fail(&self, fail: bool) {
+        self.fail.set(fail);
+    }
+
+    /// Create a pin bound to this state
+    pub fn pin(&self) -> MockOutputPin<'_> {
+        MockOutputPin(self)
+    }
+}
+
+/// Mock GPIO output pin
+pub struct MockOutputPin<'a>(&'a OutputState);
+
+impl DigitalErrorType for MockOutputPin<'_> {
+    type Error = MockError;
+}
+
+impl OutputPin for MockOutputPin<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true)
+    }
+}
+
+impl MockOutputPin<'_> {
+    fn set(&mut self, high: bool) -> Result<(), MockError> {
+        if self.0.fail.get() {
+            return Err(MockError::Io);
+        }
+        self.0.high.set(high);
+        self.0.writes.set(self.0.writes.get() + 1);
+        Ok(())
+    }
+}
+
+/// Observable state of a [`MockInputPin`]
+///
+/// The pin returns the scripted levels one after another and then repeats the last one.
+pub struct InputState {
+    /// Scripted levels
+    levels: Cell<&'static [bool]>,
+    /// Index of the next level
+    index: Cell<usize>,
+    /// Fail every read
+    fail: Cell<bool>,
+}
+
+impl InputState {
+    /// Create the state of a pin returning the scripted levels
+    pub fn new(levels: &'static [bool]) -> Self {
+        Self {
+            levels: Cell::new(levels),
+            index: Cell::new(0),
+            fail: Cell::new(false),
+        }
+    }
+
+    /// Replace the scripted levels
+    pub fn script(&self, levels: &'static [bool]) {
+        self.levels.set(levels);
+        self.index.set(0);
+    }
+
+    /// Inject a read failure
+    pub fn set_fail(&self, fail: bool) {
+        self.fail.set(fail);
+    }
+
+    /// Create a pin bound to this state
+    pub fn pin(&self) -> MockInputPin<'_> {
+        MockInputPin(self)
+    }
+}
+
+/// Mock GPIO input pin
+pub struct MockInputPin<'a>(&'a InputState);
+
+impl DigitalErrorType for MockInputPin<'_> {
+    type Error = MockError;
+}
+
+impl InputPin for MockInputPin<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        if self.0.fail.get() {
+            return Err(MockError::Io);
+        }
+        let levels = self.0.levels.get();
+        let index = self.0.index.get();
+        self.0.index.set(index + 1);
+        Ok(levels[index.min(levels.len() - 1)])
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// Observable state of a [`MockPwm`]
+pub struct PwmState {
+    /// Maximum duty cycle
+    max: u16,
+    /// Current duty cycle
+    duty: Cell<u16>,
+    /// Fail every write
+    fail: Cell<bool>,
+}
+
+impl PwmState {
+    /// Create the state of a PWM channel starting at 0% duty cycle
+    pub fn new(max: u16) -> Self {
+        Self {
+            max,
+            duty: Cell::new(0),
+            fail: Cell::new(false),
+        }
+    }
+
+    /// Current duty cycle
+    pub fn duty(&self) -> u16 {
+        self.duty.get()
+    }
+
+    /// Inject a write failure
+    pub fn set_fail(&self, fail: bool) {
+        self.fail.set(fail);
+    }
+
+    /// Create a PWM channel bound to this state
+    pub fn pwm(&self) -> MockPwm<'_> {
+        MockPwm(self)
+    }
+}
+
+/// Mock PWM channel
+pub struct MockPwm<'a>(&'a PwmState);
+
+impl PwmErrorType for MockPwm<'_> {
+    type Error = MockError;
+}
+
+impl SetDutyCycle for MockPwm<'_> {
+    fn max_duty_cycle(&self) -> u16 {
+        self.0.max
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        if self.0.fail.get() {
+            return Err(MockError::Io);
+        }
+        assert!(duty <= self.0.max, "duty cycle out of range");
+        self.0.duty.set(duty);
+        Ok(())
+    }
+}
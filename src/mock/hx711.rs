@@ -0,0 +1,148 @@
+use core::cell::Cell;
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+use super::{MockClock, MockError};
+
+/// Simulated HX711 24 bit ADC
+///
+/// Use [`clock_pin`](Self::clock_pin) and [`data_pin`](Self::data_pin) to create the pins
+/// for the driver. Data bits are shifted out MSB first on every rising edge of the clock pin.
+pub struct Hx711Device<'a> {
+    /// Clock used to detect the power down condition
+    clock: &'a MockClock,
+    /// Conversion result
+    value: Cell<i32>,
+    /// Whether a conversion result is ready
+    ready: Cell<bool>,
+    /// Level of the clock pin
+    clock_high: Cell<bool>,
+    /// Time the clock pin went high
+    high_since: Cell<u64>,
+    /// Clock pulses since the last ready check
+    pulses: Cell<u8>,
+    /// Clock pulses of the last complete read
+    last_pulses: Cell<u8>,
+    /// Number of power down cycles
+    power_downs: Cell<u32>,
+}
+
+impl<'a> Hx711Device<'a> {
+    /// Create a device with a ready conversion result of 0
+    pub fn new(clock: &'a MockClock) -> Self {
+        Self {
+            clock,
+            value: Cell::new(0),
+            ready: Cell::new(true),
+            clock_high: Cell::new(false),
+            high_since: Cell::new(0),
+            pulses: Cell::new(0),
+            last_pulses: Cell::new(0),
+            power_downs: Cell::new(0),
+        }
+    }
+
+    /// Set the conversion result (24 bit two's complement)
+    pub fn set_value(&self, value: i32) {
+        self.value.set(value);
+    }
+
+    /// Set whether a conversion result is ready
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.set(ready);
+    }
+
+    /// Clock pulses of the last complete read (24 data pulses + 1~3 gain pulses)
+    pub fn last_pulses(&self) -> u8 {
+        self.finish_read();
+        self.last_pulses.get()
+    }
+
+    /// Number of power down cycles
+    pub fn power_downs(&self) -> u32 {
+        self.power_downs.get()
+    }
+
+    /// Create the clock (PD_SCK) pin
+    pub fn clock_pin(&self) -> Hx711ClockPin<'_, 'a> {
+        Hx711ClockPin(self)
+    }
+
+    /// Create the data (DOUT) pin
+    pub fn data_pin(&self) -> Hx711DataPin<'_, 'a> {
+        Hx711DataPin(self)
+    }
+
+    fn finish_read(&self) {
+        if self.pulses.get() > 0 && !self.clock_high.get() {
+            self.last_pulses.set(self.pulses.get());
+            self.pulses.set(0);
+        }
+    }
+
+    fn data_level(&self) -> bool {
+        let pulses = self.pulses.get();
+        match pulses {
+            // 空闲时，数据就绪输出低电平
+            0 => !self.ready.get(),
+            // 每个上升沿输出一位数据，高位在前
+            1..=24 => self.value.get() & (1 << (24 - pulses)) != 0,
+            // 第25个脉冲后输出高电平，直到下一次转换完成
+            _ => true,
+        }
+    }
+}
+
+/// HX711 clock (PD_SCK) pin
+pub struct Hx711ClockPin<'d, 'a>(&'d Hx711Device<'a>);
+
+impl ErrorType for Hx711ClockPin<'_, '_> {
+    type Error = MockError;
+}
+
+impl OutputPin for Hx711ClockPin<'_, '_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let device = self.0;
+        if device.clock_high.get() {
+            // 时钟高电平保持60us以上则进入断电模式，再次拉低后重新上电
+            if device.clock.micros() - device.high_since.get() >= 60 {
+                device.power_downs.set(device.power_downs.get() + 1);
+                device.pulses.set(0);
+            }
+        }
+        device.clock_high.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let device = self.0;
+        if !device.clock_high.get() {
+            device.clock_high.set(true);
+            device.high_since.set(device.clock.micros());
+            device.pulses.set(device.pulses.get().saturating_add(1));
+        }
+        Ok(())
+    }
+}
+
+/// HX711 data (DOUT) pin
+pub struct Hx711DataPin<'d, 'a>(&'d Hx711Device<'a>);
+
+impl ErrorType for Hx711DataPin<'_, '_> {
+    type Error = MockError;
+}
+
+impl InputPin for Hx711DataPin<'_, '_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let device = self.0;
+        // 时钟为低电平时读取数据引脚，表示上一次读取已经结束
+        if !device.clock_high.get() && device.pulses.get() > 24 {
+            device.finish_read();
+        }
+        Ok(device.data_level())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
@@ -0,0 +1,69 @@
+//! Mock hardware used by the driver test suites
+//!
+//! Everything in here only exists for `cargo test`, it models just enough of each
+//! peripheral for the drivers to run without real hardware.
+
+mod aht30;
+mod bme280;
+mod bus;
+mod clock;
+mod dht11;
+#[cfg(feature = "embedded-storage")]
+mod flash;
+mod gpio;
+mod hx711;
+
+pub use aht30::Aht30Device;
+pub use bme280::{
+    Bme280Device, Calibration as Bme280Calibration, CALIBRATION as BME280_CALIBRATION,
+};
+pub use bus::MockBus;
+pub use clock::MockClock;
+pub use dht11::Dht11Device;
+#[cfg(feature = "embedded-storage")]
+pub use flash::{MockEeprom, MockFlash};
+pub use gpio::{InputState, OutputState, PwmState};
+pub use hx711::Hx711Device;
+
+/// Error returned by the mock peripherals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockError {
+    /// The addressed I2C device did not acknowledge
+    Nack,
+    /// Injected I/O failure
+    Io,
+}
+
+impl embedded_hal::digital::Error for MockError {
+    fn kind(&self) -> embedded_hal::digital::ErrorKind {
+        embedded_hal::digital::ErrorKind::Other
+    }
+}
+
+impl embedded_hal::pwm::Error for MockError {
+    fn kind(&self) -> embedded_hal::pwm::ErrorKind {
+        embedded_hal::pwm::ErrorKind::Other
+    }
+}
+
+impl embedded_hal::i2c::Error for MockError {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+        match self {
+            Self::Nack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Self::Io => ErrorKind::Other,
+        }
+    }
+}
+
+/// Assert that a floating point result is within `tolerance` of the expected value
+#[track_caller]
+pub fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() < tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
@@ -0,0 +1,509 @@
+//! PID controller for PWM and on/off actuators
+//!
+//! [`Pid`] is the bare control algorithm. [`Controller`] feeds its output into any [`Duty`]
+//! actuator, e.g. a heater on `dc_relay::PwmDriver`. Plain relays are driven through
+//! [`TimeProportioning`], which turns the duty cycle into on/off time within a fixed window.
+
+use core::time::Duration;
+
+use embedded_timers::{clock::Clock, instant::Instant};
+
+use crate::actuator::{Duty, Switch};
+
+/// PID controller config
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// Proportional gain
+    pub kp: f32,
+    /// Integral gain (1/s)
+    pub ki: f32,
+    /// Derivative gain (s)
+    pub kd: f32,
+    /// Lower output limit
+    pub output_min: f32,
+    /// Upper output limit
+    pub output_max: f32,
+    /// Minimum time between two output calculations
+    pub sample_time: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            kp: 1.0,
+            ki: 0.0,
+            kd: 0.0,
+            output_min: 0.0,
+            output_max: 1.0,
+            sample_time: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Config {
+    /// Create a config with the output range 0.0 ~ 1.0, matching a duty cycle
+    pub fn new(kp: f32, ki: f32, kd: f32, sample_time: Duration) -> Self {
+        Self {
+            kp,
+            ki,
+            kd,
+            sample_time,
+            ..Self::default()
+        }
+    }
+
+    /// Clamp a value into the output range
+    fn clamp(&self, value: f32) -> f32 {
+        value.max(self.output_min).min(self.output_max)
+    }
+}
+
+/// PID controller mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    /// The output is calculated from the measurements
+    Auto,
+    /// The output is set by [`Pid::set_manual`]
+    Manual,
+}
+
+/// PID control algorithm
+///
+/// - Anti-windup: the integral term is clamped to the output range
+/// - Derivative on measurement: setpoint changes cause no derivative kick
+/// - Bumpless transfer: switching from manual to auto continues from the manual output
+pub struct Pid<'a, C: Clock> {
+    /// Controller config
+    config: Config,
+    /// Target value
+    setpoint: f32,
+    /// Controller mode
+    mode: Mode,
+    /// Integral term
+    integral: f32,
+    /// Previous measurement
+    last_measurement: Option<f32>,
+    /// Time of the previous output calculation
+    last_time: Option<C::Instant>,
+    /// Latest output
+    output: f32,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock> Pid<'a, C> {
+    /// Create a PID controller in auto mode
+    pub fn new(clock: &'a C, config: Config, setpoint: f32) -> Self {
+        Self {
+            config,
+            setpoint,
+            mode: Mode::Auto,
+            integral: 0.0,
+            last_measurement: None,
+            last_time: None,
+            output: config.clamp(0.0),
+            clock_impl: clock,
+        }
+    }
+
+    /// Feed a new measurement
+    ///
+    /// Returns the new output once the sample time has elapsed since the previous calculation,
+    /// otherwise `None`. The actual elapsed time is used for the integral and derivative terms.
+    pub fn update(&mut self, measurement: f32) -> Option<f32> {
+        let now = self.clock_impl.now();
+        let dt = match self.last_time {
+            None => None,
+            Some(last) => {
+                let elapsed = now.duration_since(last);
+                if elapsed < self.config.sample_time || elapsed.is_zero() {
+                    return None;
+                }
+                Some(elapsed.as_secs_f32())
+            }
+        };
+        self.last_time = Some(now);
+        let last_measurement = self.last_measurement.replace(measurement);
+
+        // 手动模式下只跟踪测量值，以便无扰切换
+        if self.mode == Mode::Manual {
+            return Some(self.output);
+        }
+
+        let error = self.setpoint - measurement;
+        let mut derivative = 0.0;
+        if let Some(dt) = dt {
+            // 积分项限制在输出范围内，防止积分饱和
+            self.integral = self
+                .config
+                .clamp(self.integral + self.config.ki * error * dt);
+            // 对测量值求微分，避免设定值突变引起的微分冲击
+            if let Some(last) = last_measurement {
+                derivative = -self.config.kd * (measurement - last) / dt;
+            }
+        }
+
+        self.output = self
+            .config
+            .clamp(self.config.kp * error + self.integral + derivative);
+        Some(self.output)
+    }
+
+    /// Switch to manual mode with a fixed output, or change the manual output
+    pub fn set_manual(&mut self, output: f32) {
+        self.mode = Mode::Manual;
+        self.output = self.config.clamp(output);
+    }
+
+    /// Switch to auto mode
+    ///
+    /// The integral term is initialized from the current output, so the output does not jump
+    pub fn set_auto(&mut self) {
+        if self.mode == Mode::Manual {
+            self.integral = self.output;
+            self.mode = Mode::Auto;
+        }
+    }
+
+    /// Get the controller mode
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Get the latest output
+    pub fn output(&self) -> f32 {
+        self.output
+    }
+
+    /// Get the target value
+    pub fn setpoint(&self) -> f32 {
+        self.setpoint
+    }
+
+    /// Change the target value
+    pub fn set_setpoint(&mut self, setpoint: f32) {
+        self.setpoint = setpoint;
+    }
+
+    /// Get the controller config
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Change the controller config, the integral term is clamped to the new output range
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+        self.integral = config.clamp(self.integral);
+        self.output = config.clamp(self.output);
+    }
+
+    /// Clear the integral term and the measurement history
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_measurement = None;
+        self.last_time = None;
+        self.output = self.config.clamp(0.0);
+    }
+}
+
+/// PID controller driving a duty cycle actuator
+///
+/// The output range of the [`Config`] is mapped to 0% ~ 100% duty cycle.
+pub struct Controller<'a, C: Clock, D: Duty> {
+    /// PID algorithm
+    pid: Pid<'a, C>,
+    /// Driven actuator
+    actuator: D,
+}
+
+impl<'a, C: Clock, D: Duty> Controller<'a, C, D> {
+    /// Create a PID controller
+    ///
+    /// Note: The actuator is switched off when the controller is created
+    pub fn new(
+        clock: &'a C,
+        mut actuator: D,
+        config: Config,
+        setpoint: f32,
+    ) -> Result<Self, D::Error> {
+        // 确保执行器处于关闭状态
+        actuator.set_duty(0.0)?;
+        // OK
+        Ok(Self {
+            pid: Pid::new(clock, config, setpoint),
+            actuator,
+        })
+    }
+
+    /// Feed a new measurement, call it at least as often as the sample time
+    ///
+    /// Returns the new output when it has been recalculated, see [`Pid::update`]
+    pub fn update(&mut self, measurement: f32) -> Result<Option<f32>, D::Error> {
+        let output = self.pid.update(measurement);
+        if let Some(output) = output {
+            let config = self.pid.config();
+            let span = config.output_max - config.output_min;
+            let duty = if span > 0.0 {
+                (output - config.output_min) / span
+            } else {
+                0.0
+            };
+            self.actuator.set_duty(duty)?;
+        }
+        self.actuator.update()?;
+        Ok(output)
+    }
+
+    /// Get the PID algorithm
+    pub fn pid(&self) -> &Pid<'a, C> {
+        &self.pid
+    }
+
+    /// Get the PID algorithm, e.g. to change the setpoint or the mode
+    pub fn pid_mut(&mut self) -> &mut Pid<'a, C> {
+        &mut self.pid
+    }
+
+    /// Release the actuator
+    pub fn release(self) -> D {
+        self.actuator
+    }
+}
+
+/// Time-proportioning output for on/off actuators
+///
+/// The duty cycle is turned into the on-time within a fixed window, e.g. 30% of a 10s window
+/// switches the relay on for 3s and off for 7s. Call [`update`](Duty::update) periodically,
+/// the switching resolution is the update interval.
+pub struct TimeProportioning<'a, C: Clock, S: Switch> {
+    /// Driven actuator
+    switch: S,
+    /// Window length
+    period: Duration,
+    /// Duty cycle (0.0 ~ 1.0)
+    duty: f32,
+    /// Start time of the current window
+    window_start: Option<C::Instant>,
+    /// State last requested from the actuator, which may defer the change
+    requested: bool,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, S: Switch> TimeProportioning<'a, C, S> {
+    /// Create a time-proportioning output
+    ///
+    /// - period: Window length, relays typically use a few seconds to a few minutes
+    ///
+    /// Note: The actuator is switched off when the output is created
+    pub fn new(clock: &'a C, mut switch: S, period: Duration) -> Result<Self, S::Error> {
+        switch.switch(false)?;
+        // OK
+        Ok(Self {
+            switch,
+            period,
+            duty: 0.0,
+            window_start: None,
+            requested: false,
+            clock_impl: clock,
+        })
+    }
+
+    /// Get the duty cycle
+    pub fn duty(&self) -> f32 {
+        self.duty
+    }
+
+    /// Get the actuator state
+    pub fn is_on(&self) -> bool {
+        self.switch.is_on()
+    }
+
+    /// Release the actuator
+    pub fn release(self) -> S {
+        self.switch
+    }
+}
+
+impl<C: Clock, S: Switch> Duty for TimeProportioning<'_, C, S> {
+    type Error = S::Error;
+
+    fn set_duty(&mut self, duty: f32) -> Result<(), Self::Error> {
+        self.duty = if duty.is_nan() {
+            0.0
+        } else {
+            duty.clamp(0.0, 1.0)
+        };
+        self.update()
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        self.switch.update()?;
+        let now = self.clock_impl.now();
+        let target = if self.period.is_zero() {
+            self.duty > 0.0
+        } else {
+            let start = *self.window_start.get_or_insert(now);
+            let mut elapsed = now.duration_since(start);
+            if elapsed >= self.period {
+                // 跳过已经结束的窗口，新窗口与原来的窗口对齐
+                let windows = (elapsed.as_nanos() / self.period.as_nanos()) as u32;
+                self.window_start = Some(start + self.period * windows);
+                elapsed -= self.period * windows;
+            }
+            elapsed < self.period.mul_f32(self.duty)
+        };
+
+        if target != self.requested {
+            self.switch.switch(target)?;
+            self.requested = target;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use embedded_hal::digital::PinState;
+
+    use super::{Config, Controller, Mode, Pid, TimeProportioning};
+    use crate::{
+        actuator::Duty,
+        dc_relay,
+        mock::{assert_close, MockClock, OutputState, PwmState},
+    };
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn proportional_output() {
+        let clock = MockClock::manual();
+        let mut pid = Pid::new(&clock, Config::new(0.1, 0.0, 0.0, SECOND), 20.0);
+
+        assert_close(pid.update(15.0).unwrap(), 0.5, 1e-6);
+        // 采样时间未到不计算
+        clock.advance(Duration::from_millis(500));
+        assert!(pid.update(10.0).is_none());
+        clock.advance(Duration::from_millis(500));
+        assert_close(pid.update(10.0).unwrap(), 1.0, 1e-6);
+        clock.advance(SECOND);
+        assert_close(pid.update(25.0).unwrap(), 0.0, 1e-6);
+    }
+
+    #[test]
+    fn integral_is_clamped_against_windup() {
+        let clock = MockClock::manual();
+        let mut pid = Pid::new(&clock, Config::new(0.0, 0.5, 0.0, SECOND), 100.0);
+
+        pid.update(0.0);
+        for _ in 0..100 {
+            clock.advance(SECOND);
+            assert_close(pid.update(0.0).unwrap(), 1.0, 1e-6);
+        }
+        // 误差反向后输出立即下降，而不是等待积分项消耗
+        clock.advance(SECOND);
+        assert_close(pid.update(100.4).unwrap(), 0.8, 1e-6);
+    }
+
+    #[test]
+    fn derivative_on_measurement() {
+        let clock = MockClock::manual();
+        let config = Config {
+            output_min: -10.0,
+            output_max: 10.0,
+            ..Config::new(0.0, 0.0, 2.0, SECOND)
+        };
+        let mut pid = Pid::new(&clock, config, 20.0);
+
+        pid.update(20.0);
+        clock.advance(SECOND);
+        // 设定值突变不产生微分冲击
+        pid.set_setpoint(30.0);
+        assert_close(pid.update(20.0).unwrap(), 0.0, 1e-6);
+        // 测量值上升时微分项为负
+        clock.advance(SECOND * 2);
+        assert_close(pid.update(21.0).unwrap(), -1.0, 1e-6);
+    }
+
+    #[test]
+    fn bumpless_manual_to_auto_transfer() {
+        let clock = MockClock::manual();
+        let mut pid = Pid::new(&clock, Config::new(0.2, 0.1, 0.0, SECOND), 50.0);
+
+        pid.set_manual(0.4);
+        assert_eq!(pid.mode(), Mode::Manual);
+        assert_close(pid.update(48.0).unwrap(), 0.4, 1e-6);
+        clock.advance(SECOND);
+        assert_close(pid.update(50.0).unwrap(), 0.4, 1e-6);
+
+        pid.set_auto();
+        clock.advance(SECOND);
+        assert_close(pid.update(50.0).unwrap(), 0.4, 1e-6);
+    }
+
+    #[test]
+    fn controller_sets_pwm_duty_cycle() {
+        let clock = MockClock::manual();
+        let pwm = PwmState::new(1000);
+        let config = Config {
+            output_min: 0.0,
+            output_max: 100.0,
+            ..Config::new(10.0, 0.0, 0.0, SECOND)
+        };
+        let heater = dc_relay::PwmDriver::new(pwm.pwm());
+        let mut controller = Controller::new(&clock, heater, config, 60.0).unwrap();
+
+        assert_eq!(controller.update(57.5).unwrap(), Some(25.0));
+        assert_eq!(pwm.duty(), 250);
+        assert_eq!(controller.update(50.0).unwrap(), None);
+        assert_eq!(pwm.duty(), 250);
+
+        controller.pid_mut().set_manual(100.0);
+        clock.advance(SECOND);
+        controller.update(50.0).unwrap();
+        assert_eq!(pwm.duty(), 1000);
+
+        pwm.set_fail(true);
+        clock.advance(SECOND);
+        assert!(controller.update(50.0).is_err());
+    }
+
+    #[test]
+    fn time_proportioning_relay() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = dc_relay::Driver::new(state.pin(), PinState::High, true).unwrap();
+        let mut output = TimeProportioning::new(&clock, relay, SECOND * 10).unwrap();
+        assert!(!state.is_high());
+
+        output.set_duty(0.3).unwrap();
+        assert!(state.is_high());
+        clock.advance(SECOND * 2);
+        output.update().unwrap();
+        assert!(state.is_high());
+        clock.advance(SECOND);
+        output.update().unwrap();
+        assert!(!state.is_high());
+
+        // 下一个窗口重新开启
+        clock.advance(SECOND * 7);
+        output.update().unwrap();
+        assert!(output.is_on());
+
+        // 跳过多个窗口后仍与原窗口对齐
+        clock.advance(SECOND * 34);
+        output.update().unwrap();
+        assert!(!output.is_on());
+        clock.advance(SECOND * 6);
+        output.update().unwrap();
+        assert!(output.is_on());
+
+        output.set_duty(0.0).unwrap();
+        assert!(!state.is_high());
+    }
+}
@@ -0,0 +1,366 @@
+//! I2C bus scanning and sensor identification
+//!
+//! [`scan`] lists every address that acknowledges, [`identify`] checks the known addresses for
+//! the supported parts by their chip ID or status byte. A [`Detected`] device creates its
+//! driver with [`Detected::into_driver`].
+
+use core::fmt::{Debug, Formatter};
+
+use embedded_hal::i2c::{ErrorKind, I2c, SevenBitAddress};
+use embedded_timers::clock::Clock;
+
+use crate::{aht30, bme280};
+
+/// Chip ID of the BME280 (register 0xD0)
+pub const BME280_CHIP_ID: u8 = 0x60;
+
+/// Chip ID of the BMP280 (register 0xD0)
+///
+/// Note: Engineering samples report 0x56 or 0x57, they are identified as BMP280 as well
+pub const BMP280_CHIP_ID: u8 = 0x58;
+
+/// Addresses probed by [`identify`]
+const KNOWN_ADDRESSES: [u8; 3] = [0x38, 0x76, 0x77];
+
+/// Set of 7bit I2C addresses
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AddressSet(u128);
+
+impl AddressSet {
+    /// Create an empty set
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Add an address
+    pub fn insert(&mut self, address: u8) {
+        self.0 |= 1 << (address & 0x7F);
+    }
+
+    /// Whether the address is in the set
+    pub fn contains(&self, address: u8) -> bool {
+        address <= 0x7F && self.0 & (1 << address) != 0
+    }
+
+    /// Number of addresses in the set
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Whether the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate the addresses in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=0x7F).filter(|address| self.contains(*address))
+    }
+}
+
+/// Convert a NACK into `false`, other errors are passed through
+fn acknowledged<E: embedded_hal::i2c::Error>(result: Result<(), E>) -> Result<bool, E> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(err) if matches!(err.kind(), ErrorKind::NoAcknowledge(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Scan the bus for devices answering on the non reserved addresses 0x08 ~ 0x77
+///
+/// Every address is probed with a one byte read, a device is present when it acknowledges.
+/// Errors other than a NACK abort the scan.
+pub fn scan<B: I2c<SevenBitAddress>>(bus: &mut B) -> Result<AddressSet, B::Error> {
+    let mut found = AddressSet::new();
+    let mut buf = [0u8; 1];
+    for address in 0x08..=0x77 {
+        if acknowledged(bus.read(address, &mut buf))? {
+            found.insert(address);
+        }
+    }
+    // OK
+    Ok(found)
+}
+
+/// Supported part
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Kind {
+    /// AHT30 temperature and humidity sensor
+    Aht30,
+    /// BME280 temperature, pressure and humidity sensor
+    Bme280,
+    /// BMP280 temperature and pressure sensor
+    Bmp280,
+}
+
+/// Check for a BME280 or BMP280 by its chip ID
+///
+/// Returns `None` when nothing acknowledges or the chip ID is unknown.
+pub fn probe_bme280<B: I2c<SevenBitAddress>>(
+    bus: &mut B,
+    address: u8,
+) -> Result<Option<Kind>, B::Error> {
+    // 读取芯片ID寄存器(0xD0)
+    let mut id = [0u8; 1];
+    if !acknowledged(bus.write_read(address, &[0xD0], &mut id))? {
+        return Ok(None);
+    }
+    // OK
+    Ok(match id[0] {
+        BME280_CHIP_ID => Some(Kind::Bme280),
+        0x56..=BMP280_CHIP_ID => Some(Kind::Bmp280),
+        _ => None,
+    })
+}
+
+/// Check for an AHT30 by its status byte
+///
+/// The AHT30 is recognised when it acknowledges, is not busy and has the calibration bit set,
+/// which a powered up AHT30 reports without any initialization.
+pub fn probe_aht30<B: I2c<SevenBitAddress>>(bus: &mut B, address: u8) -> Result<bool, B::Error> {
+    // 读取1字节状态
+    let mut data = [0u8; 1];
+    if !acknowledged(bus.read(address, &mut data))? {
+        return Ok(false);
+    }
+    let status = aht30::Status::from(data[0]);
+    // OK
+    Ok(status.calibration_enabled && !status.is_busy)
+}
+
+/// Device found by [`identify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Detected {
+    /// Identified part
+    pub kind: Kind,
+    /// 7bit address
+    pub address: u8,
+}
+
+/// Devices found by [`identify`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Detections {
+    /// Found devices, in the order of [`KNOWN_ADDRESSES`]
+    devices: [Option<Detected>; KNOWN_ADDRESSES.len()],
+}
+
+impl Detections {
+    /// Iterate the found devices
+    pub fn iter(&self) -> impl Iterator<Item = Detected> + '_ {
+        self.devices.iter().flatten().copied()
+    }
+
+    /// Number of found devices
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Whether no device was found
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// First found device of the given kind
+    pub fn find(&self, kind: Kind) -> Option<Detected> {
+        self.iter().find(|device| device.kind == kind)
+    }
+}
+
+/// Identify the supported parts on the bus
+///
+/// Checks the BME280/BMP280 chip ID at 0x76 and 0x77 and the AHT30 status at 0x38.
+pub fn identify<B: I2c<SevenBitAddress>>(bus: &mut B) -> Result<Detections, B::Error> {
+    let mut found = Detections::default();
+    for (slot, address) in found.devices.iter_mut().zip(KNOWN_ADDRESSES) {
+        let kind = match address {
+            0x38 => probe_aht30(bus, address)?.then_some(Kind::Aht30),
+            _ => probe_bme280(bus, address)?,
+        };
+        *slot = kind.map(|kind| Detected { kind, address });
+    }
+    // OK
+    Ok(found)
+}
+
+/// Driver instance created from a [`Detected`] device
+pub enum Sensor<'a, C: Clock> {
+    /// AHT30 driver
+    Aht30(aht30::Driver<'a, C>),
+    /// BME280 driver
+    Bme280(bme280::Driver<'a, C>),
+}
+
+/// Driver creation error
+pub enum Error<B: I2c<SevenBitAddress>> {
+    /// AHT30 driver error
+    Aht30(aht30::Error<B>),
+    /// BME280 driver error
+    Bme280(bme280::Error<B>),
+    /// No driver of this crate supports the part, e.g. the BMP280
+    Unsupported(Kind),
+}
+
+impl<B> Debug for Error<B>
+where
+    B: I2c<SevenBitAddress>,
+    B::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Aht30(err) => write!(f, "The AHT30 driver creation failed, {:?}", err),
+            Self::Bme280(err) => write!(f, "The BME280 driver creation failed, {:?}", err),
+            Self::Unsupported(kind) => write!(f, "The {:?} is not supported by any driver.", kind),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: I2c<SevenBitAddress>> std::fmt::Display for Error<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: I2c<SevenBitAddress>> std::error::Error for Error<B> {}
+
+#[cfg(feature = "defmt")]
+impl<B: I2c<SevenBitAddress>> defmt::Format for Error<B> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Aht30(err) => defmt::write!(f, "The AHT30 driver creation failed, {}", err),
+            Self::Bme280(err) => defmt::write!(f, "The BME280 driver creation failed, {}", err),
+            Self::Unsupported(kind) => {
+                defmt::write!(f, "The {} is not supported by any driver.", kind)
+            }
+        }
+    }
+}
+
+impl Detected {
+    /// Create the matching driver instance
+    ///
+    /// Returns [`Error::Unsupported`] for the BMP280, it lacks the humidity sensor and its
+    /// calibration registers the BME280 driver reads.
+    ///
+    /// Note: The driver will not hold this I2C bus internally
+    pub fn into_driver<'a, C: Clock, B: I2c<SevenBitAddress>>(
+        self,
+        clock: &'a C,
+        bus: &mut B,
+    ) -> Result<Sensor<'a, C>, Error<B>> {
+        match self.kind {
+            Kind::Aht30 => aht30::Driver::new(clock, bus, Some(self.address))
+                .map(Sensor::Aht30)
+                .map_err(|err| Error::Aht30(err)),
+            Kind::Bme280 => bme280::Driver::new(clock, bus, Some(self.address))
+                .map(Sensor::Bme280)
+                .map_err(|err| Error::Bme280(err)),
+            Kind::Bmp280 => Err(Error::Unsupported(self.kind)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{identify, scan, Detected, Error, Kind, Sensor, BMP280_CHIP_ID};
+    use crate::mock::{Aht30Device, Bme280Device, MockBus, MockClock, MockError};
+
+    fn populated_bus() -> MockBus {
+        let mut bus = MockBus::new();
+        bus.aht30 = Some(Aht30Device::new());
+        let mut bmp280 = Bme280Device::new();
+        bmp280.set_address(0x77);
+        bmp280.set_chip_id(BMP280_CHIP_ID);
+        bus.bme280 = [Some(Bme280Device::new()), Some(bmp280)];
+        bus
+    }
+
+    #[test]
+    fn scans_acknowledging_addresses() {
+        let mut bus = populated_bus();
+        let found = scan(&mut bus).unwrap();
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().eq([0x38, 0x76, 0x77]));
+        assert!(!found.contains(0x39));
+
+        bus.fail = true;
+        assert_eq!(scan(&mut bus), Err(MockError::Io));
+    }
+
+    #[test]
+    fn identifies_supported_parts() {
+        let mut bus = populated_bus();
+        let found = identify(&mut bus).unwrap();
+        assert!(found.iter().eq([
+            Detected {
+                kind: Kind::Aht30,
+                address: 0x38
+            },
+            Detected {
+                kind: Kind::Bme280,
+                address: 0x76
+            },
+            Detected {
+                kind: Kind::Bmp280,
+                address: 0x77
+            },
+        ]));
+    }
+
+    #[test]
+    fn skips_unknown_and_uncalibrated_parts() {
+        let mut bus = populated_bus();
+        bus.aht30.as_mut().unwrap().fail_calibration();
+        bus.bme280[1].as_mut().unwrap().set_chip_id(0x55);
+        let found = identify(&mut bus).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found.find(Kind::Bme280).map(|d| d.address), Some(0x76));
+        assert!(found.find(Kind::Aht30).is_none());
+
+        assert!(identify(&mut MockBus::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn identifies_bmp280_samples() {
+        let mut bus = populated_bus();
+        for id in [0x56, 0x57, BMP280_CHIP_ID] {
+            bus.bme280[1].as_mut().unwrap().set_chip_id(id);
+            let found = identify(&mut bus).unwrap();
+            assert_eq!(found.find(Kind::Bmp280).map(|d| d.address), Some(0x77));
+        }
+    }
+
+    #[test]
+    fn creates_matching_drivers() {
+        let clock = MockClock::new();
+        let mut bus = populated_bus();
+        bus.aht30.as_mut().unwrap().set_measurement(21.0, 40.0);
+
+        let found = identify(&mut bus).unwrap();
+        for device in found.iter() {
+            match device.into_driver(&clock, &mut bus) {
+                Ok(Sensor::Aht30(mut driver)) => {
+                    let (temperature, _) = driver.read(&mut bus).unwrap();
+                    assert!((temperature - 21.0).abs() < 0.01);
+                }
+                Ok(Sensor::Bme280(mut driver)) => {
+                    assert_eq!(device.kind, Kind::Bme280);
+                    assert!(driver.read(&mut bus).is_ok());
+                }
+                Err(Error::Unsupported(kind)) => assert_eq!(kind, Kind::Bmp280),
+                Err(_) => panic!("driver creation failed"),
+            }
+        }
+
+        bus.bme280[0].as_mut().unwrap().set_status(0x01);
+        let device = found.find(Kind::Bme280).unwrap();
+        assert!(matches!(
+            device.into_driver(&clock, &mut bus),
+            Err(Error::Bme280(crate::bme280::Error::Init))
+        ));
+    }
+}
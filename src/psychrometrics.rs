@@ -0,0 +1,211 @@
+//! Psychrometric metrics derived from temperature and relative humidity
+//!
+//! All functions take the temperature in °C and the relative humidity in %RH, exactly as
+//! returned by the `aht30`, `bme280` and `dht11` drivers. Pressures are in Pa like the BME280.
+//!
+//! The relative humidity is clamped to 0.01 ~ 100 %RH, so a dry or saturated reading never
+//! produces NaN.
+
+use libm::{atanf, expf, logf, powf, sqrtf};
+
+/// Standard atmospheric pressure at sea level (Pa)
+pub const STANDARD_PRESSURE: f32 = 101_325.0;
+
+/// Magnus coefficients over water (Sonntag 1990)
+const MAGNUS_WATER: (f32, f32) = (17.62, 243.12);
+
+/// Magnus coefficients over ice (Sonntag 1990)
+const MAGNUS_ICE: (f32, f32) = (22.46, 272.62);
+
+/// Saturation vapour pressure at 0°C (Pa)
+const MAGNUS_E0: f32 = 611.2;
+
+/// Specific gas constant of water vapour (J/(kg·K))
+const WATER_VAPOUR_GAS_CONSTANT: f32 = 461.5;
+
+/// Ratio of the molar masses of water vapour and dry air, in g/kg
+const MOLAR_MASS_RATIO: f32 = 621.97;
+
+/// Clamp the relative humidity into the range where the formulas are defined
+fn clamp_humidity(humidity: f32) -> f32 {
+    humidity.clamp(0.01, 100.0)
+}
+
+/// Magnus exponent `b·T / (c + T)`
+fn magnus((b, c): (f32, f32), temperature: f32) -> f32 {
+    b * temperature / (c + temperature)
+}
+
+/// Invert the Magnus formula, `gamma` is `ln(e / E0)`
+fn inverse_magnus((b, c): (f32, f32), gamma: f32) -> f32 {
+    c * gamma / (b - gamma)
+}
+
+/// Convert °C to °F
+fn fahrenheit(celsius: f32) -> f32 {
+    celsius * 9.0 / 5.0 + 32.0
+}
+
+/// Convert °F to °C
+fn celsius(fahrenheit: f32) -> f32 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+/// Saturation vapour pressure over water (Pa), Magnus formula
+pub fn saturation_vapour_pressure(temperature: f32) -> f32 {
+    MAGNUS_E0 * expf(magnus(MAGNUS_WATER, temperature))
+}
+
+/// Actual vapour pressure (Pa)
+pub fn vapour_pressure(temperature: f32, humidity: f32) -> f32 {
+    clamp_humidity(humidity) / 100.0 * saturation_vapour_pressure(temperature)
+}
+
+/// Dew point (°C), Magnus formula over water
+///
+/// Accurate to about ±0.35°C between -45°C and 60°C
+pub fn dew_point(temperature: f32, humidity: f32) -> f32 {
+    let gamma = logf(clamp_humidity(humidity) / 100.0) + magnus(MAGNUS_WATER, temperature);
+    inverse_magnus(MAGNUS_WATER, gamma)
+}
+
+/// Frost point (°C), Magnus formula over ice
+///
+/// The temperature at which the air becomes saturated with respect to ice.
+/// Below 0°C it is slightly higher than the dew point.
+pub fn frost_point(temperature: f32, humidity: f32) -> f32 {
+    let gamma = logf(vapour_pressure(temperature, humidity) / MAGNUS_E0);
+    inverse_magnus(MAGNUS_ICE, gamma)
+}
+
+/// Heat index (°C), NOAA algorithm
+///
+/// Uses Steadman's simple formula for mild conditions and the Rothfusz regression with the
+/// NOAA low and high humidity adjustments above 80°F (26.7°C)
+pub fn heat_index(temperature: f32, humidity: f32) -> f32 {
+    let t = fahrenheit(temperature);
+    let rh = clamp_humidity(humidity);
+
+    // Steadman简化公式，结果低于80°F时直接使用
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    if (simple + t) / 2.0 < 80.0 {
+        return celsius(simple);
+    }
+
+    // Rothfusz回归公式
+    let mut index = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+        - 0.224_755_4 * t * rh
+        - 0.006_837_83 * t * t
+        - 0.054_817_17 * rh * rh
+        + 0.001_228_74 * t * t * rh
+        + 0.000_852_82 * t * rh * rh
+        - 0.000_001_99 * t * t * rh * rh;
+
+    // 低湿度与高湿度修正
+    if rh < 13.0 && (80.0..=112.0).contains(&t) {
+        index -= (13.0 - rh) / 4.0 * sqrtf((17.0 - (t - 95.0).abs()) / 17.0);
+    } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+        index += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+    }
+
+    celsius(index)
+}
+
+/// Humidex (°C), Environment Canada formula
+pub fn humidex(temperature: f32, humidity: f32) -> f32 {
+    let dew_point = dew_point(temperature, humidity) + 273.15;
+    // 由露点计算水汽压(hPa)
+    let vapour_pressure = 6.11 * expf(5417.753 * (1.0 / 273.16 - 1.0 / dew_point));
+    temperature + 0.5555 * (vapour_pressure - 10.0)
+}
+
+/// Absolute humidity (g/m³)
+pub fn absolute_humidity(temperature: f32, humidity: f32) -> f32 {
+    let vapour_pressure = vapour_pressure(temperature, humidity);
+    vapour_pressure / (WATER_VAPOUR_GAS_CONSTANT * (temperature + 273.15)) * 1000.0
+}
+
+/// Mixing ratio (g of water vapour per kg of dry air)
+///
+/// - pressure: Air pressure in Pa, use [`STANDARD_PRESSURE`] without a barometer
+pub fn mixing_ratio(temperature: f32, humidity: f32, pressure: f32) -> f32 {
+    let vapour_pressure = vapour_pressure(temperature, humidity);
+    MOLAR_MASS_RATIO * vapour_pressure / (pressure - vapour_pressure)
+}
+
+/// Vapour pressure deficit (Pa)
+///
+/// The difference between the saturation and the actual vapour pressure
+pub fn vapour_pressure_deficit(temperature: f32, humidity: f32) -> f32 {
+    saturation_vapour_pressure(temperature) - vapour_pressure(temperature, humidity)
+}
+
+/// Wet-bulb temperature (°C), Stull 2011
+///
+/// Valid at standard pressure for 5 ~ 99 %RH and -20 ~ 50°C, accurate to about ±0.3°C
+pub fn wet_bulb(temperature: f32, humidity: f32) -> f32 {
+    let rh = clamp_humidity(humidity);
+    temperature * atanf(0.151_977 * sqrtf(rh + 8.313_659)) + atanf(temperature + rh)
+        - atanf(rh - 1.676_331)
+        + 0.003_918_38 * powf(rh, 1.5) * atanf(0.023_101 * rh)
+        - 4.686_035
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        absolute_humidity, dew_point, frost_point, heat_index, humidex, mixing_ratio,
+        vapour_pressure_deficit, wet_bulb, STANDARD_PRESSURE,
+    };
+    use crate::mock::assert_close;
+
+    #[test]
+    fn dew_point_vectors() {
+        assert_close(dew_point(25.0, 60.0), 16.69, 0.01);
+        assert_close(dew_point(20.0, 50.0), 9.26, 0.01);
+        // 饱和空气的露点等于气温
+        assert_close(dew_point(15.0, 100.0), 15.0, 0.01);
+        assert!(dew_point(20.0, 0.0).is_finite());
+    }
+
+    #[test]
+    fn frost_point_vectors() {
+        assert_close(frost_point(-10.0, 80.0), -11.39, 0.01);
+        assert_close(frost_point(0.0, 100.0), 0.0, 0.01);
+        // 冰点以下霜点高于露点
+        assert!(frost_point(-10.0, 80.0) > dew_point(-10.0, 80.0));
+    }
+
+    #[test]
+    fn heat_index_vectors() {
+        // NOAA表格: 90°F, 70% -> 106°F
+        assert_close(heat_index(32.222, 70.0), 41.07, 0.05);
+        // 低于80°F使用简化公式
+        assert_close(heat_index(25.0, 50.0), 24.86, 0.01);
+        // 低湿度修正
+        assert_close(heat_index(40.0, 10.0), 36.71, 0.05);
+        // 高湿度修正
+        assert_close(heat_index(29.0, 90.0), 37.23, 0.05);
+    }
+
+    #[test]
+    fn humidex_vectors() {
+        // 环境加拿大表格: 30°C, 40%(露点约15°C) -> 34
+        assert_close(humidex(30.0, 40.0), 33.92, 0.05);
+    }
+
+    #[test]
+    fn moisture_content_vectors() {
+        assert_close(absolute_humidity(20.0, 50.0), 8.62, 0.01);
+        assert_close(mixing_ratio(20.0, 50.0, STANDARD_PRESSURE), 7.24, 0.01);
+        assert_close(vapour_pressure_deficit(25.0, 50.0), 1580.0, 1.0);
+        assert_close(vapour_pressure_deficit(25.0, 100.0), 0.0, 0.01);
+    }
+
+    #[test]
+    fn wet_bulb_vectors() {
+        // Stull 2011 示例: 20°C, 50% -> 13.7°C
+        assert_close(wet_bulb(20.0, 50.0), 13.70, 0.01);
+        assert!(wet_bulb(30.0, 60.0) < 30.0);
+    }
+}
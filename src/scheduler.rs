@@ -0,0 +1,490 @@
+//! Periodic sampling of several sensors
+//!
+//! The [`Scheduler`] polls every registered [`Sensor`] at its own period. Each measurement has
+//! a start and a collect phase, so slow conversions (e.g. the 80ms of the AHT30) never block
+//! the other sensors. Call [`Scheduler::poll`] from the main loop and handle the returned
+//! [`Sample`]s.
+
+use core::{
+    fmt::{Debug, Formatter},
+    time::Duration,
+};
+
+use embedded_hal::{
+    digital::{InputPin, OutputPin},
+    i2c::{I2c, SevenBitAddress},
+};
+use embedded_timers::clock::Clock;
+
+use crate::{aht30, bme280, dht11, hx711};
+
+/// Sampling error
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// I2C bus error
+    I2c(embedded_hal::i2c::ErrorKind),
+    /// Digital I/O error
+    Digital(embedded_hal::digital::ErrorKind),
+    /// Sensor initialization failed
+    Init,
+    /// The checksum of the data failed
+    Crc,
+    /// Sensor busy
+    Busy,
+    /// Sensor not ready
+    NotReady,
+    /// The result was not ready before the next measurement was due
+    Timeout,
+}
+
+impl Debug for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::I2c(kind) => write!(f, "I2C bus error: {:?}", kind),
+            Self::Digital(kind) => write!(f, "Digital I/O error: {:?}", kind),
+            Self::Init => write!(f, "Sensor initialization failed."),
+            Self::Crc => write!(f, "The checksum of the data failed."),
+            Self::Busy => write!(f, "The sensor is busy."),
+            Self::NotReady => write!(f, "The sensor is not ready."),
+            Self::Timeout => write!(f, "The measurement timed out."),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Sensor reading
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Reading {
+    /// AHT30 temperature and humidity
+    Aht30(aht30::Measurement),
+    /// BME280 temperature, pressure and humidity
+    Bme280(bme280::Measurement),
+    /// DHT11 temperature and humidity
+    Dht11(dht11::Measurement),
+    /// HX711 raw conversion result
+    Hx711(i32),
+}
+
+/// Sensor sampled by the [`Scheduler`]
+pub trait Sensor {
+    /// Start a measurement and return the time until the result can be collected
+    fn start(&mut self) -> Result<Duration, Error>;
+
+    /// Collect the result of the measurement
+    ///
+    /// `Ok(None)` means the result is not ready yet, the scheduler retries on the next poll.
+    fn collect(&mut self) -> Result<Option<Reading>, Error>;
+}
+
+impl<C: Clock, B: I2c<SevenBitAddress>> Sensor for aht30::OwnedDriver<'_, C, B> {
+    fn start(&mut self) -> Result<Duration, Error> {
+        self.start_measurement()
+            .map_err(|err| Error::I2c(embedded_hal::i2c::Error::kind(&err)))?;
+        Ok(aht30::MEASUREMENT_TIME)
+    }
+
+    fn collect(&mut self) -> Result<Option<Reading>, Error> {
+        match self.read_measurement() {
+            Ok(data) => Ok(Some(Reading::Aht30(data.into()))),
+            // 测量尚未完成
+            Err(aht30::Error::Busy) => Ok(None),
+            Err(aht30::Error::Raw(err)) => Err(Error::I2c(embedded_hal::i2c::Error::kind(&err))),
+            Err(aht30::Error::Init) => Err(Error::Init),
+            Err(aht30::Error::Crc) => Err(Error::Crc),
+        }
+    }
+}
+
+/// The BME280 measures continuously in normal mode, so the latest result is collected at once
+impl<C: Clock, B: I2c<SevenBitAddress>> Sensor for bme280::OwnedDriver<'_, C, B> {
+    fn start(&mut self) -> Result<Duration, Error> {
+        Ok(Duration::ZERO)
+    }
+
+    fn collect(&mut self) -> Result<Option<Reading>, Error> {
+        self.read()
+            .map(|data| Some(Reading::Bme280(data.into())))
+            .map_err(|err| Error::I2c(embedded_hal::i2c::Error::kind(&err)))
+    }
+}
+
+/// Note: The DHT11 protocol is bit-banged, collecting blocks for about 25ms
+impl<C: Clock, P: InputPin + OutputPin> Sensor for dht11::Driver<'_, C, P> {
+    fn start(&mut self) -> Result<Duration, Error> {
+        Ok(Duration::ZERO)
+    }
+
+    fn collect(&mut self) -> Result<Option<Reading>, Error> {
+        match self.read() {
+            Ok(data) => Ok(Some(Reading::Dht11(data.into()))),
+            Err(dht11::Error::Input(err) | dht11::Error::Output(err)) => {
+                Err(Error::Digital(embedded_hal::digital::Error::kind(&err)))
+            }
+            Err(dht11::Error::NotReady) => Err(Error::NotReady),
+            Err(dht11::Error::CheckSum) => Err(Error::Crc),
+        }
+    }
+}
+
+/// The HX711 converts continuously, the result is collected once the data pin signals ready
+impl<C: Clock, I: InputPin, O: OutputPin> Sensor for hx711::Driver<'_, C, I, O> {
+    fn start(&mut self) -> Result<Duration, Error> {
+        Ok(Duration::ZERO)
+    }
+
+    fn collect(&mut self) -> Result<Option<Reading>, Error> {
+        let is_ready = self
+            .is_ready()
+            .map_err(|err| Error::Digital(embedded_hal::digital::Error::kind(&err)))?;
+        if !is_ready {
+            return Ok(None);
+        }
+        match self.read() {
+            Ok(raw) => Ok(Some(Reading::Hx711(raw))),
+            Err(hx711::Error::Input(err)) => {
+                Err(Error::Digital(embedded_hal::digital::Error::kind(&err)))
+            }
+            Err(hx711::Error::Output(err)) => {
+                Err(Error::Digital(embedded_hal::digital::Error::kind(&err)))
+            }
+            Err(hx711::Error::NotReady) => Ok(None),
+        }
+    }
+}
+
+/// Timestamped sample returned by [`Scheduler::poll`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample<I> {
+    /// Sensor id returned by [`Scheduler::add`]
+    pub sensor: usize,
+    /// Time the sample was collected
+    pub timestamp: I,
+    /// Reading or sampling error
+    pub result: Result<Reading, Error>,
+}
+
+/// Measurement phase of a sensor
+#[derive(Clone, Copy)]
+enum Phase<I> {
+    /// Waiting for the next start
+    Idle,
+    /// Measurement started, waiting for the result
+    Converting {
+        /// Earliest time to collect the result
+        ready_at: I,
+        /// The measurement times out at this time
+        deadline: I,
+    },
+}
+
+/// Registered sensor
+struct Slot<'s, I> {
+    /// Sampled sensor
+    sensor: &'s mut dyn Sensor,
+    /// Sampling period
+    period: Duration,
+    /// Time of the next start
+    next_start: I,
+    /// Measurement phase
+    phase: Phase<I>,
+}
+
+impl<I: embedded_timers::instant::Instant> Slot<'_, I> {
+    /// Run the phase due at `now`, returns a result once the measurement finished
+    fn step(&mut self, now: I) -> Option<Result<Reading, Error>> {
+        if let Phase::Idle = self.phase {
+            if now < self.next_start {
+                return None;
+            }
+            // 按固定周期排程，落后超过一个周期时从当前时间重新开始
+            self.next_start += self.period;
+            if self.next_start <= now {
+                self.next_start = now + self.period;
+            }
+            let ready_at = match self.sensor.start() {
+                Ok(conversion) => now + conversion,
+                Err(err) => return Some(Err(err)),
+            };
+            self.phase = Phase::Converting {
+                ready_at,
+                deadline: self.next_start.max(ready_at),
+            };
+        }
+
+        let Phase::Converting { ready_at, deadline } = self.phase else {
+            return None;
+        };
+        if now < ready_at {
+            return None;
+        }
+        let result = match self.sensor.collect() {
+            Ok(Some(reading)) => Ok(reading),
+            Ok(None) if now < deadline => return None,
+            Ok(None) => Err(Error::Timeout),
+            Err(err) => Err(err),
+        };
+        self.phase = Phase::Idle;
+        Some(result)
+    }
+
+    /// Time the next phase is due
+    fn next_wakeup(&self) -> I {
+        match self.phase {
+            Phase::Idle => self.next_start,
+            Phase::Converting { ready_at, .. } => ready_at,
+        }
+    }
+}
+
+/// Sampling scheduler for up to `N` sensors
+///
+/// ```ignore
+/// let mut scheduler = Scheduler::<_, 4>::new(&clock);
+/// let climate = scheduler.add(&mut aht30, Duration::from_secs(2)).unwrap();
+/// scheduler.add(&mut hx711, Duration::from_millis(100)).unwrap();
+/// loop {
+///     while let Some(sample) = scheduler.poll() {
+///         // ...
+///     }
+/// }
+/// ```
+pub struct Scheduler<'a, 's, C: Clock, const N: usize> {
+    /// Registered sensors
+    slots: [Option<Slot<'s, C::Instant>>; N],
+    /// Slot checked first by the next poll, so no sensor starves the others
+    cursor: usize,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, 's, C: Clock, const N: usize> Scheduler<'a, 's, C, N> {
+    /// Create an empty scheduler
+    pub fn new(clock: &'a C) -> Self {
+        Self {
+            slots: core::array::from_fn(|_| None),
+            cursor: 0,
+            clock_impl: clock,
+        }
+    }
+
+    /// Register a sensor, the first measurement starts on the next poll
+    ///
+    /// Returns the sensor id used in the [`Sample`]s, or `None` when all `N` slots are taken.
+    pub fn add(&mut self, sensor: &'s mut dyn Sensor, period: Duration) -> Option<usize> {
+        let index = self.slots.iter().position(Option::is_none)?;
+        self.slots[index] = Some(Slot {
+            sensor,
+            period,
+            next_start: self.clock_impl.now(),
+            phase: Phase::Idle,
+        });
+        // OK
+        Some(index)
+    }
+
+    /// Unregister a sensor, a running measurement is abandoned
+    pub fn remove(&mut self, sensor: usize) -> Option<&'s mut dyn Sensor> {
+        self.slots.get_mut(sensor)?.take().map(|slot| slot.sensor)
+    }
+
+    /// Start and collect the measurements that are due
+    ///
+    /// Returns one finished sample per call, call it until it returns `None`.
+    pub fn poll(&mut self) -> Option<Sample<C::Instant>> {
+        let now = self.clock_impl.now();
+        for offset in 0..N {
+            let index = (self.cursor + offset) % N;
+            let Some(slot) = &mut self.slots[index] else {
+                continue;
+            };
+            if let Some(result) = slot.step(now) {
+                self.cursor = (index + 1) % N;
+                return Some(Sample {
+                    sensor: index,
+                    timestamp: now,
+                    result,
+                });
+            }
+        }
+        None
+    }
+
+    /// Earliest time a sensor needs to be polled again, e.g. to sleep until then
+    pub fn next_wakeup(&self) -> Option<C::Instant> {
+        self.slots.iter().flatten().map(Slot::next_wakeup).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use embedded_timers::instant::Instant64;
+
+    use super::{Error, Reading, Scheduler, Sensor};
+    use crate::{
+        aht30, hx711,
+        mock::{assert_close, Aht30Device, Hx711Device, MockClock},
+    };
+
+    /// Sensor with a scripted conversion time and results
+    struct FakeSensor {
+        conversion: Duration,
+        fail_start: bool,
+        ready: bool,
+        starts: u32,
+    }
+
+    impl FakeSensor {
+        fn new(conversion: Duration) -> Self {
+            Self {
+                conversion,
+                fail_start: false,
+                ready: true,
+                starts: 0,
+            }
+        }
+    }
+
+    impl Sensor for FakeSensor {
+        fn start(&mut self) -> Result<Duration, Error> {
+            if self.fail_start {
+                return Err(Error::Busy);
+            }
+            self.starts += 1;
+            Ok(self.conversion)
+        }
+
+        fn collect(&mut self) -> Result<Option<Reading>, Error> {
+            Ok(self.ready.then_some(Reading::Hx711(self.starts as i32)))
+        }
+    }
+
+    fn millis(ms: u64) -> Instant64<1_000_000> {
+        Instant64::new(ms * 1000)
+    }
+
+    #[test]
+    fn interleaves_aht30_and_hx711() {
+        let clock = MockClock::manual();
+        // 驱动的阻塞延时需要自动前进的时钟
+        let hw_clock = MockClock::new();
+        let mut aht_device = Aht30Device::new();
+        aht_device.set_measurement(21.0, 40.0);
+        let hx_device = Hx711Device::new(&hw_clock);
+        hx_device.set_value(1234);
+
+        let mut aht = aht30::OwnedDriver::new(&hw_clock, &mut aht_device, None).unwrap();
+        let mut hx = hx711::Driver::new(
+            &hw_clock,
+            hx_device.clock_pin(),
+            hx_device.data_pin(),
+            hx711::ChannelGain::ChannelA128,
+        )
+        .unwrap();
+
+        let mut scheduler = Scheduler::<_, 4>::new(&clock);
+        let climate = scheduler.add(&mut aht, Duration::from_secs(1)).unwrap();
+        let scale = scheduler.add(&mut hx, Duration::from_millis(100)).unwrap();
+
+        // AHT30 开始转换的同时 HX711 已经完成采样
+        let sample = scheduler.poll().unwrap();
+        assert_eq!(sample.sensor, scale);
+        assert_eq!(sample.result, Ok(Reading::Hx711(1234)));
+        assert!(scheduler.poll().is_none());
+        assert_eq!(scheduler.next_wakeup(), Some(millis(80)));
+
+        clock.advance(Duration::from_millis(80));
+        let sample = scheduler.poll().unwrap();
+        assert_eq!(sample.sensor, climate);
+        assert_eq!(sample.timestamp, millis(80));
+        let Ok(Reading::Aht30(measurement)) = sample.result else {
+            panic!("unexpected sample {:?}", sample);
+        };
+        assert_close(measurement.temperature, 21.0, 0.01);
+        assert_close(measurement.humidity, 40.0, 0.01);
+        assert!(scheduler.poll().is_none());
+
+        // 未就绪的 HX711 在下一个周期开始时超时
+        hx_device.set_ready(false);
+        clock.advance(Duration::from_millis(20));
+        assert!(scheduler.poll().is_none());
+        clock.advance(Duration::from_millis(100));
+        let sample = scheduler.poll().unwrap();
+        assert_eq!(sample.sensor, scale);
+        assert_eq!(sample.result, Err(Error::Timeout));
+
+        hx_device.set_ready(true);
+        assert_eq!(scheduler.poll().unwrap().result, Ok(Reading::Hx711(1234)));
+        assert_eq!(scheduler.next_wakeup(), Some(millis(300)));
+
+        let (_, device) = aht.release();
+        assert_eq!(device.measurements(), 1);
+    }
+
+    #[test]
+    fn keeps_the_sampling_period() {
+        let clock = MockClock::manual();
+        let mut sensor = FakeSensor::new(Duration::from_millis(30));
+        let mut scheduler = Scheduler::<_, 1>::new(&clock);
+        scheduler
+            .add(&mut sensor, Duration::from_millis(100))
+            .unwrap();
+
+        assert!(scheduler.poll().is_none());
+        // 收集时间较晚也不会推迟下一次开始
+        clock.advance(Duration::from_millis(90));
+        assert_eq!(scheduler.poll().unwrap().result, Ok(Reading::Hx711(1)));
+        clock.advance(Duration::from_millis(10));
+        assert!(scheduler.poll().is_none());
+        assert_eq!(scheduler.next_wakeup(), Some(millis(130)));
+
+        // 落后多个周期后从当前时间重新开始
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(scheduler.poll().unwrap().result, Ok(Reading::Hx711(2)));
+        assert!(scheduler.poll().is_none());
+        assert_eq!(scheduler.next_wakeup(), Some(millis(630)));
+    }
+
+    #[test]
+    fn reports_start_errors_and_retries() {
+        let clock = MockClock::manual();
+        let mut sensor = FakeSensor::new(Duration::ZERO);
+        sensor.fail_start = true;
+        let mut scheduler = Scheduler::<_, 2>::new(&clock);
+        let id = scheduler.add(&mut sensor, Duration::from_secs(1)).unwrap();
+
+        assert_eq!(scheduler.poll().unwrap().result, Err(Error::Busy));
+        assert!(scheduler.poll().is_none());
+        assert_eq!(scheduler.next_wakeup(), Some(millis(1000)));
+
+        let sensor = scheduler.remove(id).unwrap();
+        assert!(scheduler.remove(id).is_none());
+        assert_eq!(scheduler.next_wakeup(), None);
+        let id = scheduler.add(sensor, Duration::from_secs(1)).unwrap();
+        assert_eq!(id, 0);
+    }
+
+    #[test]
+    fn rejects_sensors_when_full() {
+        let clock = MockClock::manual();
+        let mut first = FakeSensor::new(Duration::ZERO);
+        let mut second = FakeSensor::new(Duration::ZERO);
+        let mut scheduler = Scheduler::<_, 1>::new(&clock);
+
+        assert_eq!(scheduler.add(&mut first, Duration::from_secs(1)), Some(0));
+        assert!(scheduler.add(&mut second, Duration::from_secs(1)).is_none());
+    }
+}
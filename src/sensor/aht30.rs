@@ -1,13 +1,23 @@
+#[cfg(feature = "embedded-hal-bus")]
+use core::cell::RefCell;
 use core::{
     fmt::{Debug, Formatter},
     time::Duration,
 };
 
 use embedded_hal::i2c::{I2c, SevenBitAddress};
+#[cfg(all(feature = "embedded-hal-bus", feature = "std"))]
+use embedded_hal_bus::i2c::MutexDevice;
+#[cfg(feature = "embedded-hal-bus")]
+use embedded_hal_bus::i2c::{CriticalSectionDevice, RefCellDevice};
 use embedded_timers::{clock::Clock, delay::Delay};
 
+use crate::calibration::Record;
+
 /// AHT30 working mode
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WorkingMode {
     /// Normal
     NOR,
@@ -21,8 +31,12 @@ pub enum WorkingMode {
 ///
 /// Binary bits are counted from right to left. For example, in 0b00000001, the 0th bit is 1
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::manual_non_exhaustive)]
 pub struct Status {
     /// 二进制位第0位和第1位暂时空置
+    #[cfg_attr(feature = "serde", serde(skip))]
     _0_1: (),
     /// 校准后的电容数据是否超出CMP中断阈值范围
     ///
@@ -77,6 +91,28 @@ impl Status {
     }
 }
 
+/// AHT30 measurement
+///
+/// Built from the tuple returned by [`Driver::read`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    /// Temperature (°C)
+    pub temperature: f32,
+    /// Relative humidity (%RH)
+    pub humidity: f32,
+}
+
+impl From<(f32, f32)> for Measurement {
+    fn from((temperature, humidity): (f32, f32)) -> Self {
+        Self {
+            temperature,
+            humidity,
+        }
+    }
+}
+
 /// AHT30 sensor driver error
 pub enum Error<B: I2c<SevenBitAddress>> {
     /// I2C bus raw error
@@ -114,11 +150,32 @@ impl<B: I2c<SevenBitAddress>> std::fmt::Display for Error<B> {
 #[cfg(feature = "std")]
 impl<B: I2c<SevenBitAddress>> std::error::Error for Error<B> {}
 
+#[cfg(feature = "defmt")]
+impl<B: I2c<SevenBitAddress>> defmt::Format for Error<B> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Raw(err) => defmt::write!(
+                f,
+                "I2C bus communication error, {}",
+                embedded_hal::i2c::Error::kind(err)
+            ),
+            Self::Init => defmt::write!(f, "The initialization of the AHT30 sensor failed."),
+            Self::Crc => defmt::write!(f, "The CRC8 verification of the AHT30 sensor data failed."),
+            Self::Busy => defmt::write!(f, "The AHT30 sensor is busy."),
+        }
+    }
+}
+
+/// Time the AHT30 needs to complete a measurement
+pub const MEASUREMENT_TIME: Duration = Duration::from_millis(80);
+
 /// AHT30 sensor driver
 pub struct Driver<'a, C: Clock> {
     /// AHT30 7bit address
     /// - The default address is usually 0x38
     address: u8,
+    /// User calibration applied on every read
+    user_calibration: Record,
     /// Delay implementation for embedded_timers
     delay_impl: Delay<'a, C>,
 }
@@ -140,28 +197,40 @@ impl<'a, C: Clock> Driver<'a, C> {
         // 文档明确要求上电后需要等待5ms
         delay_impl.delay(Duration::from_millis(5));
 
-        // 发送传感器初始化命令
-        bus.write(addr, &[0xBE, 0x08, 0x00])
-            .map_err(|err| Error::Raw(err))?;
-
-        // 文档明确说明传感器初始化需要10ms
-        delay_impl.delay(Duration::from_millis(10));
-
         // 构建传感器实例
-        let this = Self {
+        let mut this = Self {
             address: addr,
+            user_calibration: Record::default(),
             delay_impl,
         };
 
+        // 初始化传感器
+        this.initialize(bus)?;
+
+        // OK
+        Ok(this)
+    }
+
+    /// Send the initialization command and check that the calibration is enabled
+    ///
+    /// Called by [`new`](Self::new). Call it again to recover a sensor that keeps failing.
+    pub fn initialize<B: I2c<SevenBitAddress>>(&mut self, bus: &mut B) -> Result<(), Error<B>> {
+        // 发送传感器初始化命令
+        bus.write(self.address, &[0xBE, 0x08, 0x00])
+            .map_err(|err| Error::Raw(err))?;
+
+        // 文档明确说明传感器初始化需要10ms
+        self.delay_impl.delay(Duration::from_millis(10));
+
         // 检查传感器状态
-        let status = this.read_status(bus).map_err(|err| Error::Raw(err))?;
+        let status = self.read_status(bus).map_err(|err| Error::Raw(err))?;
         if !status.calibration_enabled {
             // 校准功能未启用，则传感器未初始化成功
             return Err(Error::Init);
         }
 
         // OK
-        Ok(this)
+        Ok(())
     }
 
     /// Read sensor status
@@ -176,6 +245,16 @@ impl<'a, C: Clock> Driver<'a, C> {
         Ok(Status::from(data[0]))
     }
 
+    /// User calibration applied on every read
+    pub fn user_calibration(&self) -> &Record {
+        &self.user_calibration
+    }
+
+    /// Change the user calibration applied on every read
+    pub fn set_user_calibration(&mut self, record: Record) {
+        self.user_calibration = record;
+    }
+
     /// Calculate the CRC8 checksum
     fn calc_crc8(data: &[u8]) -> u8 {
         // 声明CRC8校验和结果
@@ -199,12 +278,31 @@ impl<'a, C: Clock> Driver<'a, C> {
 
     /// Read AHT30 sensor data
     pub fn read<B: I2c<SevenBitAddress>>(&mut self, bus: &mut B) -> Result<(f32, f32), Error<B>> {
+        self.start_measurement(bus).map_err(|err| Error::Raw(err))?;
+        // 根据文档要求，测量大概需要80ms才能完成
+        self.delay_impl.delay(MEASUREMENT_TIME);
+        self.read_measurement(bus)
+    }
+
+    /// Trigger a measurement without waiting for it
+    ///
+    /// Collect the result with [`read_measurement`](Self::read_measurement) after
+    /// [`MEASUREMENT_TIME`]
+    pub fn start_measurement<B: I2c<SevenBitAddress>>(
+        &mut self,
+        bus: &mut B,
+    ) -> Result<(), B::Error> {
         // 发送测量命令
         bus.write(self.address, &[0xAC, 0x33, 0x00])
-            .map_err(|err| Error::Raw(err))?;
-        // 根据文档要求，测量大概需要80ms才能完成
-        self.delay_impl.delay(Duration::from_millis(80));
+    }
 
+    /// Read the result of a measurement triggered by [`start_measurement`](Self::start_measurement)
+    ///
+    /// Note: [`Error::Busy`] is returned while the measurement is still running
+    pub fn read_measurement<B: I2c<SevenBitAddress>>(
+        &mut self,
+        bus: &mut B,
+    ) -> Result<(f32, f32), Error<B>> {
         // 读取7字节数据
         // 第1个字节（8位）: 8位二进制状态位
         // 第2~6个字节（40位）：前20位湿度 + 后20位温度
@@ -239,7 +337,273 @@ impl<'a, C: Clock> Driver<'a, C> {
         let humidity = (humidity_raw as f32 / (1u32 << 20) as f32) * 100.0;
         let temperature = (temperature_raw as f32 / (1u32 << 20) as f32) * 200.0 - 50.0;
 
+        // 应用用户校准
+        let temperature = self.user_calibration.temperature(temperature);
+        let humidity = self.user_calibration.humidity(humidity);
+
         // OK
         Ok((temperature, humidity))
     }
 }
+
+/// AHT30 sensor driver holding its I2C device
+///
+/// Unlike [`Driver`] the bus is not passed on every call. Give it a shared bus device of
+/// `embedded_hal_bus` (e.g. `RefCellDevice`) to put several sensors on one bus.
+pub struct OwnedDriver<'a, C: Clock, B: I2c<SevenBitAddress>> {
+    /// Borrow-per-call driver
+    driver: Driver<'a, C>,
+    /// I2C device used by the driver
+    bus: B,
+}
+
+impl<'a, C: Clock, B: I2c<SevenBitAddress>> OwnedDriver<'a, C, B> {
+    /// Create an instance of the AHT30 sensor driver holding the I2C device
+    ///
+    /// - address: The default address is 0x38
+    pub fn new(clock: &'a C, mut bus: B, address: Option<u8>) -> Result<Self, Error<B>> {
+        let driver = Driver::new(clock, &mut bus, address)?;
+        // OK
+        Ok(Self { driver, bus })
+    }
+
+    /// Send the initialization command, see [`Driver::initialize`]
+    pub fn initialize(&mut self) -> Result<(), Error<B>> {
+        self.driver.initialize(&mut self.bus)
+    }
+
+    /// Read sensor status
+    pub fn read_status(&mut self) -> Result<Status, B::Error> {
+        self.driver.read_status(&mut self.bus)
+    }
+
+    /// Read AHT30 sensor data
+    pub fn read(&mut self) -> Result<(f32, f32), Error<B>> {
+        self.driver.read(&mut self.bus)
+    }
+
+    /// Trigger a measurement without waiting for it
+    pub fn start_measurement(&mut self) -> Result<(), B::Error> {
+        self.driver.start_measurement(&mut self.bus)
+    }
+
+    /// Read the result of a measurement triggered by [`start_measurement`](Self::start_measurement)
+    pub fn read_measurement(&mut self) -> Result<(f32, f32), Error<B>> {
+        self.driver.read_measurement(&mut self.bus)
+    }
+
+    /// User calibration applied on every read
+    pub fn user_calibration(&self) -> &Record {
+        self.driver.user_calibration()
+    }
+
+    /// Change the user calibration applied on every read
+    pub fn set_user_calibration(&mut self, record: Record) {
+        self.driver.set_user_calibration(record);
+    }
+
+    /// Release the borrow-per-call driver and the I2C device
+    pub fn release(self) -> (Driver<'a, C>, B) {
+        (self.driver, self.bus)
+    }
+}
+
+#[cfg(feature = "embedded-hal-bus")]
+impl<'a, 'b, C: Clock, T: I2c<SevenBitAddress>> OwnedDriver<'a, C, RefCellDevice<'b, T>> {
+    /// Create the driver on a bus shared through a `RefCell`
+    pub fn new_ref_cell(
+        clock: &'a C,
+        bus: &'b RefCell<T>,
+        address: Option<u8>,
+    ) -> Result<Self, Error<RefCellDevice<'b, T>>> {
+        Self::new(clock, RefCellDevice::new(bus), address)
+    }
+}
+
+#[cfg(feature = "embedded-hal-bus")]
+impl<'a, 'b, C: Clock, T: I2c<SevenBitAddress>> OwnedDriver<'a, C, CriticalSectionDevice<'b, T>> {
+    /// Create the driver on a bus shared through a `critical_section::Mutex`
+    pub fn new_critical_section(
+        clock: &'a C,
+        bus: &'b critical_section::Mutex<RefCell<T>>,
+        address: Option<u8>,
+    ) -> Result<Self, Error<CriticalSectionDevice<'b, T>>> {
+        Self::new(clock, CriticalSectionDevice::new(bus), address)
+    }
+}
+
+#[cfg(all(feature = "embedded-hal-bus", feature = "std"))]
+impl<'a, 'b, C: Clock, T: I2c<SevenBitAddress>> OwnedDriver<'a, C, MutexDevice<'b, T>> {
+    /// Create the driver on a bus shared through a `std::sync::Mutex`
+    pub fn new_mutex(
+        clock: &'a C,
+        bus: &'b std::sync::Mutex<T>,
+        address: Option<u8>,
+    ) -> Result<Self, Error<MutexDevice<'b, T>>> {
+        Self::new(clock, MutexDevice::new(bus), address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Driver, Error, OwnedDriver, WorkingMode};
+    use crate::calibration::{Correction, Record};
+    use crate::mock::{Aht30Device, MockClock, MockError};
+
+    #[test]
+    fn reads_temperature_and_humidity() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        device.set_measurement(23.5, 45.0);
+
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+        let (temperature, humidity) = driver.read(&mut device).unwrap();
+        assert!((temperature - 23.5).abs() < 0.01);
+        assert!((humidity - 45.0).abs() < 0.01);
+        assert_eq!(device.inits(), 1);
+        assert_eq!(device.measurements(), 1);
+    }
+
+    #[test]
+    fn reads_negative_temperature() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        device.set_measurement(-20.0, 80.0);
+
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+        let (temperature, humidity) = driver.read(&mut device).unwrap();
+        assert!((temperature + 20.0).abs() < 0.01);
+        assert!((humidity - 80.0).abs() < 0.01);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn status_round_trips_through_serde() {
+        use super::Status;
+
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        let driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        let json = serde_json::to_string(&driver.read_status(&mut device).unwrap()).unwrap();
+        let status: Status = serde_json::from_str(&json).unwrap();
+        assert!(status.calibration_enabled);
+        assert!(!status.is_busy);
+        assert!(matches!(status.mode, WorkingMode::NOR));
+    }
+
+    #[test]
+    fn reads_status() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+
+        let driver = Driver::new(&clock, &mut device, None).unwrap();
+        let status = driver.read_status(&mut device).unwrap();
+        assert!(status.calibration_enabled);
+        assert!(status.crc_ok);
+        assert!(!status.is_busy);
+        assert!(matches!(status.mode, WorkingMode::NOR));
+    }
+
+    #[test]
+    fn init_fails_without_calibration() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        device.fail_calibration();
+
+        assert!(matches!(
+            Driver::new(&clock, &mut device, None),
+            Err(Error::Init)
+        ));
+    }
+
+    #[test]
+    fn wrong_address_is_raw_error() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+
+        assert!(matches!(
+            Driver::new(&clock, &mut device, Some(0x39)),
+            Err(Error::Raw(MockError::Nack))
+        ));
+    }
+
+    #[test]
+    fn read_fails_on_crc_mismatch() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        device.set_corrupt_crc(true);
+        assert!(matches!(driver.read(&mut device), Err(Error::Crc)));
+    }
+
+    #[test]
+    fn read_fails_when_busy() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        device.set_busy(true);
+        assert!(matches!(driver.read(&mut device), Err(Error::Busy)));
+    }
+
+    #[test]
+    fn applies_user_calibration() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        device.set_measurement(25.0, 50.0);
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        // 外壳内温度偏高1.5°C
+        driver.set_user_calibration(Record {
+            temperature: Correction::from_offset(-1.5),
+            humidity: Correction::new(1.1, 0.0),
+            ..Record::default()
+        });
+        let (temperature, humidity) = driver.read(&mut device).unwrap();
+        assert!((temperature - 23.5).abs() < 0.01);
+        assert!((humidity - 55.0).abs() < 0.01);
+        assert_eq!(driver.user_calibration().temperature.offset, -1.5);
+    }
+
+    #[test]
+    fn owned_driver_holds_the_bus() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        device.set_measurement(18.0, 60.0);
+
+        let mut driver = OwnedDriver::new(&clock, &mut device, None).unwrap();
+        assert!(driver.read_status().unwrap().calibration_enabled);
+        let (temperature, humidity) = driver.read().unwrap();
+        assert!((temperature - 18.0).abs() < 0.01);
+        assert!((humidity - 60.0).abs() < 0.01);
+
+        let (_, device) = driver.release();
+        assert_eq!(device.measurements(), 1);
+    }
+
+    #[cfg(feature = "embedded-hal-bus")]
+    #[test]
+    fn owned_drivers_share_a_bus() {
+        use core::cell::RefCell;
+
+        use crate::{bme280, mock::MockBus};
+
+        let clock = MockClock::new();
+        let mut bus = MockBus::new();
+        bus.aht30 = Some(Aht30Device::new());
+        bus.bme280[0] = Some(crate::mock::Bme280Device::new());
+        let bus = RefCell::new(bus);
+
+        let mut aht30 = OwnedDriver::new_ref_cell(&clock, &bus, None).unwrap();
+        let mut bme280 = bme280::OwnedDriver::new_ref_cell(&clock, &bus, None).unwrap();
+        assert!(aht30.read().is_ok());
+        assert!(bme280.read().is_ok());
+        assert!(aht30.read().is_ok());
+
+        let bus = critical_section::Mutex::new(bus);
+        let mut aht30 = OwnedDriver::new_critical_section(&clock, &bus, None).unwrap();
+        assert!(aht30.read().is_ok());
+    }
+}
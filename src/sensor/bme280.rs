@@ -1,11 +1,19 @@
+#[cfg(feature = "embedded-hal-bus")]
+use core::cell::RefCell;
 use core::{
     fmt::{Debug, Formatter},
     time::Duration,
 };
 
 use embedded_hal::i2c::{I2c, SevenBitAddress};
+#[cfg(all(feature = "embedded-hal-bus", feature = "std"))]
+use embedded_hal_bus::i2c::MutexDevice;
+#[cfg(feature = "embedded-hal-bus")]
+use embedded_hal_bus::i2c::{CriticalSectionDevice, RefCellDevice};
 use embedded_timers::{clock::Clock, delay::Delay};
 
+use crate::calibration::Record;
+
 /// BME280传感器校准参数结构体
 ///
 /// 该结构体存储了从传感器 NVM 中读取的所有校准参数，用于
@@ -14,7 +22,7 @@ use embedded_timers::{clock::Clock, delay::Delay};
 ///
 /// # 存储分布
 /// - 温度/压力参数: 地址 0x88-0xA1 (24字节)
-/// - 湿度参数: 地址 0xA1, 0xE1-0xE7 (7字节)
+/// - 湿度参数: 地址 0xA1, 0xE1-0xE7 (8字节)
 ///
 /// # 重要性
 /// 校准参数消除了传感器制造差异，提供：
@@ -22,7 +30,10 @@ use embedded_timers::{clock::Clock, delay::Delay};
 /// - 非线性响应校正  
 /// - 长期稳定性保证
 /// - 交叉敏感性消除
-struct Calibration {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Calibration {
     /// 温度校准参数组
     pub dig_t1: u16,
     pub dig_t2: i16,
@@ -50,7 +61,7 @@ struct Calibration {
 
 impl Calibration {
     /// Parse BME280 Calibration params
-    pub fn from(tp_calib: &[u8; 24], h_calib: &[u8; 7]) -> Self {
+    pub fn from(tp_calib: &[u8; 24], h_calib: &[u8; 8]) -> Self {
         Self {
             // 温度、气压校准参数
             dig_t1: u16::from_le_bytes([tp_calib[0], tp_calib[1]]),
@@ -70,155 +81,11 @@ impl Calibration {
             dig_h1: h_calib[0],
             dig_h2: i16::from_le_bytes([h_calib[1], h_calib[2]]),
             dig_h3: h_calib[3],
-            dig_h4: (i16::from(h_calib[4]) << 4) | (i16::from(h_calib[5]) & 0x0F),
-            dig_h5: (i16::from(h_calib[6]) << 4) | (i16::from(h_calib[5]) >> 4),
-            dig_h6: h_calib[6] as i8,
+            dig_h4: (i16::from(h_calib[4] as i8) << 4) | (i16::from(h_calib[5]) & 0x0F),
+            dig_h5: (i16::from(h_calib[6] as i8) << 4) | (i16::from(h_calib[5]) >> 4),
+            dig_h6: h_calib[7] as i8,
         }
     }
-}
-
-/// BME280 sensor driver error
-pub enum Error<B: I2c<SevenBitAddress>> {
-    /// I2C bus raw error
-    Raw(B::Error),
-    /// Sensor initialization failed
-    Init,
-    /// Sensor busy
-    Busy,
-}
-
-impl<B> Debug for Error<B>
-where
-    B: I2c<SevenBitAddress>,
-    B::Error: Debug,
-{
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        match self {
-            Self::Raw(err) => write!(f, "I2C bus communication error, {:?}", err),
-            Self::Init => write!(f, "The initialization of the BME280 sensor failed."),
-            Self::Busy => write!(f, "The BME280 sensor is busy."),
-        }
-    }
-}
-
-#[cfg(feature = "std")]
-impl<B: I2c<SevenBitAddress>> std::fmt::Display for Error<B> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        Debug::fmt(self, f)
-    }
-}
-
-#[cfg(feature = "std")]
-impl<B: I2c<SevenBitAddress>> std::error::Error for Error<B> {}
-
-/// BME280 sensor driver
-pub struct Driver<'a, C: Clock> {
-    /// BME280 7bit address
-    /// - The default address is usually 0x76
-    address: u8,
-    /// BME280 Calibration params
-    calib: Calibration,
-    /// Delay implementation for embedded_timers
-    delay_impl: Delay<'a, C>,
-}
-
-impl<'a, C: Clock> Driver<'a, C> {
-    /// Read calibration data
-    fn read_calibration_data<B: I2c<SevenBitAddress>>(
-        bus: &mut B,
-        address: u8,
-    ) -> Result<Calibration, B::Error> {
-        // 读取温度/压力校准参数 (0x88-0x9F)
-        let mut tp_calib = [0u8; 24];
-        bus.write_read(address, &[0x88], &mut tp_calib)?;
-        // 读取湿度校准参数 (0xA1, 0xE1-0xE7)
-        let mut h_calib = [0u8; 7];
-        bus.write_read(address, &[0xA1], &mut h_calib[0..1])?;
-        bus.write_read(address, &[0xE1], &mut h_calib[1..7])?;
-        // OK
-        Ok(Calibration::from(&tp_calib, &h_calib))
-    }
-
-    /// Create an instance of the BME280 sensor driver
-    ///
-    /// Note: The driver will not hold this I2C bus internally
-    pub fn new<B: I2c<SevenBitAddress>>(
-        clock: &'a C,
-        bus: &mut B,
-        address: Option<u8>,
-    ) -> Result<Self, Error<B>> {
-        // 处理地址
-        let addr = address.unwrap_or(0x76);
-        // 创建延迟实例
-        let mut delay_impl = Delay::new(clock);
-
-        // 文档明确要求上电后需要等待2ms以上
-        delay_impl.delay(Duration::from_millis(3));
-
-        // 检查传感器是否就绪
-        let mut status = [0u8];
-        bus.write_read(addr, &[0xF3], &mut status)
-            .map_err(|err| Error::Raw(err))?;
-        // 检查状态
-        if status[0] & 0x01 != 0 {
-            return Err(Error::Init);
-        }
-
-        // 读取校准参数
-        let calib = Self::read_calibration_data(bus, addr).map_err(|err| Error::Raw(err))?;
-
-        // 配置湿度采样率 (osrs_h = 1x)
-        bus.write(addr, &[0xF2, 0x01])
-            .map_err(|err| Error::Raw(err))?;
-        delay_impl.delay(Duration::from_millis(10));
-        // 配置温度、压力采样率 (osrs_t = 1x, osrs_p = 1x) 和正常模式
-        bus.write(addr, &[0xF4, 0x27])
-            .map_err(|err| Error::Raw(err))?; // 00100111 = 0x27
-        delay_impl.delay(Duration::from_millis(10));
-        // 配置滤波器关闭，待机时间 0.5ms
-        bus.write(addr, &[0xF5, 0x00])
-            .map_err(|err| Error::Raw(err))?;
-        delay_impl.delay(Duration::from_millis(10));
-
-        // OK
-        Ok(Self {
-            address: addr,
-            calib,
-            delay_impl,
-        })
-    }
-
-    /// Read ADC raw data
-    fn read_raw_data<B: I2c<SevenBitAddress>>(
-        &self,
-        bus: &mut B,
-    ) -> Result<(i32, i32, i32), B::Error> {
-        // 声明缓冲区
-        let mut data = [0u8; 8];
-
-        // 读取原始数据
-        bus.write_read(self.address, &[0xF7], &mut data)?;
-
-        // 解析20位压力数据 (0xF7-0xF9)
-        let press_msb = data[0] as i32;
-        let press_lsb = data[1] as i32;
-        let press_xlsb = data[2] as i32;
-        let press_raw = (press_msb << 12) | (press_lsb << 4) | (press_xlsb >> 4);
-
-        // 解析20位温度数据 (0xFA-0xFC)
-        let temp_msb = data[3] as i32;
-        let temp_lsb = data[4] as i32;
-        let temp_xlsb = data[5] as i32;
-        let temp_raw = (temp_msb << 12) | (temp_lsb << 4) | (temp_xlsb >> 4);
-
-        // 解析16位湿度数据 (0xFD-0xFE)
-        let hum_msb = data[6] as i32;
-        let hum_lsb = data[7] as i32;
-        let hum_raw = (hum_msb << 8) | hum_lsb;
-
-        // OK
-        Ok((press_raw, temp_raw, hum_raw))
-    }
 
     /// BME280温度补偿函数
     ///
@@ -241,11 +108,11 @@ impl<'a, C: Clock> Driver<'a, C> {
     /// - 分辨率: 0.01°C
     /// - 绝对精度: ±0.5°C (0-65°C范围内)
     /// - 长期稳定性: ±0.08°C/年
-    fn compensate_temperature(&self, adc_t: i32) -> (f32, i64) {
+    pub fn compensate_temperature(&self, adc_t: i32) -> (f32, i64) {
         // 提取温度补偿数据编译换算（注意温度补偿运算是在32位有符号整型下转换的）
-        let dig_t1 = self.calib.dig_t1 as i32;
-        let dig_t2 = self.calib.dig_t2 as i32;
-        let dig_t3 = self.calib.dig_t3 as i32;
+        let dig_t1 = self.dig_t1 as i32;
+        let dig_t2 = self.dig_t2 as i32;
+        let dig_t3 = self.dig_t3 as i32;
         // 带入公式进行换算
         let var1 = (((adc_t >> 3) - (dig_t1 << 1)) * dig_t2) >> 11;
         let var2 = ((((adc_t >> 4) - dig_t1) * ((adc_t >> 4) - dig_t1)) >> 12) * dig_t3;
@@ -282,17 +149,17 @@ impl<'a, C: Clock> Driver<'a, C> {
     /// - 分辨率: 0.18Pa (相当于1.7cm高度)
     /// - 绝对精度: ±1.0hPa (300-1100hPa, 0-65°C)
     /// - 温度系数: ±1.5Pa/K
-    fn compensate_pressure(&self, adc_p: i32, t_fine: i64) -> f32 {
+    pub fn compensate_pressure(&self, adc_p: i32, t_fine: i64) -> f32 {
         // 提取压力补偿数据编译换算（注意压力补偿运算是在64位有符号整型下转换的）
-        let dig_p1 = self.calib.dig_p1 as i64;
-        let dig_p2 = self.calib.dig_p2 as i64;
-        let dig_p3 = self.calib.dig_p3 as i64;
-        let dig_p4 = self.calib.dig_p4 as i64;
-        let dig_p5 = self.calib.dig_p5 as i64;
-        let dig_p6 = self.calib.dig_p6 as i64;
-        let dig_p7 = self.calib.dig_p7 as i64;
-        let dig_p8 = self.calib.dig_p8 as i64;
-        let dig_p9 = self.calib.dig_p9 as i64;
+        let dig_p1 = self.dig_p1 as i64;
+        let dig_p2 = self.dig_p2 as i64;
+        let dig_p3 = self.dig_p3 as i64;
+        let dig_p4 = self.dig_p4 as i64;
+        let dig_p5 = self.dig_p5 as i64;
+        let dig_p6 = self.dig_p6 as i64;
+        let dig_p7 = self.dig_p7 as i64;
+        let dig_p8 = self.dig_p8 as i64;
+        let dig_p9 = self.dig_p9 as i64;
 
         // 步骤1: 计算温度相关变量
         // var1 = t_fine - 128000
@@ -302,9 +169,9 @@ impl<'a, C: Clock> Driver<'a, C> {
         // var2 = var1 * var1 * dig_P6
         let mut var2 = var1 * var1 * dig_p6;
         // var2 = var2 + (var1 * dig_P5 << 17)
-        var2 = var2 + ((var1 * dig_p5) << 17);
+        var2 += (var1 * dig_p5) << 17;
         // var2 = var2 + (dig_P4 << 35)
-        var2 = var2 + (dig_p4 << 35);
+        var2 += dig_p4 << 35;
 
         // 步骤3: 计算主补偿项
         // var1 = ((var1 * var1 * dig_P3) >> 8) + ((var1 * dig_P2) << 12)
@@ -347,14 +214,14 @@ impl<'a, C: Clock> Driver<'a, C> {
     ///
     /// **返回**
     /// - `f32`: 补偿后的湿度值(%RH)，范围 0.0-100.0
-    fn compensate_humidity(&self, adc_h: i32, t_fine: i64) -> f32 {
+    pub fn compensate_humidity(&self, adc_h: i32, t_fine: i64) -> f32 {
         // 提取湿度补偿数据编译换算（注意湿度补偿运算是在32位有符号整型下转换的）
-        let dig_h1 = self.calib.dig_h1 as i32;
-        let dig_h2 = self.calib.dig_h2 as i32;
-        let dig_h3 = self.calib.dig_h3 as i32;
-        let dig_h4 = self.calib.dig_h4 as i32;
-        let dig_h5 = self.calib.dig_h5 as i32;
-        let dig_h6 = self.calib.dig_h6 as i32;
+        let dig_h1 = self.dig_h1 as i32;
+        let dig_h2 = self.dig_h2 as i32;
+        let dig_h3 = self.dig_h3 as i32;
+        let dig_h4 = self.dig_h4 as i32;
+        let dig_h5 = self.dig_h5 as i32;
+        let dig_h6 = self.dig_h6 as i32;
 
         // 步骤1: 计算温度调整项
         // var1 = t_fine - 76800
@@ -374,7 +241,203 @@ impl<'a, C: Clock> Driver<'a, C> {
         var5 = if var5 > 419430400 { 419430400 } else { var5 };
 
         // 返回相对湿度: Q22.10格式的湿度值 / 1024
-        (((var5 >> 12) as u32) / 1024) as f32
+        (var5 >> 12) as f32 / 1024.0
+    }
+}
+
+/// BME280 measurement
+///
+/// Built from the tuple returned by [`Driver::read`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    /// Temperature (°C)
+    pub temperature: f32,
+    /// Pressure (Pa)
+    pub pressure: f32,
+    /// Relative humidity (%RH)
+    pub humidity: f32,
+}
+
+impl From<(f32, f32, f32)> for Measurement {
+    fn from((temperature, pressure, humidity): (f32, f32, f32)) -> Self {
+        Self {
+            temperature,
+            pressure,
+            humidity,
+        }
+    }
+}
+
+/// BME280 sensor driver error
+pub enum Error<B: I2c<SevenBitAddress>> {
+    /// I2C bus raw error
+    Raw(B::Error),
+    /// Sensor initialization failed
+    Init,
+    /// Sensor busy
+    Busy,
+}
+
+impl<B> Debug for Error<B>
+where
+    B: I2c<SevenBitAddress>,
+    B::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Raw(err) => write!(f, "I2C bus communication error, {:?}", err),
+            Self::Init => write!(f, "The initialization of the BME280 sensor failed."),
+            Self::Busy => write!(f, "The BME280 sensor is busy."),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: I2c<SevenBitAddress>> std::fmt::Display for Error<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: I2c<SevenBitAddress>> std::error::Error for Error<B> {}
+
+#[cfg(feature = "defmt")]
+impl<B: I2c<SevenBitAddress>> defmt::Format for Error<B> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Raw(err) => defmt::write!(
+                f,
+                "I2C bus communication error, {}",
+                embedded_hal::i2c::Error::kind(err)
+            ),
+            Self::Init => defmt::write!(f, "The initialization of the BME280 sensor failed."),
+            Self::Busy => defmt::write!(f, "The BME280 sensor is busy."),
+        }
+    }
+}
+
+/// BME280 sensor driver
+pub struct Driver<'a, C: Clock> {
+    /// BME280 7bit address
+    /// - The default address is usually 0x76
+    address: u8,
+    /// BME280 Calibration params
+    calib: Calibration,
+    /// User calibration applied on every read
+    user_calibration: Record,
+    /// Delay implementation for embedded_timers
+    delay_impl: Delay<'a, C>,
+}
+
+impl<'a, C: Clock> Driver<'a, C> {
+    /// Read calibration data
+    fn read_calibration_data<B: I2c<SevenBitAddress>>(
+        bus: &mut B,
+        address: u8,
+    ) -> Result<Calibration, B::Error> {
+        // 读取温度/压力校准参数 (0x88-0x9F)
+        let mut tp_calib = [0u8; 24];
+        bus.write_read(address, &[0x88], &mut tp_calib)?;
+        // 读取湿度校准参数 (0xA1, 0xE1-0xE7)
+        let mut h_calib = [0u8; 8];
+        bus.write_read(address, &[0xA1], &mut h_calib[0..1])?;
+        bus.write_read(address, &[0xE1], &mut h_calib[1..8])?;
+        // OK
+        Ok(Calibration::from(&tp_calib, &h_calib))
+    }
+
+    /// Create an instance of the BME280 sensor driver
+    ///
+    /// Note: The driver will not hold this I2C bus internally
+    pub fn new<B: I2c<SevenBitAddress>>(
+        clock: &'a C,
+        bus: &mut B,
+        address: Option<u8>,
+    ) -> Result<Self, Error<B>> {
+        // 处理地址
+        let addr = address.unwrap_or(0x76);
+        // 创建延迟实例
+        let mut delay_impl = Delay::new(clock);
+
+        // 文档明确要求上电后需要等待2ms以上
+        delay_impl.delay(Duration::from_millis(3));
+
+        // 检查传感器是否就绪
+        let mut status = [0u8];
+        bus.write_read(addr, &[0xF3], &mut status)
+            .map_err(|err| Error::Raw(err))?;
+        // 检查状态
+        if status[0] & 0x01 != 0 {
+            return Err(Error::Init);
+        }
+
+        // 读取校准参数
+        let calib = Self::read_calibration_data(bus, addr).map_err(|err| Error::Raw(err))?;
+
+        let mut driver = Self {
+            address: addr,
+            calib,
+            user_calibration: Record::default(),
+            delay_impl,
+        };
+        // 配置采样率和工作模式
+        driver.configure(bus).map_err(|err| Error::Raw(err))?;
+
+        // OK
+        Ok(driver)
+    }
+
+    /// Write the oversampling, mode and filter settings
+    ///
+    /// Called by [`new`](Self::new). A soft reset puts the sensor back to sleep mode, call
+    /// this after [`reset`](Self::reset) to resume the measurements.
+    pub fn configure<B: I2c<SevenBitAddress>>(&mut self, bus: &mut B) -> Result<(), B::Error> {
+        // 配置湿度采样率 (osrs_h = 1x)
+        bus.write(self.address, &[0xF2, 0x01])?;
+        self.delay_impl.delay(Duration::from_millis(10));
+        // 配置温度、压力采样率 (osrs_t = 1x, osrs_p = 1x) 和正常模式
+        bus.write(self.address, &[0xF4, 0x27])?; // 00100111 = 0x27
+        self.delay_impl.delay(Duration::from_millis(10));
+        // 配置滤波器关闭，待机时间 0.5ms
+        bus.write(self.address, &[0xF5, 0x00])?;
+        self.delay_impl.delay(Duration::from_millis(10));
+        // OK
+        Ok(())
+    }
+
+    /// Read ADC raw data
+    fn read_raw_data<B: I2c<SevenBitAddress>>(
+        &self,
+        bus: &mut B,
+    ) -> Result<(i32, i32, i32), B::Error> {
+        // 声明缓冲区
+        let mut data = [0u8; 8];
+
+        // 读取原始数据
+        bus.write_read(self.address, &[0xF7], &mut data)?;
+
+        // 解析20位压力数据 (0xF7-0xF9)
+        let press_msb = data[0] as i32;
+        let press_lsb = data[1] as i32;
+        let press_xlsb = data[2] as i32;
+        let press_raw = (press_msb << 12) | (press_lsb << 4) | (press_xlsb >> 4);
+
+        // 解析20位温度数据 (0xFA-0xFC)
+        let temp_msb = data[3] as i32;
+        let temp_lsb = data[4] as i32;
+        let temp_xlsb = data[5] as i32;
+        let temp_raw = (temp_msb << 12) | (temp_lsb << 4) | (temp_xlsb >> 4);
+
+        // 解析16位湿度数据 (0xFD-0xFE)
+        let hum_msb = data[6] as i32;
+        let hum_lsb = data[7] as i32;
+        let hum_raw = (hum_msb << 8) | hum_lsb;
+
+        // OK
+        Ok((press_raw, temp_raw, hum_raw))
     }
 
     /// Read BME280 sensor data
@@ -386,15 +449,37 @@ impl<'a, C: Clock> Driver<'a, C> {
         let (adc_p, adc_t, adc_h) = self.read_raw_data(bus)?;
 
         // 使用补偿公式补偿数据
-        let (temperature, t_fine) = self.compensate_temperature(adc_t);
-        let pressure = self.compensate_pressure(adc_p, t_fine);
-        let humidity = self.compensate_humidity(adc_h, t_fine);
+        let (temperature, t_fine) = self.calib.compensate_temperature(adc_t);
+        let pressure = self.calib.compensate_pressure(adc_p, t_fine);
+        let humidity = self.calib.compensate_humidity(adc_h, t_fine);
+
+        // 应用用户校准
+        let temperature = self.user_calibration.temperature(temperature);
+        let pressure = self.user_calibration.pressure(pressure);
+        let humidity = self.user_calibration.humidity(humidity);
 
         // OK
         Ok((temperature, pressure, humidity))
     }
 
+    /// Calibration params read from the sensor NVM
+    pub fn calibration(&self) -> &Calibration {
+        &self.calib
+    }
+
+    /// User calibration applied on every read
+    pub fn user_calibration(&self) -> &Record {
+        &self.user_calibration
+    }
+
+    /// Change the user calibration applied on every read
+    pub fn set_user_calibration(&mut self, record: Record) {
+        self.user_calibration = record;
+    }
+
     /// Soft reset sensor
+    ///
+    /// Note: The sensor returns to sleep mode, call [`configure`](Self::configure) afterwards
     pub fn reset<B: I2c<SevenBitAddress>>(&mut self, bus: &mut B) -> Result<(), B::Error> {
         // 软重置
         bus.write(self.address, &[0xE0, 0xB6])?;
@@ -406,3 +491,322 @@ impl<'a, C: Clock> Driver<'a, C> {
         Ok(())
     }
 }
+
+/// BME280 sensor driver holding its I2C device
+///
+/// Unlike [`Driver`] the bus is not passed on every call. Give it a shared bus device of
+/// `embedded_hal_bus` (e.g. `RefCellDevice`) to put several sensors on one bus.
+pub struct OwnedDriver<'a, C: Clock, B: I2c<SevenBitAddress>> {
+    /// Borrow-per-call driver
+    driver: Driver<'a, C>,
+    /// I2C device used by the driver
+    bus: B,
+}
+
+impl<'a, C: Clock, B: I2c<SevenBitAddress>> OwnedDriver<'a, C, B> {
+    /// Create an instance of the BME280 sensor driver holding the I2C device
+    ///
+    /// - address: The default address is 0x76
+    pub fn new(clock: &'a C, mut bus: B, address: Option<u8>) -> Result<Self, Error<B>> {
+        let driver = Driver::new(clock, &mut bus, address)?;
+        // OK
+        Ok(Self { driver, bus })
+    }
+
+    /// Read BME280 sensor data
+    pub fn read(&mut self) -> Result<(f32, f32, f32), B::Error> {
+        self.driver.read(&mut self.bus)
+    }
+
+    /// Calibration params read from the sensor NVM
+    pub fn calibration(&self) -> &Calibration {
+        self.driver.calibration()
+    }
+
+    /// User calibration applied on every read
+    pub fn user_calibration(&self) -> &Record {
+        self.driver.user_calibration()
+    }
+
+    /// Change the user calibration applied on every read
+    pub fn set_user_calibration(&mut self, record: Record) {
+        self.driver.set_user_calibration(record);
+    }
+
+    /// Soft reset sensor
+    ///
+    /// Note: The sensor returns to sleep mode, call [`configure`](Self::configure) afterwards
+    pub fn reset(&mut self) -> Result<(), B::Error> {
+        self.driver.reset(&mut self.bus)
+    }
+
+    /// Write the oversampling, mode and filter settings
+    pub fn configure(&mut self) -> Result<(), B::Error> {
+        self.driver.configure(&mut self.bus)
+    }
+
+    /// Release the borrow-per-call driver and the I2C device
+    pub fn release(self) -> (Driver<'a, C>, B) {
+        (self.driver, self.bus)
+    }
+}
+
+#[cfg(feature = "embedded-hal-bus")]
+impl<'a, 'b, C: Clock, T: I2c<SevenBitAddress>> OwnedDriver<'a, C, RefCellDevice<'b, T>> {
+    /// Create the driver on a bus shared through a `RefCell`
+    pub fn new_ref_cell(
+        clock: &'a C,
+        bus: &'b RefCell<T>,
+        address: Option<u8>,
+    ) -> Result<Self, Error<RefCellDevice<'b, T>>> {
+        Self::new(clock, RefCellDevice::new(bus), address)
+    }
+}
+
+#[cfg(feature = "embedded-hal-bus")]
+impl<'a, 'b, C: Clock, T: I2c<SevenBitAddress>> OwnedDriver<'a, C, CriticalSectionDevice<'b, T>> {
+    /// Create the driver on a bus shared through a `critical_section::Mutex`
+    pub fn new_critical_section(
+        clock: &'a C,
+        bus: &'b critical_section::Mutex<RefCell<T>>,
+        address: Option<u8>,
+    ) -> Result<Self, Error<CriticalSectionDevice<'b, T>>> {
+        Self::new(clock, CriticalSectionDevice::new(bus), address)
+    }
+}
+
+#[cfg(all(feature = "embedded-hal-bus", feature = "std"))]
+impl<'a, 'b, C: Clock, T: I2c<SevenBitAddress>> OwnedDriver<'a, C, MutexDevice<'b, T>> {
+    /// Create the driver on a bus shared through a `std::sync::Mutex`
+    pub fn new_mutex(
+        clock: &'a C,
+        bus: &'b std::sync::Mutex<T>,
+        address: Option<u8>,
+    ) -> Result<Self, Error<MutexDevice<'b, T>>> {
+        Self::new(clock, MutexDevice::new(bus), address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Calibration, Driver, Error, Measurement, OwnedDriver};
+    use crate::calibration::{Correction, Record};
+    use crate::mock::{Bme280Calibration, Bme280Device, MockClock, MockError, BME280_CALIBRATION};
+
+    #[test]
+    fn configures_sensor_on_init() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+
+        assert!(Driver::new(&clock, &mut device, None).is_ok());
+        assert_eq!(device.register(0xF2), 0x01);
+        assert_eq!(device.register(0xF4), 0x27);
+        assert_eq!(device.register(0xF5), 0x00);
+    }
+
+    #[test]
+    fn compensates_datasheet_example() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_raw(415148, 519888, 30000);
+
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+        let (temperature, pressure, humidity) = driver.read(&mut device).unwrap();
+        // 数据手册示例: 25.08°C, 100653.27Pa
+        assert!((temperature - 25.08).abs() < 0.001);
+        assert!((pressure - 100653.27).abs() < 0.5);
+        // 按数据手册整数补偿公式计算结果为55.00%RH
+        assert!((humidity - 55.00).abs() < 0.01);
+    }
+
+    /// Humidity calibration with the given dig_H4 ~ dig_H6 written into the mock NVM
+    fn humidity_calibration(h4: i16, h5: i16, h6: i8) -> Bme280Calibration {
+        Bme280Calibration {
+            h: (
+                BME280_CALIBRATION.h.0,
+                BME280_CALIBRATION.h.1,
+                0,
+                h4,
+                h5,
+                h6,
+            ),
+            ..BME280_CALIBRATION
+        }
+    }
+
+    #[test]
+    fn humidity_calibration_is_eight_bytes() {
+        // 0xA1, 0xE1 ~ 0xE7 共8字节，每个字节都参与解析
+        let calib = Calibration::from(&[0; 24], &[0x4B, 0x6A, 0x01, 0x07, 0x14, 0x25, 0x03, 0x1E]);
+        assert_eq!(calib.dig_h1, 0x4B);
+        assert_eq!(calib.dig_h2, 0x016A);
+        assert_eq!(calib.dig_h3, 0x07);
+        assert_eq!(calib.dig_h4, 0x145);
+        assert_eq!(calib.dig_h5, 0x032);
+        assert_eq!(calib.dig_h6, 0x1E);
+    }
+
+    #[test]
+    fn dig_h4_and_dig_h5_are_signed() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_calibration(&humidity_calibration(-300, -1000, 30));
+
+        let driver = Driver::new(&clock, &mut device, None).unwrap();
+        assert_eq!(driver.calibration().dig_h4, -300);
+        assert_eq!(driver.calibration().dig_h5, -1000);
+    }
+
+    #[test]
+    fn dig_h4_and_dig_h5_split_the_shared_nibbles() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_calibration(&humidity_calibration(0x123, 0x456, 30));
+        // dig_H4 取 0xE5 的低4位，dig_H5 取高4位
+        assert_eq!(device.register(0xE5), 0x63);
+
+        let driver = Driver::new(&clock, &mut device, None).unwrap();
+        assert_eq!(driver.calibration().dig_h4, 0x123);
+        assert_eq!(driver.calibration().dig_h5, 0x456);
+    }
+
+    #[test]
+    fn dig_h6_is_read_from_0xe7() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_calibration(&humidity_calibration(324, 0x7F, -7));
+
+        let driver = Driver::new(&clock, &mut device, None).unwrap();
+        assert_eq!(driver.calibration().dig_h6, -7);
+    }
+
+    #[test]
+    fn humidity_keeps_fractional_part() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        let driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        let (_, t_fine) = driver.calibration().compensate_temperature(519888);
+        let humidity = driver.calibration().compensate_humidity(30000, t_fine);
+        // Q22.10 格式保留 1/1024 %RH 的分辨率，不能截断为整数
+        assert!(humidity.fract() > 0.0);
+        assert!((humidity - 55.00).abs() < 0.01);
+    }
+
+    #[test]
+    fn applies_user_calibration() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_raw(415148, 519888, 30000);
+
+        let mut driver = OwnedDriver::new(&clock, &mut device, None).unwrap();
+        driver.set_user_calibration(Record {
+            pressure: Correction::from_offset(-53.27),
+            ..Record::default()
+        });
+        let (temperature, pressure, _) = driver.read().unwrap();
+        assert!((temperature - 25.08).abs() < 0.001);
+        assert!((pressure - 100600.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn measurement_keeps_field_order() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_raw(415148, 519888, 30000);
+
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+        let measurement = Measurement::from(driver.read(&mut device).unwrap());
+        assert!((measurement.temperature - 25.08).abs() < 0.001);
+        assert!((measurement.pressure - 100653.27).abs() < 0.5);
+        assert!((measurement.humidity - 55.00).abs() < 0.01);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn calibration_round_trips_through_serde() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_raw(415148, 519888, 30000);
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        let json = serde_json::to_string(driver.calibration()).unwrap();
+        let calibration: Calibration = serde_json::from_str(&json).unwrap();
+        assert_eq!(&calibration, driver.calibration());
+
+        // 反序列化的校准参数可以离线补偿原始数据
+        let (temperature, _) = calibration.compensate_temperature(519888);
+        assert!((temperature - 25.08).abs() < 0.001);
+
+        let measurement = Measurement::from(driver.read(&mut device).unwrap());
+        let json = serde_json::to_string(&measurement).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Measurement>(&json).unwrap(),
+            measurement
+        );
+    }
+
+    #[test]
+    fn init_fails_while_copying_nvm() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_status(0x01);
+
+        assert!(matches!(
+            Driver::new(&clock, &mut device, None),
+            Err(Error::Init)
+        ));
+    }
+
+    #[test]
+    fn wrong_address_is_raw_error() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+
+        assert!(matches!(
+            Driver::new(&clock, &mut device, Some(0x77)),
+            Err(Error::Raw(MockError::Nack))
+        ));
+    }
+
+    #[test]
+    fn reset_sends_soft_reset_command() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        driver.reset(&mut device).unwrap();
+        assert_eq!(device.resets(), 1);
+        assert_eq!(device.register(0xF4), 0x00);
+
+        driver.configure(&mut device).unwrap();
+        assert_eq!(device.register(0xF2), 0x01);
+        assert_eq!(device.register(0xF4), 0x27);
+    }
+
+    #[test]
+    fn owned_driver_holds_the_bus() {
+        let clock = MockClock::new();
+        let mut device = Bme280Device::new();
+        device.set_raw(415148, 519888, 30000);
+
+        let mut driver = OwnedDriver::new(&clock, &mut device, None).unwrap();
+        let (temperature, _, _) = driver.read().unwrap();
+        assert!((temperature - 25.08).abs() < 0.001);
+        driver.reset().unwrap();
+
+        let (_, device) = driver.release();
+        assert_eq!(device.resets(), 1);
+    }
+
+    #[cfg(all(feature = "embedded-hal-bus", feature = "std"))]
+    #[test]
+    fn owned_driver_on_mutex_bus() {
+        let clock = MockClock::new();
+        let bus = std::sync::Mutex::new(Bme280Device::new());
+
+        let mut driver = OwnedDriver::new_mutex(&clock, &bus, None).unwrap();
+        assert!(driver.read().is_ok());
+        assert_eq!(bus.lock().unwrap().register(0xF4), 0x27);
+    }
+}
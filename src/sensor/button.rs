@@ -65,6 +65,20 @@ impl<P: InputPin> std::fmt::Display for AntishakeDriverError<P> {
 #[cfg(feature = "std")]
 impl<P: InputPin> std::error::Error for AntishakeDriverError<P> {}
 
+#[cfg(feature = "defmt")]
+impl<P: InputPin> defmt::Format for AntishakeDriverError<P> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Raw(err) => defmt::write!(
+                f,
+                "The button signal input is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::NotReady => defmt::write!(f, "The button sensor is not ready."),
+        }
+    }
+}
+
 /// Anti-shake button sensor driver
 ///
 /// TODO: Unfortunately, I currently don't have a good way to decouple interrupts
@@ -117,7 +131,7 @@ impl<P: InputPin> AntishakeDriver<P> {
             if pin.is_high()? {
                 cnt = (cnt << 1) | 1;
             } else {
-                cnt = cnt << 1;
+                cnt <<= 1;
             }
         }
         if cnt == 0xFF {
@@ -145,3 +159,58 @@ impl<P: InputPin> AntishakeDriver<P> {
         Ok(self.last_state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AntishakeDriver, AntishakeDriverError, PinState, TransientDriver};
+    use crate::mock::{InputState, MockError};
+
+    #[test]
+    fn transient_reports_pressed_level() {
+        let state = InputState::new(&[false, true]);
+        let mut button = TransientDriver::new(state.pin(), PinState::Low);
+        assert!(button.state().unwrap());
+        assert!(!button.state().unwrap());
+    }
+
+    #[test]
+    fn antishake_ignores_bouncing_input() {
+        let state = InputState::new(&[true]);
+        let mut button = AntishakeDriver::new(state.pin(), PinState::High).unwrap();
+        assert!(button.state().unwrap());
+
+        // 抖动的输入保持上一次的稳定状态
+        state.script(&[false, true, false, true, false, true, false, true]);
+        assert!(button.state().unwrap());
+
+        state.script(&[false]);
+        assert!(!button.state().unwrap());
+    }
+
+    #[test]
+    fn antishake_fails_on_unstable_input() {
+        let state = InputState::new(&[
+            true, false, true, false, true, false, true, false, true, false, true, false, true,
+            false, true, false, true, false, true, false, true, false, true, false, true, false,
+            true, false, true, false, true, false, true, false, true, false, true, false, true,
+            false, true, false, true, false, true, false, true, false, true, false, true, false,
+            true, false, true, false, true, false, true, false, true, false, true, false, true,
+            false, true, false, true, false, true, false, true, false, true, false, true, false,
+            true, false, false,
+        ]);
+        assert!(matches!(
+            AntishakeDriver::new(state.pin(), PinState::High),
+            Err(AntishakeDriverError::NotReady)
+        ));
+    }
+
+    #[test]
+    fn antishake_reports_input_error() {
+        let state = InputState::new(&[true]);
+        state.set_fail(true);
+        assert!(matches!(
+            AntishakeDriver::new(state.pin(), PinState::High),
+            Err(AntishakeDriverError::Raw(MockError::Io))
+        ));
+    }
+}
@@ -1,4 +1,13 @@
-use embedded_hal::{digital::OutputPin, pwm::SetDutyCycle};
+use core::{
+    fmt::{Debug, Formatter},
+    time::Duration,
+};
+
+use embedded_hal::{
+    digital::{InputPin, OutputPin},
+    pwm::SetDutyCycle,
+};
+use embedded_timers::{clock::Clock, instant::Instant};
 
 pub use embedded_hal::digital::PinState;
 
@@ -13,19 +22,32 @@ pub struct Driver<P: OutputPin> {
     pin: P,
     /// Output level type
     out_level: PinState,
+    /// Whether the dc relay is on
+    is_on: bool,
 }
 
 impl<P: OutputPin> Driver<P> {
     /// Create an instance of the dc relay sensor driver
     ///
     /// - out_level: What level should be used to make the dc relay on
-    pub fn new(pin: P, out_level: PinState) -> Self {
-        Self { pin, out_level }
+    /// - initial_on: The safe initial state applied immediately, usually false (off)
+    pub fn new(pin: P, out_level: PinState, initial_on: bool) -> Result<Self, P::Error> {
+        let mut this = Self {
+            pin,
+            out_level,
+            is_on: initial_on,
+        };
+        // 立即输出初始状态，避免继电器处于不确定的状态
+        this.set(initial_on)?;
+        // OK
+        Ok(this)
     }
 
     /// On the dc relay sensor
     pub fn on(&mut self) -> Result<(), P::Error> {
-        self.pin.set_state(self.out_level)
+        self.pin.set_state(self.out_level)?;
+        self.is_on = true;
+        Ok(())
     }
 
     /// Off the dc relay sensor
@@ -33,10 +55,797 @@ impl<P: OutputPin> Driver<P> {
         match self.out_level {
             PinState::High => self.pin.set_low(),
             PinState::Low => self.pin.set_high(),
+        }?;
+        self.is_on = false;
+        Ok(())
+    }
+
+    /// Set the dc relay sensor state
+    ///
+    /// - True: On the dc relay
+    /// - False: Off the dc relay
+    pub fn set(&mut self, on: bool) -> Result<(), P::Error> {
+        if on {
+            self.on()
+        } else {
+            self.off()
+        }
+    }
+
+    /// Toggle the dc relay sensor state
+    pub fn toggle(&mut self) -> Result<(), P::Error> {
+        self.set(!self.is_on)
+    }
+
+    /// Get the dc relay sensor state last set by the driver
+    ///
+    /// - True: The dc relay is on
+    /// - False: The dc relay is off
+    pub fn is_on(&self) -> bool {
+        self.is_on
+    }
+}
+
+/// Feedback dc relay sensor Error
+pub enum FeedbackDriverError<P: OutputPin, F: InputPin> {
+    /// Digital I/O output error
+    Output(P::Error),
+    /// Digital I/O input error
+    Input(F::Error),
+    /// The auxiliary contact does not match the commanded state
+    Mismatch,
+}
+
+impl<P, F> Debug for FeedbackDriverError<P, F>
+where
+    P: OutputPin,
+    P::Error: Debug,
+    F: InputPin,
+    F::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Output(err) => write!(f, "The dc relay signal output is incorrect, {:?}.", err),
+            Self::Input(err) => write!(f, "The dc relay feedback input is incorrect, {:?}.", err),
+            Self::Mismatch => write!(
+                f,
+                "The dc relay feedback does not match the commanded state."
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: OutputPin, F: InputPin> std::fmt::Display for FeedbackDriverError<P, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: OutputPin, F: InputPin> std::error::Error for FeedbackDriverError<P, F> {}
+
+#[cfg(feature = "defmt")]
+impl<P: OutputPin, F: InputPin> defmt::Format for FeedbackDriverError<P, F> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Output(err) => defmt::write!(
+                f,
+                "The dc relay signal output is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::Input(err) => defmt::write!(
+                f,
+                "The dc relay feedback input is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::Mismatch => defmt::write!(
+                f,
+                "The dc relay feedback does not match the commanded state."
+            ),
+        }
+    }
+}
+
+/// DC relay sensor driver with state feedback
+///
+/// Verifies the commanded state through an auxiliary contact wired to a GPIO input,
+/// so a welded or stuck relay can be detected.
+///
+/// Note: Relays need a few milliseconds to operate, call [`verify`](Self::verify)
+/// only after the operate time given in the relay datasheet has elapsed
+pub struct FeedbackDriver<P: OutputPin, F: InputPin> {
+    /// DC relay driver
+    driver: Driver<P>,
+    /// Auxiliary contact used GPIO pin
+    feedback: F,
+    /// Input level of the auxiliary contact when the dc relay is on
+    feedback_level: PinState,
+}
+
+impl<P: OutputPin, F: InputPin> FeedbackDriver<P, F> {
+    /// Create an instance of the feedback dc relay sensor driver
+    ///
+    /// - feedback_level: The level input from the auxiliary contact when the dc relay is on
+    pub fn new(driver: Driver<P>, feedback: F, feedback_level: PinState) -> Self {
+        Self {
+            driver,
+            feedback,
+            feedback_level,
+        }
+    }
+
+    /// On the dc relay sensor
+    pub fn on(&mut self) -> Result<(), FeedbackDriverError<P, F>> {
+        self.driver
+            .on()
+            .map_err(|err| FeedbackDriverError::Output(err))
+    }
+
+    /// Off the dc relay sensor
+    pub fn off(&mut self) -> Result<(), FeedbackDriverError<P, F>> {
+        self.driver
+            .off()
+            .map_err(|err| FeedbackDriverError::Output(err))
+    }
+
+    /// Set the dc relay sensor state
+    pub fn set(&mut self, on: bool) -> Result<(), FeedbackDriverError<P, F>> {
+        self.driver
+            .set(on)
+            .map_err(|err| FeedbackDriverError::Output(err))
+    }
+
+    /// Toggle the dc relay sensor state
+    pub fn toggle(&mut self) -> Result<(), FeedbackDriverError<P, F>> {
+        self.driver
+            .toggle()
+            .map_err(|err| FeedbackDriverError::Output(err))
+    }
+
+    /// Get the dc relay sensor state last set by the driver
+    pub fn is_on(&self) -> bool {
+        self.driver.is_on()
+    }
+
+    /// Read the dc relay state from the auxiliary contact
+    ///
+    /// - True: The dc relay is on
+    /// - False: The dc relay is off
+    pub fn feedback_state(&mut self) -> Result<bool, FeedbackDriverError<P, F>> {
+        let is_high = self
+            .feedback
+            .is_high()
+            .map_err(|err| FeedbackDriverError::Input(err))?;
+        Ok(is_high == (self.feedback_level == PinState::High))
+    }
+
+    /// Verify that the auxiliary contact matches the commanded state
+    pub fn verify(&mut self) -> Result<(), FeedbackDriverError<P, F>> {
+        if self.feedback_state()? != self.driver.is_on() {
+            return Err(FeedbackDriverError::Mismatch);
+        }
+        Ok(())
+    }
+
+    /// Release the wrapped dc relay driver and the feedback pin
+    pub fn release(self) -> (Driver<P>, F) {
+        (self.driver, self.feedback)
+    }
+}
+
+/// What to do when a state change violates the relay protection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProtectionMode {
+    /// Reject the state change and return an error
+    Reject,
+    /// Remember the state change and apply it in [`ProtectedDriver::update`] once it is allowed
+    Defer,
+}
+
+/// DC relay switching protection config
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Protection {
+    /// Minimum time the relay must stay on before it may be turned off
+    pub min_on_time: Duration,
+    /// Minimum time the relay must stay off before it may be turned on
+    pub min_off_time: Duration,
+    /// Maximum number of state changes within `switch_window`, 0 means unlimited
+    pub max_switches: u32,
+    /// Time window of `max_switches`, starting at the first state change of the window
+    pub switch_window: Duration,
+    /// What to do when a state change violates the protection
+    pub mode: ProtectionMode,
+}
+
+impl Default for Protection {
+    fn default() -> Self {
+        Self {
+            min_on_time: Duration::ZERO,
+            min_off_time: Duration::ZERO,
+            max_switches: 0,
+            switch_window: Duration::ZERO,
+            mode: ProtectionMode::Reject,
+        }
+    }
+}
+
+/// Protected DC relay sensor driver error
+pub enum ProtectedDriverError<P: OutputPin> {
+    /// Digital I/O output error
+    Raw(P::Error),
+    /// The minimum on-time or off-time has not elapsed yet
+    Dwell,
+    /// The maximum number of state changes within the time window has been reached
+    RateLimited,
+}
+
+impl<P> Debug for ProtectedDriverError<P>
+where
+    P: OutputPin,
+    P::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Raw(err) => write!(f, "The dc relay signal output is incorrect, {:?}.", err),
+            Self::Dwell => write!(f, "The dc relay minimum dwell time has not elapsed."),
+            Self::RateLimited => write!(f, "The dc relay switching rate limit has been reached."),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl<P: OutputPin> std::fmt::Display for ProtectedDriverError<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: OutputPin> std::error::Error for ProtectedDriverError<P> {}
+
+#[cfg(feature = "defmt")]
+impl<P: OutputPin> defmt::Format for ProtectedDriverError<P> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Raw(err) => defmt::write!(
+                f,
+                "The dc relay signal output is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::Dwell => defmt::write!(f, "The dc relay minimum dwell time has not elapsed."),
+            Self::RateLimited => {
+                defmt::write!(f, "The dc relay switching rate limit has been reached.")
+            }
+        }
+    }
+}
+
+/// Protected DC relay sensor driver
+///
+/// Relays and contactors wear out quickly when a misbehaving control loop chatters them.
+/// This driver wraps [`Driver`] and enforces a minimum on-time, a minimum off-time
+/// and a maximum number of state changes per time window.
+/// Depending on [`ProtectionMode`], a violating state change is rejected or deferred.
+pub struct ProtectedDriver<'a, C: Clock, P: OutputPin> {
+    /// DC relay driver
+    driver: Driver<P>,
+    /// Protection config
+    protection: Protection,
+    /// State requested in defer mode but not applied yet
+    pending: Option<bool>,
+    /// Time of the last state change
+    last_switch: Option<C::Instant>,
+    /// Start time of the current switching window
+    window_start: Option<C::Instant>,
+    /// Number of state changes within the current switching window
+    window_switches: u32,
+    /// Number of on/off cycles, counted each time the relay is turned on
+    cycles: u32,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, P: OutputPin> ProtectedDriver<'a, C, P> {
+    /// Create an instance of the protected dc relay sensor driver
+    ///
+    /// Note: The dc relay keeps the state applied by [`Driver::new`], a relay that is already on
+    /// must stay on for the minimum on-time counted from now
+    pub fn new(clock: &'a C, driver: Driver<P>, protection: Protection) -> Self {
+        // 初始为开启状态时，从现在开始计算最小开启时间
+        let last_switch = driver.is_on().then(|| clock.now());
+        Self {
+            driver,
+            protection,
+            pending: None,
+            last_switch,
+            window_start: None,
+            window_switches: 0,
+            cycles: 0,
+            clock_impl: clock,
+        }
+    }
+
+    /// On the dc relay sensor
+    ///
+    /// In defer mode, `Ok` is returned even if the change is postponed, see [`pending`](Self::pending)
+    pub fn on(&mut self) -> Result<(), ProtectedDriverError<P>> {
+        self.request(true)
+    }
+
+    /// Off the dc relay sensor
+    ///
+    /// In defer mode, `Ok` is returned even if the change is postponed, see [`pending`](Self::pending)
+    pub fn off(&mut self) -> Result<(), ProtectedDriverError<P>> {
+        self.request(false)
+    }
+
+    /// Off the dc relay sensor immediately, ignoring the protection
+    ///
+    /// Intended for emergency stops, the state change is still counted
+    pub fn force_off(&mut self) -> Result<(), ProtectedDriverError<P>> {
+        self.pending = None;
+        if self.driver.is_on() {
+            self.switch(false)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the deferred state change once the protection allows it
+    ///
+    /// - True: A deferred state change has been applied
+    /// - False: Nothing has changed
+    pub fn update(&mut self) -> Result<bool, ProtectedDriverError<P>> {
+        let Some(target) = self.pending else {
+            return Ok(false);
+        };
+        if self.check(target).is_err() {
+            return Ok(false);
+        }
+        self.pending = None;
+        self.switch(target)?;
+        Ok(true)
+    }
+
+    /// Get the dc relay sensor state
+    pub fn is_on(&self) -> bool {
+        self.driver.is_on()
+    }
+
+    /// The deferred state change waiting for [`update`](Self::update)
+    ///
+    /// - Some(True): The relay will be turned on
+    /// - Some(False): The relay will be turned off
+    pub fn pending(&self) -> Option<bool> {
+        self.pending
+    }
+
+    /// Number of on/off cycles, counted each time the relay is turned on
+    pub fn cycles(&self) -> u32 {
+        self.cycles
+    }
+
+    /// Set the number of on/off cycles, e.g. restored from persistent storage
+    pub fn set_cycles(&mut self, cycles: u32) {
+        self.cycles = cycles;
+    }
+
+    /// Get the protection config
+    pub fn protection(&self) -> &Protection {
+        &self.protection
+    }
+
+    /// Set the protection config
+    pub fn set_protection(&mut self, protection: Protection) {
+        self.protection = protection;
+    }
+
+    /// Release the wrapped dc relay driver
+    pub fn release(self) -> Driver<P> {
+        self.driver
+    }
+
+    /// Handle a state change request
+    fn request(&mut self, target: bool) -> Result<(), ProtectedDriverError<P>> {
+        if target == self.driver.is_on() {
+            // 状态未改变，取消等待中的切换
+            self.pending = None;
+            return Ok(());
+        }
+        match self.check(target) {
+            Ok(()) => {
+                self.pending = None;
+                self.switch(target)
+            }
+            Err(err) => match self.protection.mode {
+                ProtectionMode::Reject => Err(err),
+                ProtectionMode::Defer => {
+                    self.pending = Some(target);
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// Check whether the state change is allowed now
+    fn check(&self, target: bool) -> Result<(), ProtectedDriverError<P>> {
+        let now = self.clock_impl.now();
+        // 检查最小保持时间
+        if let Some(last) = self.last_switch {
+            let dwell = if target {
+                self.protection.min_off_time
+            } else {
+                self.protection.min_on_time
+            };
+            if now.duration_since(last) < dwell {
+                return Err(ProtectedDriverError::Dwell);
+            }
+        }
+        // 检查时间窗口内的切换次数
+        if self.protection.max_switches > 0 {
+            if let Some(start) = self.window_start {
+                if now.duration_since(start) < self.protection.switch_window
+                    && self.window_switches >= self.protection.max_switches
+                {
+                    return Err(ProtectedDriverError::RateLimited);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the relay state and record it
+    fn switch(&mut self, target: bool) -> Result<(), ProtectedDriverError<P>> {
+        self.driver
+            .set(target)
+            .map_err(|err| ProtectedDriverError::Raw(err))?;
+
+        let now = self.clock_impl.now();
+        // 时间窗口已过期，开启新的时间窗口
+        let expired = match self.window_start {
+            Some(start) => now.duration_since(start) >= self.protection.switch_window,
+            None => true,
+        };
+        if expired {
+            self.window_start = Some(now);
+            self.window_switches = 0;
+        }
+        self.window_switches += 1;
+        self.last_switch = Some(now);
+        if target {
+            self.cycles = self.cycles.wrapping_add(1);
+        }
+        // OK
+        Ok(())
+    }
+}
+
+/// Scheduled dc relay state change
+#[derive(Clone, Copy)]
+enum Schedule<I> {
+    /// Turn on at the given time, optionally turning off again after a duration
+    OnAt(I, Option<Duration>),
+    /// Turn off at the given time
+    OffAt(I),
+}
+
+/// Timed dc relay sensor driver
+///
+/// Adds non-blocking timed modes to [`Driver`], e.g. "energize the door strike for 3 seconds".
+/// Deadlines are stored as absolute instants of the clock, so if the caller loop stalls,
+/// the overdue change is applied by the very next [`update`](Self::update) call.
+pub struct TimedDriver<'a, C: Clock, P: OutputPin> {
+    /// DC relay driver
+    driver: Driver<P>,
+    /// Scheduled state change
+    schedule: Option<Schedule<C::Instant>>,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, P: OutputPin> TimedDriver<'a, C, P> {
+    /// Create an instance of the timed dc relay sensor driver
+    pub fn new(clock: &'a C, driver: Driver<P>) -> Self {
+        Self {
+            driver,
+            schedule: None,
+            clock_impl: clock,
+        }
+    }
+
+    /// On the dc relay sensor, cancelling any scheduled change
+    pub fn on(&mut self) -> Result<(), P::Error> {
+        self.schedule = None;
+        self.driver.on()
+    }
+
+    /// Off the dc relay sensor, cancelling any scheduled change
+    pub fn off(&mut self) -> Result<(), P::Error> {
+        self.schedule = None;
+        self.driver.off()
+    }
+
+    /// On the dc relay sensor now and off it automatically after the duration
+    ///
+    /// Calling it again while the dc relay is on restarts the timer.
+    /// A duration beyond the range of the clock (e.g. `Duration::MAX`) keeps it on.
+    pub fn on_for(&mut self, duration: Duration) -> Result<(), P::Error> {
+        self.driver.on()?;
+        // 超出时钟范围的时间永远不会到达
+        self.schedule = self
+            .clock_impl
+            .now()
+            .checked_add(duration)
+            .map(Schedule::OffAt);
+        Ok(())
+    }
+
+    /// On the dc relay sensor after the delay and keep it on
+    ///
+    /// A delay beyond the range of the clock (e.g. `Duration::MAX`) never turns it on
+    pub fn on_after(&mut self, delay: Duration) -> Result<(), P::Error> {
+        self.schedule = self
+            .clock_impl
+            .now()
+            .checked_add(delay)
+            .map(|at| Schedule::OnAt(at, None));
+        self.update()?;
+        Ok(())
+    }
+
+    /// On the dc relay sensor after the delay, then off it automatically after the duration
+    ///
+    /// Delays and durations beyond the range of the clock behave like [`on_after`](Self::on_after)
+    /// and [`on_for`](Self::on_for)
+    pub fn pulse(&mut self, delay: Duration, duration: Duration) -> Result<(), P::Error> {
+        self.schedule = self
+            .clock_impl
+            .now()
+            .checked_add(delay)
+            .map(|at| Schedule::OnAt(at, Some(duration)));
+        self.update()?;
+        Ok(())
+    }
+
+    /// Apply the scheduled state change once it is due
+    ///
+    /// - True: The dc relay state has changed
+    /// - False: Nothing has changed
+    pub fn update(&mut self) -> Result<bool, P::Error> {
+        let Some(schedule) = self.schedule else {
+            return Ok(false);
+        };
+        let now = self.clock_impl.now();
+        match schedule {
+            Schedule::OnAt(at, off_after) if now >= at => {
+                // 从计划的开启时间开始计时，保证总时长不会因为调用延迟而变长
+                let off_at = off_after.and_then(|duration| at.checked_add(duration));
+                if off_at.is_some_and(|off_at| now >= off_at) {
+                    // 整个脉冲都已经过期，不再开启
+                    self.schedule = None;
+                    return Ok(false);
+                }
+                self.schedule = off_at.map(Schedule::OffAt);
+                self.driver.on()?;
+                Ok(true)
+            }
+            Schedule::OffAt(at) if now >= at => {
+                self.schedule = None;
+                self.driver.off()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Cancel the scheduled state change, keeping the current state
+    pub fn cancel(&mut self) {
+        self.schedule = None;
+    }
+
+    /// Time left until the scheduled state change
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = self.clock_impl.now();
+        match self.schedule? {
+            Schedule::OnAt(at, _) | Schedule::OffAt(at) => Some(at.duration_since(now)),
+        }
+    }
+
+    /// Get the dc relay sensor state
+    pub fn is_on(&self) -> bool {
+        self.driver.is_on()
+    }
+
+    /// Release the wrapped dc relay driver
+    pub fn release(self) -> Driver<P> {
+        self.driver
+    }
+}
+
+/// DC relay interlock group error
+pub enum InterlockError<P: OutputPin> {
+    /// Digital I/O output error
+    Raw(P::Error),
+    /// The member index is out of range
+    Index,
+}
+
+impl<P> Debug for InterlockError<P>
+where
+    P: OutputPin,
+    P::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Raw(err) => write!(f, "The dc relay signal output is incorrect, {:?}.", err),
+            Self::Index => write!(f, "The dc relay interlock member index is out of range."),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: OutputPin> std::fmt::Display for InterlockError<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<P: OutputPin> std::error::Error for InterlockError<P> {}
+
+#[cfg(feature = "defmt")]
+impl<P: OutputPin> defmt::Format for InterlockError<P> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Raw(err) => defmt::write!(
+                f,
+                "The dc relay signal output is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::Index => defmt::write!(f, "The dc relay interlock member index is out of range."),
+        }
+    }
+}
+
+/// Mutually exclusive dc relay interlock group
+///
+/// Guarantees that at most one member of the group is on, e.g. forward/reverse motor contactors
+/// or heating/cooling relays. When switching from one member to another, the previous member
+/// is turned off first and the next member is only turned on after the dead time has elapsed.
+///
+/// The switch-on after the dead time is non-blocking, call [`update`](Self::update) periodically.
+pub struct InterlockGroup<'a, C: Clock, P: OutputPin, const N: usize> {
+    /// Member dc relay drivers
+    members: [Driver<P>; N],
+    /// Minimum time between turning one member off and the next one on
+    dead_time: Duration,
+    /// Index of the member that is on
+    active: Option<usize>,
+    /// Index of the member waiting for the dead time to elapse
+    pending: Option<usize>,
+    /// Time the last member was turned off
+    last_off: Option<C::Instant>,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, P: OutputPin, const N: usize> InterlockGroup<'a, C, P, N> {
+    /// Create an instance of the dc relay interlock group
+    ///
+    /// Note: All members are turned off when the group is created
+    pub fn new(
+        clock: &'a C,
+        mut members: [Driver<P>; N],
+        dead_time: Duration,
+    ) -> Result<Self, InterlockError<P>> {
+        // 先断开所有继电器，确保处于安全状态
+        for member in members.iter_mut() {
+            member.off().map_err(|err| InterlockError::Raw(err))?;
+        }
+        // OK
+        Ok(Self {
+            members,
+            dead_time,
+            active: None,
+            pending: None,
+            last_off: Some(clock.now()),
+            clock_impl: clock,
+        })
+    }
+
+    /// Select the member to turn on
+    ///
+    /// The member currently on is turned off immediately. The selected member is turned on
+    /// right away if the dead time has already elapsed, otherwise by a later [`update`](Self::update).
+    pub fn select(&mut self, index: usize) -> Result<(), InterlockError<P>> {
+        if index >= N {
+            return Err(InterlockError::Index);
+        }
+        if self.active == Some(index) {
+            self.pending = None;
+            return Ok(());
+        }
+        self.turn_off_active()?;
+        self.pending = Some(index);
+        self.update()?;
+        Ok(())
+    }
+
+    /// Turn off all members
+    pub fn off(&mut self) -> Result<(), InterlockError<P>> {
+        self.pending = None;
+        self.turn_off_active()
+    }
+
+    /// Turn on the pending member once the dead time has elapsed
+    ///
+    /// - True: The pending member has been turned on
+    /// - False: Nothing has changed
+    pub fn update(&mut self) -> Result<bool, InterlockError<P>> {
+        let Some(index) = self.pending else {
+            return Ok(false);
+        };
+        // 检查死区时间是否已过
+        if let Some(last_off) = self.last_off {
+            if self.clock_impl.now().duration_since(last_off) < self.dead_time {
+                return Ok(false);
+            }
+        }
+        // 再次确认其他继电器均已断开
+        if self.members.iter().any(|member| member.is_on()) {
+            return Ok(false);
+        }
+        self.members[index]
+            .on()
+            .map_err(|err| InterlockError::Raw(err))?;
+        self.active = Some(index);
+        self.pending = None;
+        Ok(true)
+    }
+
+    /// Index of the member that is on
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// Index of the member waiting for the dead time to elapse
+    pub fn pending(&self) -> Option<usize> {
+        self.pending
+    }
+
+    /// Get the dead time
+    pub fn dead_time(&self) -> Duration {
+        self.dead_time
+    }
+
+    /// Set the dead time
+    pub fn set_dead_time(&mut self, dead_time: Duration) {
+        self.dead_time = dead_time;
+    }
+
+    /// Release the member dc relay drivers
+    ///
+    /// Note: The members keep their current state
+    pub fn release(self) -> [Driver<P>; N] {
+        self.members
+    }
+
+    /// Turn off the member that is on
+    fn turn_off_active(&mut self) -> Result<(), InterlockError<P>> {
+        if let Some(index) = self.active {
+            self.members[index]
+                .off()
+                .map_err(|err| InterlockError::Raw(err))?;
+            self.active = None;
+            self.last_off = Some(self.clock_impl.now());
+        }
+        Ok(())
+    }
+}
+
 /// DC relay sensor pwm driver
 /// 
 /// The PWM driver is optional, and you can directly use your board-level PWM driver. 
@@ -102,3 +911,468 @@ impl<P: SetDutyCycle> PwmDriver<P> {
         self.pin.set_duty_cycle_percent(percent)
     }
 }
+
+/// Solenoid/relay coil state of the economizer driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CoilState {
+    /// The coil is not energized
+    Off,
+    /// The coil is driven at 100% duty cycle to pull in
+    PullIn,
+    /// The coil is driven at the hold duty cycle
+    Hold,
+}
+
+/// DC relay sensor pwm hold-current economizer driver
+///
+/// Relay and solenoid coils need the full current to pull in, but far less to stay pulled in.
+/// This driver applies 100% duty cycle for the pull-in time and then drops to the hold duty cycle,
+/// reducing coil heating and power consumption.
+///
+/// The drop to the hold duty cycle is non-blocking, call [`update`](Self::update) periodically.
+pub struct EconomizerDriver<'a, C: Clock, P: SetDutyCycle> {
+    /// DC relay pwm driver
+    driver: PwmDriver<P>,
+    /// Time at 100% duty cycle after energizing
+    pull_in_time: Duration,
+    /// Hold duty cycle in percent
+    hold_percent: u8,
+    /// Coil state
+    state: CoilState,
+    /// Time the coil was energized
+    energized_at: Option<C::Instant>,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, P: SetDutyCycle> EconomizerDriver<'a, C, P> {
+    /// Create an instance of the dc relay economizer driver
+    ///
+    /// - pull_in_time: Time at 100% duty cycle after energizing
+    /// - hold_percent: Hold duty cycle in percent, must be less than or equal to 100
+    ///
+    /// Note: The coil is de-energized when the driver is created
+    pub fn new(
+        clock: &'a C,
+        mut driver: PwmDriver<P>,
+        pull_in_time: Duration,
+        hold_percent: u8,
+    ) -> Result<Self, P::Error> {
+        // 确保线圈处于断电状态
+        driver.set_duty_cycle_fully_off()?;
+        // OK
+        Ok(Self {
+            driver,
+            pull_in_time,
+            hold_percent: hold_percent.min(100),
+            state: CoilState::Off,
+            energized_at: None,
+            clock_impl: clock,
+        })
+    }
+
+    /// Energize the coil, starting with the pull-in phase
+    ///
+    /// Calling it again while the coil is energized has no effect
+    pub fn on(&mut self) -> Result<(), P::Error> {
+        if self.state != CoilState::Off {
+            return Ok(());
+        }
+        self.driver.set_duty_cycle_fully_on()?;
+        self.state = CoilState::PullIn;
+        self.energized_at = Some(self.clock_impl.now());
+        // 吸合时间为0时直接进入保持阶段
+        self.update()?;
+        Ok(())
+    }
+
+    /// De-energize the coil
+    pub fn off(&mut self) -> Result<(), P::Error> {
+        self.driver.set_duty_cycle_fully_off()?;
+        self.state = CoilState::Off;
+        self.energized_at = None;
+        Ok(())
+    }
+
+    /// Drop to the hold duty cycle once the pull-in time has elapsed
+    ///
+    /// - True: The coil has switched to the hold duty cycle
+    /// - False: Nothing has changed
+    pub fn update(&mut self) -> Result<bool, P::Error> {
+        let Some(energized_at) = self.energized_at else {
+            return Ok(false);
+        };
+        if self.state != CoilState::PullIn
+            || self.clock_impl.now().duration_since(energized_at) < self.pull_in_time
+        {
+            return Ok(false);
+        }
+        self.driver.set_duty_cycle_percent(self.hold_percent)?;
+        self.state = CoilState::Hold;
+        Ok(true)
+    }
+
+    /// Get the coil state
+    pub fn state(&self) -> CoilState {
+        self.state
+    }
+
+    /// Whether the coil is energized
+    pub fn is_on(&self) -> bool {
+        self.state != CoilState::Off
+    }
+
+    /// Set the pull-in time, applied the next time the coil is energized
+    pub fn set_pull_in_time(&mut self, pull_in_time: Duration) {
+        self.pull_in_time = pull_in_time;
+    }
+
+    /// Set the hold duty cycle in percent
+    ///
+    /// If the coil is already holding, the new duty cycle is applied immediately
+    pub fn set_hold_percent(&mut self, hold_percent: u8) -> Result<(), P::Error> {
+        self.hold_percent = hold_percent.min(100);
+        if self.state == CoilState::Hold {
+            self.driver.set_duty_cycle_percent(self.hold_percent)?;
+        }
+        Ok(())
+    }
+
+    /// Release the wrapped dc relay pwm driver
+    pub fn release(self) -> PwmDriver<P> {
+        self.driver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{
+        CoilState, Driver, EconomizerDriver, FeedbackDriver, FeedbackDriverError, InterlockGroup,
+        PinState, ProtectedDriver, ProtectedDriverError, Protection, ProtectionMode, PwmDriver,
+        TimedDriver,
+    };
+    use crate::mock::{InputState, MockClock, OutputState, PwmState};
+
+    #[test]
+    fn applies_initial_state_and_tracks_it() {
+        let state = OutputState::new();
+        let mut relay = Driver::new(state.pin(), PinState::Low, false).unwrap();
+        assert!(state.is_high());
+        assert!(!relay.is_on());
+
+        relay.toggle().unwrap();
+        assert!(relay.is_on());
+        assert!(!state.is_high());
+        relay.set(false).unwrap();
+        assert!(!relay.is_on());
+    }
+
+    #[test]
+    fn feedback_detects_mismatch() {
+        let (out, feedback) = (OutputState::new(), InputState::new(&[false]));
+        let relay = Driver::new(out.pin(), PinState::High, false).unwrap();
+        let mut relay = FeedbackDriver::new(relay, feedback.pin(), PinState::High);
+        relay.verify().unwrap();
+
+        relay.on().unwrap();
+        assert!(matches!(relay.verify(), Err(FeedbackDriverError::Mismatch)));
+        feedback.script(&[true]);
+        relay.verify().unwrap();
+    }
+
+    #[test]
+    fn protection_rejects_chattering() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = Driver::new(state.pin(), PinState::High, false).unwrap();
+        let protection = Protection {
+            min_on_time: Duration::from_secs(10),
+            max_switches: 3,
+            switch_window: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut relay = ProtectedDriver::new(&clock, relay, protection);
+
+        relay.on().unwrap();
+        assert!(matches!(relay.off(), Err(ProtectedDriverError::Dwell)));
+        clock.advance(Duration::from_secs(10));
+        relay.off().unwrap();
+        relay.on().unwrap();
+        clock.advance(Duration::from_secs(10));
+        assert!(matches!(
+            relay.off(),
+            Err(ProtectedDriverError::RateLimited)
+        ));
+        clock.advance(Duration::from_secs(40));
+        relay.off().unwrap();
+        assert_eq!(relay.cycles(), 2);
+    }
+
+    #[test]
+    fn protection_defers_change() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = Driver::new(state.pin(), PinState::High, false).unwrap();
+        let protection = Protection {
+            min_on_time: Duration::from_secs(5),
+            mode: ProtectionMode::Defer,
+            ..Default::default()
+        };
+        let mut relay = ProtectedDriver::new(&clock, relay, protection);
+
+        relay.on().unwrap();
+        relay.off().unwrap();
+        assert!(relay.is_on());
+        assert_eq!(relay.pending(), Some(false));
+        assert!(!relay.update().unwrap());
+
+        clock.advance(Duration::from_secs(5));
+        assert!(relay.update().unwrap());
+        assert!(!relay.is_on());
+        assert!(!state.is_high());
+    }
+
+    #[test]
+    fn protection_applies_to_initially_on_relay() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = Driver::new(state.pin(), PinState::High, true).unwrap();
+        let protection = Protection {
+            min_on_time: Duration::from_secs(10),
+            min_off_time: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let mut relay = ProtectedDriver::new(&clock, relay, protection);
+
+        assert!(matches!(relay.off(), Err(ProtectedDriverError::Dwell)));
+        clock.advance(Duration::from_secs(10));
+        relay.off().unwrap();
+        assert!(!state.is_high());
+        assert_eq!(relay.cycles(), 0);
+
+        // 初始为关闭状态时可以立即开启
+        let relay = Driver::new(state.pin(), PinState::High, false).unwrap();
+        let mut relay = ProtectedDriver::new(&clock, relay, protection);
+        relay.on().unwrap();
+        assert!(state.is_high());
+    }
+
+    #[test]
+    fn force_off_ignores_protection() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = Driver::new(state.pin(), PinState::High, false).unwrap();
+        let protection = Protection {
+            min_on_time: Duration::from_secs(10),
+            mode: ProtectionMode::Defer,
+            ..Default::default()
+        };
+        let mut relay = ProtectedDriver::new(&clock, relay, protection);
+
+        relay.on().unwrap();
+        relay.off().unwrap();
+        assert_eq!(relay.pending(), Some(false));
+        // 请求恢复原状态时取消等待中的切换
+        relay.on().unwrap();
+        assert_eq!(relay.pending(), None);
+
+        relay.force_off().unwrap();
+        assert!(!relay.is_on());
+        assert!(!state.is_high());
+        assert!(!relay.update().unwrap());
+    }
+
+    #[test]
+    fn interlock_keeps_one_member_on() {
+        let clock = MockClock::manual();
+        let (a, b) = (OutputState::new(), OutputState::new());
+        let members = [
+            Driver::new(a.pin(), PinState::High, false).unwrap(),
+            Driver::new(b.pin(), PinState::High, false).unwrap(),
+        ];
+        let mut group = InterlockGroup::new(&clock, members, Duration::from_millis(100)).unwrap();
+
+        clock.advance(Duration::from_millis(100));
+        group.select(0).unwrap();
+        assert!(a.is_high() && !b.is_high());
+
+        group.select(1).unwrap();
+        assert!(!a.is_high() && !b.is_high());
+        assert_eq!(group.pending(), Some(1));
+        clock.advance(Duration::from_millis(100));
+        assert!(group.update().unwrap());
+        assert!(!a.is_high() && b.is_high());
+        assert_eq!(group.active(), Some(1));
+        assert!(group.select(2).is_err());
+    }
+
+    #[test]
+    fn interlock_waits_dead_time_after_creation() {
+        let clock = MockClock::manual();
+        let (a, b) = (OutputState::new(), OutputState::new());
+        let members = [
+            Driver::new(a.pin(), PinState::High, true).unwrap(),
+            Driver::new(b.pin(), PinState::High, true).unwrap(),
+        ];
+        // 创建时断开所有继电器，并从此刻开始计算死区时间
+        let mut group = InterlockGroup::new(&clock, members, Duration::from_millis(50)).unwrap();
+        assert!(!a.is_high() && !b.is_high());
+        assert_eq!(group.active(), None);
+
+        group.select(1).unwrap();
+        assert_eq!(group.pending(), Some(1));
+        clock.advance(Duration::from_millis(49));
+        assert!(!group.update().unwrap());
+        assert!(!b.is_high());
+        clock.advance(Duration::from_millis(1));
+        assert!(group.update().unwrap());
+        assert!(b.is_high());
+    }
+
+    #[test]
+    fn interlock_off_cancels_pending_member() {
+        let clock = MockClock::manual();
+        let (a, b, c) = (OutputState::new(), OutputState::new(), OutputState::new());
+        let members = [
+            Driver::new(a.pin(), PinState::High, false).unwrap(),
+            Driver::new(b.pin(), PinState::High, false).unwrap(),
+            Driver::new(c.pin(), PinState::High, false).unwrap(),
+        ];
+        let mut group = InterlockGroup::new(&clock, members, Duration::from_millis(100)).unwrap();
+        clock.advance(Duration::from_millis(100));
+        group.select(0).unwrap();
+
+        // 死区时间内改选其他继电器，只有最后选择的会开启
+        group.select(1).unwrap();
+        group.select(2).unwrap();
+        assert_eq!(group.pending(), Some(2));
+        group.off().unwrap();
+        assert_eq!(group.pending(), None);
+        clock.advance(Duration::from_millis(100));
+        assert!(!group.update().unwrap());
+        assert!(!a.is_high() && !b.is_high() && !c.is_high());
+
+        // 重新选择正在开启的继电器不会断开它
+        group.select(2).unwrap();
+        assert!(c.is_high());
+        group.select(2).unwrap();
+        assert!(c.is_high());
+        assert_eq!(group.active(), Some(2));
+
+        // 死区时间为0时立即切换
+        group.set_dead_time(Duration::ZERO);
+        group.select(0).unwrap();
+        assert!(a.is_high() && !c.is_high());
+    }
+
+    #[test]
+    fn timed_pulse_turns_off_after_overdue_update() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = Driver::new(state.pin(), PinState::High, false).unwrap();
+        let mut relay = TimedDriver::new(&clock, relay);
+
+        relay.on_for(Duration::from_secs(3)).unwrap();
+        assert!(relay.is_on());
+        // 调用方卡住很久之后，下一次更新立即关闭
+        clock.advance(Duration::from_secs(60));
+        assert!(relay.update().unwrap());
+        assert!(!relay.is_on());
+
+        relay
+            .pulse(Duration::from_secs(1), Duration::from_secs(2))
+            .unwrap();
+        assert!(!relay.is_on());
+        clock.advance(Duration::from_secs(1));
+        relay.update().unwrap();
+        assert!(relay.is_on());
+        assert_eq!(relay.remaining(), Some(Duration::from_secs(2)));
+        clock.advance(Duration::from_secs(2));
+        relay.update().unwrap();
+        assert!(!relay.is_on());
+    }
+
+    #[test]
+    fn timed_delayed_on_and_cancel() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = Driver::new(state.pin(), PinState::High, false).unwrap();
+        let mut relay = TimedDriver::new(&clock, relay);
+
+        relay.on_after(Duration::from_secs(5)).unwrap();
+        assert!(!relay.is_on());
+        assert_eq!(relay.remaining(), Some(Duration::from_secs(5)));
+        clock.advance(Duration::from_secs(5));
+        assert!(relay.update().unwrap());
+        assert!(relay.is_on());
+        assert_eq!(relay.remaining(), None);
+
+        relay.on_for(Duration::from_secs(1)).unwrap();
+        relay.cancel();
+        clock.advance(Duration::from_secs(2));
+        assert!(!relay.update().unwrap());
+        assert!(relay.is_on());
+
+        // 整个脉冲都过期后不再开启
+        relay.off().unwrap();
+        relay
+            .pulse(Duration::from_secs(1), Duration::from_secs(1))
+            .unwrap();
+        clock.advance(Duration::from_secs(3));
+        assert!(!relay.update().unwrap());
+        assert!(!relay.is_on());
+    }
+
+    #[test]
+    fn timed_durations_beyond_the_clock_range_never_expire() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = Driver::new(state.pin(), PinState::High, false).unwrap();
+        let mut relay = TimedDriver::new(&clock, relay);
+
+        relay.on_for(Duration::MAX).unwrap();
+        assert!(relay.is_on());
+        assert_eq!(relay.remaining(), None);
+
+        relay.off().unwrap();
+        relay.on_after(Duration::MAX).unwrap();
+        assert!(!relay.is_on());
+        assert_eq!(relay.remaining(), None);
+
+        relay.pulse(Duration::ZERO, Duration::MAX).unwrap();
+        assert!(relay.is_on());
+        clock.advance(Duration::from_secs(3600));
+        assert!(!relay.update().unwrap());
+        assert!(relay.is_on());
+
+        relay.pulse(Duration::MAX, Duration::from_secs(1)).unwrap();
+        assert!(relay.is_on());
+        assert_eq!(relay.remaining(), None);
+    }
+
+    #[test]
+    fn economizer_drops_to_hold_duty() {
+        let clock = MockClock::manual();
+        let pwm = PwmState::new(1000);
+        let mut coil = EconomizerDriver::new(
+            &clock,
+            PwmDriver::new(pwm.pwm()),
+            Duration::from_millis(50),
+            30,
+        )
+        .unwrap();
+
+        coil.on().unwrap();
+        assert_eq!(coil.state(), CoilState::PullIn);
+        assert_eq!(pwm.duty(), 1000);
+        clock.advance(Duration::from_millis(50));
+        assert!(coil.update().unwrap());
+        assert_eq!(coil.state(), CoilState::Hold);
+        assert_eq!(pwm.duty(), 300);
+        coil.off().unwrap();
+        assert_eq!(pwm.duty(), 0);
+    }
+}
@@ -7,6 +7,30 @@ use core::{
 use embedded_hal::digital::{InputPin, OutputPin, PinState};
 use embedded_timers::{clock::Clock, delay::Delay};
 
+use crate::calibration::Record;
+
+/// DHT11 measurement
+///
+/// Built from the tuple returned by [`Driver::read`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    /// Temperature (°C)
+    pub temperature: f32,
+    /// Relative humidity (%RH)
+    pub humidity: f32,
+}
+
+impl From<(f32, f32)> for Measurement {
+    fn from((temperature, humidity): (f32, f32)) -> Self {
+        Self {
+            temperature,
+            humidity,
+        }
+    }
+}
+
 /// DHT11 sensor Error
 #[derive(Clone, Copy)]
 pub enum Error<P: InputPin + OutputPin> {
@@ -50,6 +74,29 @@ impl<P: InputPin + OutputPin> std::fmt::Display for Error<P> {
 #[cfg(feature = "std")]
 impl<P: InputPin + OutputPin> std::error::Error for Error<P> {}
 
+#[cfg(feature = "defmt")]
+impl<P: InputPin + OutputPin> defmt::Format for Error<P> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Input(err) => defmt::write!(
+                f,
+                "The DHT11 data signal input is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::Output(err) => defmt::write!(
+                f,
+                "The DHT11 data signal ouput is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::NotReady => defmt::write!(f, "The DHT11 sensor is not ready."),
+            Self::CheckSum => defmt::write!(
+                f,
+                "The checksum of the input data of the DHT11 sensor is incorrect."
+            ),
+        }
+    }
+}
+
 /// DHT11 Sensor Driver
 pub struct Driver<'a, C: Clock, P: InputPin + OutputPin> {
     /// 1-Wire used GPIO pin
@@ -58,6 +105,8 @@ pub struct Driver<'a, C: Clock, P: InputPin + OutputPin> {
     clock_impl: &'a C,
     /// Delay implementation for embedded_timers
     delay_impl: Delay<'a, C>,
+    /// User calibration applied on every read
+    user_calibration: Record,
 }
 
 impl<'a, C: Clock, P: InputPin + OutputPin> Driver<'a, C, P> {
@@ -78,6 +127,7 @@ impl<'a, C: Clock, P: InputPin + OutputPin> Driver<'a, C, P> {
             pin,
             clock_impl: clock,
             delay_impl: delay,
+            user_calibration: Record::default(),
         })
     }
 
@@ -117,7 +167,8 @@ impl<'a, C: Clock, P: InputPin + OutputPin> Driver<'a, C, P> {
         self.pin.set_low().map_err(|err| Error::Output(err))?;
         self.delay_impl.delay(Duration::from_millis(20));
 
-        // 将数据总线切换为输入模式，由于上拉电阻的存在，数据总线会自动变为高电平
+        // 释放数据总线(开漏输出置高)，由于上拉电阻的存在，数据总线会自动变为高电平
+        self.pin.set_high().map_err(|err| Error::Output(err))?;
         // 等待传感器把数据总线（SDA）拉低83µs，再拉高87µs以响应主机的起始信号
         // 0. 等待低电平开始, 超时长一点即可
         self.wait_sensor_signal(PinState::Low, Duration::from_micros(1000))?;
@@ -133,7 +184,7 @@ impl<'a, C: Clock, P: InputPin + OutputPin> Driver<'a, C, P> {
         // 注：其中湿度小数部分为0。
         let mut data = [0u8; 5];
         // 将数据分为5组来读
-        for byte in 0..5 {
+        for byte in data.iter_mut() {
             // 每组数据都8位
             for bit in 0..8 {
                 // 位数据“0”的格式为: 54us的低电平和23-27us的高电平
@@ -146,7 +197,7 @@ impl<'a, C: Clock, P: InputPin + OutputPin> Driver<'a, C, P> {
                     self.wait_sensor_signal(PinState::Low, Duration::from_micros(80))?;
                 // 3. 根据高电平的时长来判断位数据是"0"还是"1", 这里30是给的冗余判断，稍微大于位数据"0"的范围即可
                 if high_time > Duration::from_micros(30) {
-                    data[byte] |= 1 << (7 - bit);
+                    *byte |= 1 << (7 - bit);
                 }
             }
         }
@@ -168,7 +219,90 @@ impl<'a, C: Clock, P: InputPin + OutputPin> Driver<'a, C, P> {
             temperature = -temperature;
         }
 
+        // 应用用户校准
+        let temperature = self.user_calibration.temperature(temperature);
+        let humidity = self.user_calibration.humidity(humidity);
+
         // OK
         Ok((temperature, humidity))
     }
+
+    /// User calibration applied on every read
+    pub fn user_calibration(&self) -> &Record {
+        &self.user_calibration
+    }
+
+    /// Change the user calibration applied on every read
+    pub fn set_user_calibration(&mut self, record: Record) {
+        self.user_calibration = record;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Driver, Error};
+    use crate::calibration::{Correction, Record};
+    use crate::mock::{Dht11Device, MockClock};
+
+    #[test]
+    fn reads_temperature_and_humidity() {
+        let clock = MockClock::new();
+        let mut device = Dht11Device::new(&clock);
+        device.set_measurement(55, 24, 3);
+
+        let mut driver = Driver::new(&clock, device).unwrap();
+        let (temperature, humidity) = driver.read().unwrap();
+        assert!((temperature - 24.3).abs() < 0.01);
+        assert_eq!(humidity, 55.0);
+    }
+
+    #[test]
+    fn reads_negative_temperature() {
+        let clock = MockClock::new();
+        let mut device = Dht11Device::new(&clock);
+        device.set_measurement(30, 5, 0x80 | 2);
+
+        let mut driver = Driver::new(&clock, device).unwrap();
+        let (temperature, _) = driver.read().unwrap();
+        assert!((temperature + 5.2).abs() < 0.01);
+    }
+
+    #[test]
+    fn read_fails_on_checksum_mismatch() {
+        let clock = MockClock::new();
+        let mut device = Dht11Device::new(&clock);
+        device.set_measurement(55, 24, 3);
+        device.set_checksum(0);
+
+        let mut driver = Driver::new(&clock, device).unwrap();
+        assert!(matches!(driver.read(), Err(Error::CheckSum)));
+    }
+
+    #[test]
+    fn applies_user_calibration() {
+        let clock = MockClock::new();
+        let mut device = Dht11Device::new(&clock);
+        device.set_measurement(55, 24, 3);
+
+        let mut driver = Driver::new(&clock, device).unwrap();
+        // 两点校准: 读数20.0°C -> 18.0°C, 读数30.0°C -> 29.0°C
+        let temperature = Correction::two_point((20.0, 18.0), (30.0, 29.0)).unwrap();
+        driver.set_user_calibration(Record {
+            temperature,
+            ..Record::default()
+        });
+        let (temperature, humidity) = driver.read().unwrap();
+        assert!((temperature - 22.73).abs() < 0.01);
+        assert_eq!(humidity, 55.0);
+    }
+
+    #[test]
+    fn read_fails_without_response() {
+        let clock = MockClock::new();
+        let mut device = Dht11Device::new(&clock);
+        device.set_respond(false);
+
+        let mut driver = Driver::new(&clock, device).unwrap();
+        assert!(matches!(driver.read(), Err(Error::NotReady)));
+    }
 }
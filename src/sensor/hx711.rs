@@ -7,6 +7,8 @@ use embedded_timers::delay::Delay;
 
 /// HX711 channel and gain
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChannelGain {
     /// Channel: A, Gain: 128
     /// - Send one pulse
@@ -55,6 +57,25 @@ impl<IP: InputPin, OP: OutputPin> std::fmt::Display for Error<IP, OP> {
 #[cfg(feature = "std")]
 impl<IP: InputPin, OP: OutputPin> std::error::Error for Error<IP, OP> {}
 
+#[cfg(feature = "defmt")]
+impl<IP: InputPin, OP: OutputPin> defmt::Format for Error<IP, OP> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Input(err) => defmt::write!(
+                f,
+                "The HX711 data signal input is incorrect, {}",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::Output(err) => defmt::write!(
+                f,
+                "The HX711 data signal ouput is incorrect, {}",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::NotReady => defmt::write!(f, "The HX711 sensor is not ready."),
+        }
+    }
+}
+
 /// HX711 Sensor Driver
 pub struct Driver<'a, C: Clock, I: InputPin, O: OutputPin> {
     /// Clock used GPIO pin
@@ -121,7 +142,7 @@ impl<'a, C: Clock, I: InputPin, O: OutputPin> Driver<'a, C, I, O> {
             } else {
                 // 低电平表示读取到的二进制位为0
                 // 把原来的数据左移一位，末尾一位自动就变为0了
-                raw_data = raw_data << 1
+                raw_data <<= 1;
             }
 
             // 发送时钟信号低电平，表示读取完一位数据
@@ -194,3 +215,96 @@ impl<'a, C: Clock, I: InputPin, O: OutputPin> Driver<'a, C, I, O> {
         self.channel_gain = gain;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ChannelGain, Driver, Error};
+    use crate::mock::{Hx711Device, MockClock};
+
+    #[test]
+    fn reads_positive_value() {
+        let clock = MockClock::new();
+        let device = Hx711Device::new(&clock);
+        device.set_value(0x12_3456);
+
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelA128,
+        )
+        .unwrap();
+        assert_eq!(driver.read().unwrap(), 0x12_3456);
+        assert_eq!(device.last_pulses(), 25);
+    }
+
+    #[test]
+    fn sign_extends_negative_value() {
+        let clock = MockClock::new();
+        let device = Hx711Device::new(&clock);
+        device.set_value(-1000 & 0x00FF_FFFF);
+
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelA128,
+        )
+        .unwrap();
+        assert_eq!(driver.read().unwrap(), -1000);
+    }
+
+    #[test]
+    fn sends_gain_pulses() {
+        let clock = MockClock::new();
+        let device = Hx711Device::new(&clock);
+
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelB32,
+        )
+        .unwrap();
+        driver.read().unwrap();
+        assert_eq!(device.last_pulses(), 26);
+
+        driver.set_channel_gain(ChannelGain::ChannelA64);
+        driver.read().unwrap();
+        assert_eq!(device.last_pulses(), 27);
+    }
+
+    #[test]
+    fn read_fails_when_not_ready() {
+        let clock = MockClock::new();
+        let device = Hx711Device::new(&clock);
+        device.set_ready(false);
+
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelA128,
+        )
+        .unwrap();
+        assert!(!driver.is_ready().unwrap());
+        assert!(matches!(driver.read(), Err(Error::NotReady)));
+    }
+
+    #[test]
+    fn reset_powers_down_and_up() {
+        let clock = MockClock::new();
+        let device = Hx711Device::new(&clock);
+
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelA128,
+        )
+        .unwrap();
+        driver.reset().unwrap();
+        assert_eq!(device.power_downs(), 1);
+        assert!(driver.is_ready().unwrap());
+    }
+}
@@ -13,6 +13,8 @@ pub struct Driver<P: OutputPin> {
     pin: P,
     /// Output level type
     out_level: PinState,
+    /// Whether the LED was last turned on
+    is_on: bool,
 }
 
 impl<P: OutputPin> Driver<P> {
@@ -20,12 +22,18 @@ impl<P: OutputPin> Driver<P> {
     ///
     /// - out_level: What level should be used to make the LED conduct
     pub fn new(pin: P, out_level: PinState) -> Self {
-        Self { pin, out_level }
+        Self {
+            pin,
+            out_level,
+            is_on: false,
+        }
     }
 
     /// On the LED sensor
     pub fn on(&mut self) -> Result<(), P::Error> {
-        self.pin.set_state(self.out_level)
+        self.pin.set_state(self.out_level)?;
+        self.is_on = true;
+        Ok(())
     }
 
     /// Off the LED sensor
@@ -33,7 +41,17 @@ impl<P: OutputPin> Driver<P> {
         match self.out_level {
             PinState::High => self.pin.set_low(),
             PinState::Low => self.pin.set_high(),
-        }
+        }?;
+        self.is_on = false;
+        Ok(())
+    }
+
+    /// Whether the LED was last turned on
+    ///
+    /// Note: The pin is not touched when the driver is created, so this is false until
+    /// [`on`](Self::on) or [`off`](Self::off) is called
+    pub fn is_on(&self) -> bool {
+        self.is_on
     }
 }
 
@@ -102,3 +120,43 @@ impl<P: SetDutyCycle> PwmDriver<P> {
         self.pin.set_duty_cycle_percent(percent)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Driver, PinState, PwmDriver};
+    use crate::mock::{OutputState, PwmState};
+
+    #[test]
+    fn drives_active_low_led() {
+        let state = OutputState::new();
+        let mut led = Driver::new(state.pin(), PinState::Low);
+        led.on().unwrap();
+        assert!(!state.is_high());
+        assert!(led.is_on());
+        led.off().unwrap();
+        assert!(state.is_high());
+        assert!(!led.is_on());
+        assert_eq!(state.writes(), 2);
+    }
+
+    #[test]
+    fn reports_output_error() {
+        let state = OutputState::new();
+        let mut led = Driver::new(state.pin(), PinState::High);
+        state.set_fail(true);
+        assert!(led.on().is_err());
+        assert!(!state.is_high());
+        assert!(!led.is_on());
+    }
+
+    #[test]
+    fn passes_duty_cycle_through() {
+        let state = PwmState::new(1000);
+        let mut led = PwmDriver::new(state.pwm());
+        assert_eq!(led.max_duty_cycle(), 1000);
+        led.set_duty_cycle_percent(25).unwrap();
+        assert_eq!(state.duty(), 250);
+        led.set_duty_cycle_fully_on().unwrap();
+        assert_eq!(state.duty(), 1000);
+    }
+}
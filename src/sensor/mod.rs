@@ -5,3 +5,6 @@ pub mod dc_relay;
 pub mod dht11;
 pub mod hx711;
 pub mod led;
+pub mod motor;
+pub mod rgb_led;
+pub mod ws2812;
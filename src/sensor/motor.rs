@@ -0,0 +1,616 @@
+use core::{
+    fmt::{Debug, Formatter},
+    time::Duration,
+};
+
+use embedded_hal::{digital::OutputPin, pwm::SetDutyCycle};
+use embedded_timers::{clock::Clock, instant::Instant};
+
+/// Full speed, speeds range from `-MAX_SPEED` (full reverse) to `MAX_SPEED` (full forward)
+pub const MAX_SPEED: i16 = 1000;
+
+/// H-bridge output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Drive {
+    /// Both motor terminals floating, the motor spins down freely
+    Coast,
+    /// Both motor terminals shorted, the motor stops quickly
+    Brake,
+    /// Forward at the given speed (1 ~ MAX_SPEED)
+    Forward(u16),
+    /// Reverse at the given speed (1 ~ MAX_SPEED)
+    Reverse(u16),
+}
+
+/// How the motor stops when the speed reaches zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopMode {
+    /// Let the motor spin down freely
+    Coast,
+    /// Short the motor terminals
+    Brake,
+}
+
+/// H-bridge abstraction used by the motor [`Driver`]
+///
+/// Implement it for bridges not covered by [`DirPwmBridge`] and [`DualPwmBridge`].
+/// Implementations must never drive both half-bridges in a way that shorts the supply.
+pub trait HBridge {
+    /// H-bridge error type
+    type Error;
+
+    /// Apply the output to the H-bridge
+    fn drive(&mut self, drive: Drive) -> Result<(), Self::Error>;
+}
+
+/// Direction and PWM H-bridge error
+pub enum DirPwmError<A: OutputPin, B: OutputPin, E: SetDutyCycle> {
+    /// IN1 digital I/O output error
+    In1(A::Error),
+    /// IN2 digital I/O output error
+    In2(B::Error),
+    /// Enable PWM output error
+    Pwm(E::Error),
+}
+
+impl<A, B, E> Debug for DirPwmError<A, B, E>
+where
+    A: OutputPin,
+    A::Error: Debug,
+    B: OutputPin,
+    B::Error: Debug,
+    E: SetDutyCycle,
+    E::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::In1(err) => write!(f, "The motor IN1 signal output is incorrect, {:?}.", err),
+            Self::In2(err) => write!(f, "The motor IN2 signal output is incorrect, {:?}.", err),
+            Self::Pwm(err) => write!(f, "The motor PWM output is incorrect, {:?}.", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A: OutputPin, B: OutputPin, E: SetDutyCycle> std::fmt::Display for DirPwmError<A, B, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A: OutputPin, B: OutputPin, E: SetDutyCycle> std::error::Error for DirPwmError<A, B, E> {}
+
+#[cfg(feature = "defmt")]
+impl<A: OutputPin, B: OutputPin, E: SetDutyCycle> defmt::Format for DirPwmError<A, B, E> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::In1(err) => defmt::write!(
+                f,
+                "The motor IN1 signal output is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::In2(err) => defmt::write!(
+                f,
+                "The motor IN2 signal output is incorrect, {}.",
+                embedded_hal::digital::Error::kind(err)
+            ),
+            Self::Pwm(err) => defmt::write!(
+                f,
+                "The motor PWM output is incorrect, {}.",
+                embedded_hal::pwm::Error::kind(err)
+            ),
+        }
+    }
+}
+
+/// H-bridge with two direction inputs and one PWM enable input (L298N/L293D style)
+pub struct DirPwmBridge<A: OutputPin, B: OutputPin, E: SetDutyCycle> {
+    /// IN1 used GPIO pin
+    in1: A,
+    /// IN2 used GPIO pin
+    in2: B,
+    /// Enable used PWM pin
+    enable: E,
+    /// Levels of IN1 and IN2 last set
+    direction: Option<(bool, bool)>,
+}
+
+impl<A: OutputPin, B: OutputPin, E: SetDutyCycle> DirPwmBridge<A, B, E> {
+    /// Create an instance of the direction and PWM H-bridge
+    pub fn new(in1: A, in2: B, enable: E) -> Self {
+        Self {
+            in1,
+            in2,
+            enable,
+            direction: None,
+        }
+    }
+
+    /// Set both direction pins
+    fn set_direction(&mut self, in1: bool, in2: bool) -> Result<(), DirPwmError<A, B, E>> {
+        self.in1
+            .set_state(in1.into())
+            .map_err(|err| DirPwmError::In1(err))?;
+        self.in2
+            .set_state(in2.into())
+            .map_err(|err| DirPwmError::In2(err))?;
+        self.direction = Some((in1, in2));
+        Ok(())
+    }
+}
+
+impl<A: OutputPin, B: OutputPin, E: SetDutyCycle> HBridge for DirPwmBridge<A, B, E> {
+    type Error = DirPwmError<A, B, E>;
+
+    fn drive(&mut self, drive: Drive) -> Result<(), Self::Error> {
+        let (in1, in2, speed) = match drive {
+            Drive::Coast => (false, false, 0),
+            Drive::Brake => (false, false, MAX_SPEED as u16),
+            Drive::Forward(speed) => (true, false, speed),
+            Drive::Reverse(speed) => (false, true, speed),
+        };
+        if self.direction != Some((in1, in2)) {
+            // 先关闭使能再切换方向引脚，避免切换过程中出现瞬间反向驱动
+            self.enable
+                .set_duty_cycle_fully_off()
+                .map_err(|err| DirPwmError::Pwm(err))?;
+            self.set_direction(in1, in2)?;
+        }
+        self.enable
+            .set_duty_cycle_fraction(speed.min(MAX_SPEED as u16), MAX_SPEED as u16)
+            .map_err(|err| DirPwmError::Pwm(err))
+    }
+}
+
+/// Dual PWM H-bridge error
+pub enum DualPwmError<A: SetDutyCycle, B: SetDutyCycle> {
+    /// IN1 PWM output error
+    In1(A::Error),
+    /// IN2 PWM output error
+    In2(B::Error),
+}
+
+impl<A, B> Debug for DualPwmError<A, B>
+where
+    A: SetDutyCycle,
+    A::Error: Debug,
+    B: SetDutyCycle,
+    B::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::In1(err) => write!(f, "The motor IN1 PWM output is incorrect, {:?}.", err),
+            Self::In2(err) => write!(f, "The motor IN2 PWM output is incorrect, {:?}.", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A: SetDutyCycle, B: SetDutyCycle> std::fmt::Display for DualPwmError<A, B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A: SetDutyCycle, B: SetDutyCycle> std::error::Error for DualPwmError<A, B> {}
+
+#[cfg(feature = "defmt")]
+impl<A: SetDutyCycle, B: SetDutyCycle> defmt::Format for DualPwmError<A, B> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::In1(err) => defmt::write!(
+                f,
+                "The motor IN1 PWM output is incorrect, {}.",
+                embedded_hal::pwm::Error::kind(err)
+            ),
+            Self::In2(err) => defmt::write!(
+                f,
+                "The motor IN2 PWM output is incorrect, {}.",
+                embedded_hal::pwm::Error::kind(err)
+            ),
+        }
+    }
+}
+
+/// H-bridge with two PWM inputs (DRV8833/DRV8871 style)
+///
+/// Uses fast decay: the inactive input is held low while the active input is modulated.
+pub struct DualPwmBridge<A: SetDutyCycle, B: SetDutyCycle> {
+    /// IN1 used PWM pin
+    in1: A,
+    /// IN2 used PWM pin
+    in2: B,
+}
+
+impl<A: SetDutyCycle, B: SetDutyCycle> DualPwmBridge<A, B> {
+    /// Create an instance of the dual PWM H-bridge
+    pub fn new(in1: A, in2: B) -> Self {
+        Self { in1, in2 }
+    }
+}
+
+impl<A: SetDutyCycle, B: SetDutyCycle> HBridge for DualPwmBridge<A, B> {
+    type Error = DualPwmError<A, B>;
+
+    fn drive(&mut self, drive: Drive) -> Result<(), Self::Error> {
+        let (in1, in2) = match drive {
+            Drive::Coast => (0, 0),
+            Drive::Brake => (MAX_SPEED as u16, MAX_SPEED as u16),
+            Drive::Forward(speed) => (speed.min(MAX_SPEED as u16), 0),
+            Drive::Reverse(speed) => (0, speed.min(MAX_SPEED as u16)),
+        };
+        // 先降低即将变为非驱动侧的输入，避免两侧同时处于驱动状态
+        if in1 < in2 {
+            self.in1
+                .set_duty_cycle_fraction(in1, MAX_SPEED as u16)
+                .map_err(|err| DualPwmError::In1(err))?;
+            self.in2
+                .set_duty_cycle_fraction(in2, MAX_SPEED as u16)
+                .map_err(|err| DualPwmError::In2(err))
+        } else {
+            self.in2
+                .set_duty_cycle_fraction(in2, MAX_SPEED as u16)
+                .map_err(|err| DualPwmError::In2(err))?;
+            self.in1
+                .set_duty_cycle_fraction(in1, MAX_SPEED as u16)
+                .map_err(|err| DualPwmError::In1(err))
+        }
+    }
+}
+
+/// H-bridge DC motor driver
+///
+/// Speeds are signed, from `-MAX_SPEED` (full reverse) to `MAX_SPEED` (full forward).
+/// Speed changes are ramped with a configurable acceleration, and a direction change always
+/// passes through a stop followed by a dead time, so the bridge never switches straight
+/// from one direction to the other.
+///
+/// Ramps are non-blocking, call [`update`](Self::update) periodically.
+pub struct Driver<'a, C: Clock, H: HBridge> {
+    /// H-bridge
+    bridge: H,
+    /// Speed currently applied
+    speed: i16,
+    /// Speed requested
+    target: i16,
+    /// Acceleration in speed units per second, 0 means changes are applied immediately
+    acceleration: u32,
+    /// Minimum stop time before changing direction
+    dead_time: Duration,
+    /// How the motor stops when the speed reaches zero
+    stop_mode: StopMode,
+    /// Direction of the last movement (1 forward, -1 reverse, 0 none)
+    last_direction: i8,
+    /// Time the motor last stopped
+    stopped_at: Option<C::Instant>,
+    /// Time of the last ramp step
+    last_update: C::Instant,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, H: HBridge> Driver<'a, C, H> {
+    /// Create an instance of the H-bridge motor driver
+    ///
+    /// - acceleration: Speed units per second, `MAX_SPEED` ramps from stop to full speed in one second,
+    ///   0 applies speed changes immediately
+    /// - dead_time: Minimum stop time before changing direction
+    ///
+    /// Note: The motor coasts after creation
+    pub fn new(
+        clock: &'a C,
+        mut bridge: H,
+        acceleration: u32,
+        dead_time: Duration,
+    ) -> Result<Self, H::Error> {
+        // 上电后电机处于自由停止状态
+        bridge.drive(Drive::Coast)?;
+        let now = clock.now();
+        // OK
+        Ok(Self {
+            bridge,
+            speed: 0,
+            target: 0,
+            acceleration,
+            dead_time,
+            stop_mode: StopMode::Coast,
+            last_direction: 0,
+            stopped_at: Some(now),
+            last_update: now,
+            clock_impl: clock,
+        })
+    }
+
+    /// Set the target speed, ramping towards it with the configured acceleration
+    ///
+    /// - speed: `-MAX_SPEED` ~ `MAX_SPEED`, out of range values are clamped
+    pub fn set_speed(&mut self, speed: i16) -> Result<(), H::Error> {
+        if self.speed == self.target {
+            // 新的加速过程从现在开始计时，空闲时间不计入
+            self.last_update = self.clock_impl.now();
+        }
+        self.target = speed.clamp(-MAX_SPEED, MAX_SPEED);
+        self.update()?;
+        Ok(())
+    }
+
+    /// Stop the motor immediately by letting it spin down freely
+    pub fn coast(&mut self) -> Result<(), H::Error> {
+        self.stop_now(Drive::Coast)
+    }
+
+    /// Stop the motor immediately by shorting its terminals
+    pub fn brake(&mut self) -> Result<(), H::Error> {
+        self.stop_now(Drive::Brake)
+    }
+
+    /// Advance the speed ramp
+    ///
+    /// - True: The motor has not reached the target speed yet
+    /// - False: The motor runs at the target speed
+    pub fn update(&mut self) -> Result<bool, H::Error> {
+        let now = self.clock_impl.now();
+        if self.speed == self.target {
+            self.last_update = now;
+            return Ok(false);
+        }
+
+        // 计算本次允许的最大速度变化量
+        let max_step = if self.acceleration == 0 {
+            u32::MAX
+        } else {
+            let elapsed = now.duration_since(self.last_update);
+            (self.acceleration as u64 * elapsed.as_micros() as u64 / 1_000_000).min(u32::MAX as u64)
+                as u32
+        };
+        if max_step == 0 {
+            // 时间太短，累积到下一次再处理
+            return Ok(true);
+        }
+        self.last_update = now;
+
+        // 换向时必须先减速到0，不能直接越过0
+        let goal = if self.speed != 0 && self.target.signum() != self.speed.signum() {
+            0
+        } else {
+            self.target
+        };
+        // 从停止状态起步，如果方向改变则需要等待死区时间
+        if self.speed == 0 && goal != 0 && goal.signum() as i8 != self.last_direction {
+            if let Some(stopped_at) = self.stopped_at {
+                if self.last_direction != 0 && now.duration_since(stopped_at) < self.dead_time {
+                    return Ok(true);
+                }
+            }
+        }
+
+        let step = max_step.min(2 * MAX_SPEED as u32) as i32;
+        let delta = (goal as i32 - self.speed as i32).clamp(-step, step);
+        self.apply(self.speed + delta as i16)?;
+        Ok(self.speed != self.target)
+    }
+
+    /// Speed currently applied
+    pub fn speed(&self) -> i16 {
+        self.speed
+    }
+
+    /// Target speed
+    pub fn target(&self) -> i16 {
+        self.target
+    }
+
+    /// Set the acceleration in speed units per second, 0 applies speed changes immediately
+    pub fn set_acceleration(&mut self, acceleration: u32) {
+        self.acceleration = acceleration;
+    }
+
+    /// Set the minimum stop time before changing direction
+    pub fn set_dead_time(&mut self, dead_time: Duration) {
+        self.dead_time = dead_time;
+    }
+
+    /// Set how the motor stops when a ramp reaches zero speed
+    pub fn set_stop_mode(&mut self, stop_mode: StopMode) -> Result<(), H::Error> {
+        self.stop_mode = stop_mode;
+        if self.speed == 0 {
+            self.bridge.drive(self.stop_drive())?;
+        }
+        Ok(())
+    }
+
+    /// Release the H-bridge
+    pub fn release(self) -> H {
+        self.bridge
+    }
+
+    /// H-bridge output used when the speed is zero
+    fn stop_drive(&self) -> Drive {
+        match self.stop_mode {
+            StopMode::Coast => Drive::Coast,
+            StopMode::Brake => Drive::Brake,
+        }
+    }
+
+    /// Stop immediately with the given output
+    fn stop_now(&mut self, drive: Drive) -> Result<(), H::Error> {
+        self.bridge.drive(drive)?;
+        self.target = 0;
+        if self.speed != 0 {
+            self.speed = 0;
+            self.stopped_at = Some(self.clock_impl.now());
+        }
+        Ok(())
+    }
+
+    /// Apply the speed to the H-bridge
+    fn apply(&mut self, speed: i16) -> Result<(), H::Error> {
+        let drive = match speed {
+            0 => self.stop_drive(),
+            s if s > 0 => Drive::Forward(s as u16),
+            s => Drive::Reverse(s.unsigned_abs()),
+        };
+        self.bridge.drive(drive)?;
+        if speed == 0 && self.speed != 0 {
+            // 记录停止时间，用于换向死区
+            self.stopped_at = Some(self.clock_impl.now());
+        }
+        if speed != 0 {
+            self.last_direction = speed.signum() as i8;
+        }
+        self.speed = speed;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{DirPwmBridge, Drive, DualPwmBridge, HBridge, StopMode, MAX_SPEED};
+    use crate::mock::{MockClock, OutputState, PwmState};
+
+    type Driver<'a, H> = super::Driver<'a, MockClock, H>;
+
+    #[test]
+    fn dir_pwm_bridge_sets_direction_and_duty() {
+        let (in1, in2, en) = (OutputState::new(), OutputState::new(), PwmState::new(1000));
+        let mut bridge = DirPwmBridge::new(in1.pin(), in2.pin(), en.pwm());
+
+        bridge.drive(Drive::Forward(300)).unwrap();
+        assert!(in1.is_high() && !in2.is_high());
+        assert_eq!(en.duty(), 300);
+
+        bridge.drive(Drive::Reverse(500)).unwrap();
+        assert!(!in1.is_high() && in2.is_high());
+        assert_eq!(en.duty(), 500);
+
+        bridge.drive(Drive::Brake).unwrap();
+        assert!(!in1.is_high() && !in2.is_high());
+        assert_eq!(en.duty(), 1000);
+    }
+
+    #[test]
+    fn dual_pwm_bridge_drives_one_input() {
+        let (in1, in2) = (PwmState::new(100), PwmState::new(100));
+        let mut bridge = DualPwmBridge::new(in1.pwm(), in2.pwm());
+
+        bridge.drive(Drive::Reverse(MAX_SPEED as u16 / 2)).unwrap();
+        assert_eq!((in1.duty(), in2.duty()), (0, 50));
+        bridge.drive(Drive::Coast).unwrap();
+        assert_eq!((in1.duty(), in2.duty()), (0, 0));
+    }
+
+    #[test]
+    fn ramps_speed_with_acceleration() {
+        let clock = MockClock::manual();
+        let (in1, in2) = (PwmState::new(1000), PwmState::new(1000));
+        let mut motor = Driver::new(
+            &clock,
+            DualPwmBridge::new(in1.pwm(), in2.pwm()),
+            1000,
+            Duration::ZERO,
+        )
+        .unwrap();
+
+        motor.set_speed(800).unwrap();
+        assert_eq!(motor.speed(), 0);
+        clock.advance(Duration::from_millis(250));
+        assert!(motor.update().unwrap());
+        assert_eq!(motor.speed(), 250);
+        assert_eq!(in1.duty(), 250);
+        clock.advance(Duration::from_secs(1));
+        assert!(!motor.update().unwrap());
+        assert_eq!(motor.speed(), 800);
+    }
+
+    #[test]
+    fn ramp_starts_when_speed_is_set_after_idle() {
+        let clock = MockClock::manual();
+        let (in1, in2) = (PwmState::new(1000), PwmState::new(1000));
+        let mut motor = Driver::new(
+            &clock,
+            DualPwmBridge::new(in1.pwm(), in2.pwm()),
+            1000,
+            Duration::ZERO,
+        )
+        .unwrap();
+
+        // 空闲一段时间后设置速度，不能直接跳到目标速度
+        clock.advance(Duration::from_secs(5));
+        motor.set_speed(800).unwrap();
+        assert_eq!(motor.speed(), 0);
+        clock.advance(Duration::from_millis(100));
+        motor.update().unwrap();
+        assert_eq!(motor.speed(), 100);
+
+        clock.advance(Duration::from_secs(1));
+        motor.update().unwrap();
+        motor.coast().unwrap();
+        clock.advance(Duration::from_secs(5));
+        motor.set_speed(-400).unwrap();
+        assert_eq!(motor.speed(), 0);
+        clock.advance(Duration::from_millis(200));
+        motor.update().unwrap();
+        assert_eq!(motor.speed(), -200);
+    }
+
+    #[test]
+    fn reversal_waits_for_dead_time() {
+        let clock = MockClock::manual();
+        let (in1, in2) = (PwmState::new(1000), PwmState::new(1000));
+        let mut motor = Driver::new(
+            &clock,
+            DualPwmBridge::new(in1.pwm(), in2.pwm()),
+            0,
+            Duration::from_millis(100),
+        )
+        .unwrap();
+
+        motor.set_speed(500).unwrap();
+        assert_eq!(motor.speed(), 500);
+
+        // 先停止，死区时间内保持停止
+        motor.set_speed(-500).unwrap();
+        assert_eq!(motor.speed(), 0);
+        assert_eq!((in1.duty(), in2.duty()), (0, 0));
+        clock.advance(Duration::from_millis(50));
+        motor.update().unwrap();
+        assert_eq!(motor.speed(), 0);
+
+        clock.advance(Duration::from_millis(50));
+        motor.update().unwrap();
+        assert_eq!(motor.speed(), -500);
+        assert_eq!((in1.duty(), in2.duty()), (0, 500));
+    }
+
+    #[test]
+    fn brake_stops_immediately() {
+        let clock = MockClock::manual();
+        let (in1, in2) = (PwmState::new(1000), PwmState::new(1000));
+        let mut motor = Driver::new(
+            &clock,
+            DualPwmBridge::new(in1.pwm(), in2.pwm()),
+            100,
+            Duration::ZERO,
+        )
+        .unwrap();
+        motor.set_stop_mode(StopMode::Brake).unwrap();
+        assert_eq!((in1.duty(), in2.duty()), (1000, 1000));
+
+        motor.set_speed(MAX_SPEED).unwrap();
+        clock.advance(Duration::from_secs(2));
+        motor.update().unwrap();
+        assert_eq!(motor.speed(), 200);
+
+        motor.coast().unwrap();
+        assert_eq!(motor.speed(), 0);
+        assert_eq!(motor.target(), 0);
+        assert_eq!((in1.duty(), in2.duty()), (0, 0));
+    }
+}
@@ -0,0 +1,590 @@
+use core::{
+    fmt::{Debug, Formatter},
+    time::Duration,
+};
+
+use embedded_hal::pwm::SetDutyCycle;
+use embedded_timers::{clock::Clock, instant::Instant};
+
+pub use embedded_hal::digital::PinState;
+
+use crate::led::PwmDriver;
+
+/// RGB(W) color
+///
+/// Each component ranges from 0 (off) to 255 (full brightness).
+/// The white component is ignored by RGB LEDs without a white channel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    /// Red component
+    pub red: u8,
+    /// Green component
+    pub green: u8,
+    /// Blue component
+    pub blue: u8,
+    /// White component
+    pub white: u8,
+}
+
+impl Color {
+    /// All channels off
+    pub const OFF: Self = Self::rgbw(0, 0, 0, 0);
+    /// All channels at full brightness
+    pub const FULL: Self = Self::rgbw(255, 255, 255, 255);
+
+    /// Create a color from red, green and blue components
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self::rgbw(red, green, blue, 0)
+    }
+
+    /// Create a color from red, green, blue and white components
+    pub const fn rgbw(red: u8, green: u8, blue: u8, white: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            white,
+        }
+    }
+
+    /// Create a color from hue, saturation and value
+    ///
+    /// - hue: Hue angle in degrees, values above 359 wrap around
+    /// - saturation: 0 (gray) to 255 (fully saturated)
+    /// - value: 0 (black) to 255 (full brightness)
+    pub fn from_hsv(hue: u16, saturation: u8, value: u8) -> Self {
+        // 没有饱和度时为灰度
+        if saturation == 0 {
+            return Self::rgb(value, value, value);
+        }
+
+        // 将色相划分为6个60度的扇区，并计算在扇区内的偏移量(0~255)
+        let hue = (hue % 360) as u32;
+        let sector = hue / 60;
+        let offset = (hue % 60) * 255 / 59;
+
+        let v = value as u32;
+        let s = saturation as u32;
+        // 色相环上三个辅助分量
+        let p = (v * (255 - s) / 255) as u8;
+        let q = (v * (255 - s * offset / 255) / 255) as u8;
+        let t = (v * (255 - s * (255 - offset) / 255) / 255) as u8;
+
+        match sector {
+            0 => Self::rgb(value, t, p),
+            1 => Self::rgb(q, value, p),
+            2 => Self::rgb(p, value, t),
+            3 => Self::rgb(p, q, value),
+            4 => Self::rgb(t, p, value),
+            _ => Self::rgb(value, p, q),
+        }
+    }
+
+    /// Linearly interpolate between two colors
+    ///
+    /// - progress: 0 returns `self`, 255 returns `target`
+    fn lerp(self, target: Self, progress: u8) -> Self {
+        let mix = |from: u8, to: u8| -> u8 {
+            let from = from as i32;
+            let to = to as i32;
+            (from + (to - from) * progress as i32 / 255) as u8
+        };
+        Self::rgbw(
+            mix(self.red, target.red),
+            mix(self.green, target.green),
+            mix(self.blue, target.blue),
+            mix(self.white, target.white),
+        )
+    }
+}
+
+/// RGB(W) LED driver error
+pub enum Error<R: SetDutyCycle, G: SetDutyCycle, B: SetDutyCycle, W: SetDutyCycle> {
+    /// Red channel PWM error
+    Red(R::Error),
+    /// Green channel PWM error
+    Green(G::Error),
+    /// Blue channel PWM error
+    Blue(B::Error),
+    /// White channel PWM error
+    White(W::Error),
+}
+
+impl<R, G, B, W> Debug for Error<R, G, B, W>
+where
+    R: SetDutyCycle,
+    R::Error: Debug,
+    G: SetDutyCycle,
+    G::Error: Debug,
+    B: SetDutyCycle,
+    B::Error: Debug,
+    W: SetDutyCycle,
+    W::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Red(err) => write!(f, "The red channel PWM output is incorrect, {:?}.", err),
+            Self::Green(err) => write!(f, "The green channel PWM output is incorrect, {:?}.", err),
+            Self::Blue(err) => write!(f, "The blue channel PWM output is incorrect, {:?}.", err),
+            Self::White(err) => write!(f, "The white channel PWM output is incorrect, {:?}.", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: SetDutyCycle, G: SetDutyCycle, B: SetDutyCycle, W: SetDutyCycle> std::fmt::Display
+    for Error<R, G, B, W>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: SetDutyCycle, G: SetDutyCycle, B: SetDutyCycle, W: SetDutyCycle> std::error::Error
+    for Error<R, G, B, W>
+{
+}
+
+#[cfg(feature = "defmt")]
+impl<R: SetDutyCycle, G: SetDutyCycle, B: SetDutyCycle, W: SetDutyCycle> defmt::Format
+    for Error<R, G, B, W>
+{
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Red(err) => defmt::write!(
+                f,
+                "The red channel PWM output is incorrect, {}.",
+                embedded_hal::pwm::Error::kind(err)
+            ),
+            Self::Green(err) => defmt::write!(
+                f,
+                "The green channel PWM output is incorrect, {}.",
+                embedded_hal::pwm::Error::kind(err)
+            ),
+            Self::Blue(err) => defmt::write!(
+                f,
+                "The blue channel PWM output is incorrect, {}.",
+                embedded_hal::pwm::Error::kind(err)
+            ),
+            Self::White(err) => defmt::write!(
+                f,
+                "The white channel PWM output is incorrect, {}.",
+                embedded_hal::pwm::Error::kind(err)
+            ),
+        }
+    }
+}
+
+/// Color fade in progress
+struct Fade<I> {
+    /// Color at the start of the fade
+    from: Color,
+    /// Color at the end of the fade
+    to: Color,
+    /// Start time of the fade
+    start: I,
+    /// Total duration of the fade
+    duration: Duration,
+}
+
+/// RGB(W) LED driver
+///
+/// Built on top of three (RGB) or four (RGBW) [`PwmDriver`] channels.
+/// It takes care of the LED polarity (common anode or common cathode),
+/// per-channel calibration (white balance) and non-blocking color fades.
+pub struct Driver<
+    'a,
+    C: Clock,
+    R: SetDutyCycle,
+    G: SetDutyCycle,
+    B: SetDutyCycle,
+    W: SetDutyCycle = R,
+> {
+    /// Red channel
+    red: PwmDriver<R>,
+    /// Green channel
+    green: PwmDriver<G>,
+    /// Blue channel
+    blue: PwmDriver<B>,
+    /// White channel
+    white: Option<PwmDriver<W>>,
+    /// Output level type
+    out_level: PinState,
+    /// Maximum brightness of each channel, used for white balance
+    calibration: Color,
+    /// Color currently output
+    color: Color,
+    /// Color fade in progress
+    fade: Option<Fade<C::Instant>>,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, R: SetDutyCycle, G: SetDutyCycle, B: SetDutyCycle> Driver<'a, C, R, G, B, R> {
+    /// Create an instance of the RGB LED driver
+    ///
+    /// - out_level: What level should be used to make the LED conduct,
+    ///   `PinState::High` for common cathode and `PinState::Low` for common anode LEDs
+    ///
+    /// Note: The LED is turned off after creation
+    pub fn new(
+        clock: &'a C,
+        red: PwmDriver<R>,
+        green: PwmDriver<G>,
+        blue: PwmDriver<B>,
+        out_level: PinState,
+    ) -> Result<Self, Error<R, G, B, R>> {
+        Self::build(clock, red, green, blue, None, out_level)
+    }
+}
+
+impl<'a, C: Clock, R: SetDutyCycle, G: SetDutyCycle, B: SetDutyCycle, W: SetDutyCycle>
+    Driver<'a, C, R, G, B, W>
+{
+    /// Create an instance of the RGBW LED driver
+    ///
+    /// - out_level: What level should be used to make the LED conduct,
+    ///   `PinState::High` for common cathode and `PinState::Low` for common anode LEDs
+    ///
+    /// Note: The LED is turned off after creation
+    pub fn new_rgbw(
+        clock: &'a C,
+        red: PwmDriver<R>,
+        green: PwmDriver<G>,
+        blue: PwmDriver<B>,
+        white: PwmDriver<W>,
+        out_level: PinState,
+    ) -> Result<Self, Error<R, G, B, W>> {
+        Self::build(clock, red, green, blue, Some(white), out_level)
+    }
+
+    fn build(
+        clock: &'a C,
+        red: PwmDriver<R>,
+        green: PwmDriver<G>,
+        blue: PwmDriver<B>,
+        white: Option<PwmDriver<W>>,
+        out_level: PinState,
+    ) -> Result<Self, Error<R, G, B, W>> {
+        let mut this = Self {
+            red,
+            green,
+            blue,
+            white,
+            out_level,
+            calibration: Color::FULL,
+            color: Color::OFF,
+            fade: None,
+            clock_impl: clock,
+        };
+        // 确保LED处于熄灭状态
+        this.apply(Color::OFF)?;
+        // OK
+        Ok(this)
+    }
+
+    /// Set the per-channel calibration
+    ///
+    /// Each component is the duty cycle (0~255) used when that channel is at full brightness,
+    /// so the channels can be balanced to produce a neutral white.
+    /// The current color is re-applied with the new calibration.
+    pub fn set_calibration(&mut self, calibration: Color) -> Result<(), Error<R, G, B, W>> {
+        self.calibration = calibration;
+        self.apply(self.color)
+    }
+
+    /// Get the per-channel calibration
+    pub fn calibration(&self) -> Color {
+        self.calibration
+    }
+
+    /// Get the color currently output
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// Set the color immediately
+    ///
+    /// Note: Any fade in progress is cancelled
+    pub fn set_color(&mut self, color: Color) -> Result<(), Error<R, G, B, W>> {
+        self.fade = None;
+        self.apply(color)
+    }
+
+    /// Set the color from hue, saturation and value
+    ///
+    /// See [`Color::from_hsv`]
+    pub fn set_hsv(
+        &mut self,
+        hue: u16,
+        saturation: u8,
+        value: u8,
+    ) -> Result<(), Error<R, G, B, W>> {
+        self.set_color(Color::from_hsv(hue, saturation, value))
+    }
+
+    /// Turn on all channels at full brightness
+    pub fn on(&mut self) -> Result<(), Error<R, G, B, W>> {
+        self.set_color(Color::FULL)
+    }
+
+    /// Turn off all channels
+    pub fn off(&mut self) -> Result<(), Error<R, G, B, W>> {
+        self.set_color(Color::OFF)
+    }
+
+    /// Start fading from the current color to the target color
+    ///
+    /// The fade is non-blocking, call [`update`](Self::update) periodically to advance it.
+    pub fn fade_to(&mut self, target: Color, duration: Duration) -> Result<(), Error<R, G, B, W>> {
+        if duration.is_zero() {
+            return self.set_color(target);
+        }
+        self.fade = Some(Fade {
+            from: self.color,
+            to: target,
+            start: self.clock_impl.now(),
+            duration,
+        });
+        // OK
+        Ok(())
+    }
+
+    /// Check whether a fade is in progress
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    /// Advance the fade in progress
+    ///
+    /// - True: The fade is still in progress
+    /// - False: There is no fade in progress
+    pub fn update(&mut self) -> Result<bool, Error<R, G, B, W>> {
+        let Some(fade) = &self.fade else {
+            return Ok(false);
+        };
+
+        // 根据已经过去的时间计算渐变进度
+        let elapsed = self.clock_impl.now().duration_since(fade.start);
+        if elapsed >= fade.duration {
+            // 渐变完成，直接输出目标颜色
+            let target = fade.to;
+            self.fade = None;
+            self.apply(target)?;
+            return Ok(false);
+        }
+        let progress = (elapsed.as_micros() * 255 / fade.duration.as_micros()) as u8;
+        let color = fade.from.lerp(fade.to, progress);
+        self.apply(color)?;
+        // OK
+        Ok(true)
+    }
+
+    /// Convert a channel brightness to a duty cycle
+    fn duty(out_level: PinState, value: u8, calibration: u8, max_duty: u16) -> u16 {
+        // 按照校准系数缩放亮度
+        let scaled = value as u32 * calibration as u32;
+        let duty = (scaled * max_duty as u32 / (255 * 255)) as u16;
+        // 共阳极LED需要反转占空比
+        match out_level {
+            PinState::High => duty,
+            PinState::Low => max_duty - duty,
+        }
+    }
+
+    /// Output the color to all channels
+    fn apply(&mut self, color: Color) -> Result<(), Error<R, G, B, W>> {
+        let (out_level, calib) = (self.out_level, self.calibration);
+
+        let duty = Self::duty(out_level, color.red, calib.red, self.red.max_duty_cycle());
+        self.red
+            .set_duty_cycle(duty)
+            .map_err(|err| Error::Red(err))?;
+
+        let duty = Self::duty(
+            out_level,
+            color.green,
+            calib.green,
+            self.green.max_duty_cycle(),
+        );
+        self.green
+            .set_duty_cycle(duty)
+            .map_err(|err| Error::Green(err))?;
+
+        let duty = Self::duty(
+            out_level,
+            color.blue,
+            calib.blue,
+            self.blue.max_duty_cycle(),
+        );
+        self.blue
+            .set_duty_cycle(duty)
+            .map_err(|err| Error::Blue(err))?;
+
+        if let Some(white) = &mut self.white {
+            let duty = Self::duty(out_level, color.white, calib.white, white.max_duty_cycle());
+            white
+                .set_duty_cycle(duty)
+                .map_err(|err| Error::White(err))?;
+        }
+
+        // 记录当前输出的颜色
+        self.color = color;
+        // OK
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{Color, Driver, Error, PinState};
+    use crate::{
+        led::PwmDriver,
+        mock::{MockClock, MockError, PwmState},
+    };
+
+    #[test]
+    fn converts_hsv_to_rgb() {
+        assert_eq!(Color::from_hsv(0, 255, 255), Color::rgb(255, 0, 0));
+        assert_eq!(Color::from_hsv(120, 255, 255), Color::rgb(0, 255, 0));
+        assert_eq!(Color::from_hsv(240, 255, 255), Color::rgb(0, 0, 255));
+        assert_eq!(Color::from_hsv(60, 255, 255), Color::rgb(255, 255, 0));
+        assert_eq!(Color::from_hsv(300, 0, 128), Color::rgb(128, 128, 128));
+    }
+
+    #[test]
+    fn inverts_common_anode_and_applies_calibration() {
+        let clock = MockClock::manual();
+        let (r, g, b) = (PwmState::new(255), PwmState::new(255), PwmState::new(255));
+        let mut led = Driver::new(
+            &clock,
+            PwmDriver::new(r.pwm()),
+            PwmDriver::new(g.pwm()),
+            PwmDriver::new(b.pwm()),
+            PinState::Low,
+        )
+        .unwrap();
+        // 共阳极熄灭时占空比为100%
+        assert_eq!((r.duty(), g.duty(), b.duty()), (255, 255, 255));
+
+        led.set_calibration(Color::rgb(255, 128, 255)).unwrap();
+        led.set_color(Color::rgb(255, 255, 0)).unwrap();
+        assert_eq!((r.duty(), g.duty(), b.duty()), (0, 127, 255));
+    }
+
+    #[test]
+    fn fades_between_colors() {
+        let clock = MockClock::manual();
+        let (r, g, b, w) = (
+            PwmState::new(255),
+            PwmState::new(255),
+            PwmState::new(255),
+            PwmState::new(255),
+        );
+        let mut led = Driver::new_rgbw(
+            &clock,
+            PwmDriver::new(r.pwm()),
+            PwmDriver::new(g.pwm()),
+            PwmDriver::new(b.pwm()),
+            PwmDriver::new(w.pwm()),
+            PinState::High,
+        )
+        .unwrap();
+
+        led.fade_to(Color::rgbw(200, 0, 0, 100), Duration::from_secs(1))
+            .unwrap();
+        clock.advance(Duration::from_millis(500));
+        assert!(led.update().unwrap());
+        assert_eq!(r.duty(), 99);
+        assert_eq!(w.duty(), 49);
+
+        clock.advance(Duration::from_millis(500));
+        assert!(!led.update().unwrap());
+        assert!(!led.is_fading());
+        assert_eq!(led.color(), Color::rgbw(200, 0, 0, 100));
+        assert_eq!((r.duty(), w.duty()), (200, 100));
+    }
+
+    #[test]
+    fn hsv_hue_wraps_around() {
+        assert_eq!(Color::from_hsv(360, 255, 255), Color::from_hsv(0, 255, 255));
+        assert_eq!(
+            Color::from_hsv(480, 255, 200),
+            Color::from_hsv(120, 255, 200)
+        );
+        // 半饱和的青色
+        assert_eq!(Color::from_hsv(180, 128, 255), Color::rgb(127, 255, 255));
+    }
+
+    #[test]
+    fn scales_to_channel_resolution() {
+        let clock = MockClock::manual();
+        let (r, g, b) = (PwmState::new(1000), PwmState::new(1000), PwmState::new(100));
+        let mut led = Driver::new(
+            &clock,
+            PwmDriver::new(r.pwm()),
+            PwmDriver::new(g.pwm()),
+            PwmDriver::new(b.pwm()),
+            PinState::High,
+        )
+        .unwrap();
+        assert_eq!((r.duty(), g.duty(), b.duty()), (0, 0, 0));
+
+        led.on().unwrap();
+        assert_eq!((r.duty(), g.duty(), b.duty()), (1000, 1000, 100));
+        led.set_color(Color::rgb(51, 0, 255)).unwrap();
+        assert_eq!((r.duty(), g.duty(), b.duty()), (200, 0, 100));
+        led.off().unwrap();
+        assert_eq!(led.color(), Color::OFF);
+        assert_eq!((r.duty(), g.duty(), b.duty()), (0, 0, 0));
+    }
+
+    #[test]
+    fn set_color_cancels_fade() {
+        let clock = MockClock::manual();
+        let (r, g, b) = (PwmState::new(255), PwmState::new(255), PwmState::new(255));
+        let mut led = Driver::new(
+            &clock,
+            PwmDriver::new(r.pwm()),
+            PwmDriver::new(g.pwm()),
+            PwmDriver::new(b.pwm()),
+            PinState::High,
+        )
+        .unwrap();
+
+        // 渐变时长为0时立即输出目标颜色
+        led.fade_to(Color::rgb(0, 0, 90), Duration::ZERO).unwrap();
+        assert!(!led.is_fading());
+        assert_eq!(b.duty(), 90);
+
+        led.fade_to(Color::rgb(255, 0, 0), Duration::from_secs(1))
+            .unwrap();
+        assert!(led.is_fading());
+        led.set_color(Color::rgb(0, 255, 0)).unwrap();
+        assert!(!led.is_fading());
+        clock.advance(Duration::from_secs(1));
+        assert!(!led.update().unwrap());
+        assert_eq!((r.duty(), g.duty(), b.duty()), (0, 255, 0));
+    }
+
+    #[test]
+    fn reports_channel_error() {
+        let clock = MockClock::manual();
+        let (r, g, b) = (PwmState::new(255), PwmState::new(255), PwmState::new(255));
+        let mut led = Driver::new(
+            &clock,
+            PwmDriver::new(r.pwm()),
+            PwmDriver::new(g.pwm()),
+            PwmDriver::new(b.pwm()),
+            PinState::High,
+        )
+        .unwrap();
+        g.set_fail(true);
+        assert!(matches!(led.on(), Err(Error::Green(MockError::Io))));
+    }
+}
@@ -0,0 +1,545 @@
+use core::fmt::{Debug, Formatter};
+
+use embedded_hal::spi::SpiBus;
+
+pub use crate::rgb_led::Color;
+
+/// Gamma correction table (gamma = 2.8)
+const GAMMA8: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
+    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
+    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
+    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
+    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
+    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
+    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
+    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
+    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// Maximum SPI bits used to encode one LED data bit
+const MAX_SYMBOL_BITS: usize = 8;
+
+/// Maximum encoded size of one pixel in bytes (4 channels * 8 bits * 8 SPI bits / 8)
+const MAX_PIXEL_BYTES: usize = 4 * MAX_SYMBOL_BITS;
+
+/// Addressable LED chip type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChipType {
+    /// WS2812/WS2812B/SK6812 RGB, transmitted in GRB order
+    Ws2812,
+    /// SK6812 RGBW, transmitted in GRBW order
+    Sk6812Rgbw,
+}
+
+impl ChipType {
+    /// Number of color channels per pixel
+    fn channels(self) -> usize {
+        match self {
+            Self::Ws2812 => 3,
+            Self::Sk6812Rgbw => 4,
+        }
+    }
+}
+
+/// WS2812 sensor driver error
+pub enum Error<S: SpiBus> {
+    /// SPI bus raw error
+    Raw(S::Error),
+    /// The SPI frequency cannot produce a valid WS2812 waveform
+    Frequency,
+    /// The buffer is too small to hold the encoded frame
+    BufferTooSmall,
+}
+
+impl<S> Debug for Error<S>
+where
+    S: SpiBus,
+    S::Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Raw(err) => write!(f, "SPI bus communication error, {:?}", err),
+            Self::Frequency => write!(
+                f,
+                "The SPI frequency is out of the supported range (2MHz up to below 6.8MHz)."
+            ),
+            Self::BufferTooSmall => write!(f, "The buffer is too small for the WS2812 frame."),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: SpiBus> std::fmt::Display for Error<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: SpiBus> std::error::Error for Error<S> {}
+
+#[cfg(feature = "defmt")]
+impl<S: SpiBus> defmt::Format for Error<S> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Raw(err) => defmt::write!(
+                f,
+                "SPI bus communication error, {}",
+                embedded_hal::spi::Error::kind(err)
+            ),
+            Self::Frequency => defmt::write!(
+                f,
+                "The SPI frequency is out of the supported range (2MHz up to below 6.8MHz)."
+            ),
+            Self::BufferTooSmall => {
+                defmt::write!(f, "The buffer is too small for the WS2812 frame.")
+            }
+        }
+    }
+}
+
+/// SPI waveform timing derived from the SPI frequency
+#[derive(Debug, Clone, Copy)]
+struct Timing {
+    /// SPI clock frequency in Hz
+    frequency: u32,
+    /// SPI bits per LED data bit
+    symbol_bits: u8,
+    /// High SPI bits of a "0" LED data bit
+    zero_high_bits: u8,
+    /// High SPI bits of a "1" LED data bit
+    one_high_bits: u8,
+    /// Number of zero bytes making up the reset (latch) signal
+    reset_bytes: usize,
+}
+
+impl Timing {
+    /// Calculate the waveform timing
+    ///
+    /// - frequency: SPI clock frequency in Hz
+    /// - reset_us: Reset (latch) time in microseconds
+    fn new(frequency: u32, reset_us: u32) -> Option<Self> {
+        let freq = frequency as u64;
+        // 每个数据位周期为1.25us，四舍五入计算需要的SPI位数
+        let symbol_bits = (freq * 125 + 50_000_000) / 100_000_000;
+        // 数据位“0”的高电平约为0.35us
+        let zero_high_bits = ((freq * 35 + 50_000_000) / 100_000_000).max(1);
+        // 数据位“1”的高电平约为0.8us
+        let one_high_bits = (freq * 80 + 50_000_000) / 100_000_000;
+        if !(3..=MAX_SYMBOL_BITS as u64).contains(&symbol_bits)
+            || one_high_bits <= zero_high_bits
+            || one_high_bits >= symbol_bits
+        {
+            return None;
+        }
+        Some(Self {
+            frequency,
+            symbol_bits: symbol_bits as u8,
+            zero_high_bits: zero_high_bits as u8,
+            one_high_bits: one_high_bits as u8,
+            reset_bytes: Self::reset_bytes(frequency, reset_us),
+        })
+    }
+
+    /// Number of zero bytes holding the line low for the reset time
+    fn reset_bytes(frequency: u32, reset_us: u32) -> usize {
+        // 复位信号需要保持足够长时间的低电平
+        (reset_us as u64 * frequency as u64).div_ceil(8_000_000) as usize
+    }
+
+    /// SPI bit pattern of one LED data bit, right aligned
+    fn symbol(&self, bit: bool) -> u8 {
+        let high_bits = if bit {
+            self.one_high_bits
+        } else {
+            self.zero_high_bits
+        };
+        // 高电平在前，低电平在后
+        (((1u16 << high_bits) - 1) << (self.symbol_bits - high_bits)) as u8
+    }
+}
+
+/// WS2812/SK6812 addressable LED strip driver
+///
+/// The one-wire WS2812 protocol is generated on the MOSI pin of an SPI bus,
+/// every LED data bit is encoded as several SPI bits, so the timing only depends on the SPI clock.
+/// Supported SPI frequencies range from 2MHz up to below 6.8MHz, 2.4MHz and 3.2MHz are common choices.
+///
+/// Note: The driver holds a frame buffer of `N` pixels, call [`write`](Self::write) to send it to the strip
+pub struct Driver<S: SpiBus, const N: usize> {
+    /// SPI bus connected to the strip data line (MOSI)
+    spi: S,
+    /// LED chip type
+    chip: ChipType,
+    /// SPI waveform timing
+    timing: Timing,
+    /// Frame buffer
+    pixels: [Color; N],
+    /// Global brightness
+    brightness: u8,
+    /// Whether gamma correction is applied
+    gamma: bool,
+}
+
+impl<S: SpiBus, const N: usize> Driver<S, N> {
+    /// Create an instance of the WS2812 driver
+    ///
+    /// - frequency: The SPI clock frequency in Hz configured on the bus
+    ///
+    /// Note: The reset time defaults to 300us, which also covers newer WS2812B revisions
+    pub fn new(spi: S, frequency: u32, chip: ChipType) -> Result<Self, Error<S>> {
+        let timing = Timing::new(frequency, 300).ok_or(Error::Frequency)?;
+        // OK
+        Ok(Self {
+            spi,
+            chip,
+            timing,
+            pixels: [Color::OFF; N],
+            brightness: 255,
+            gamma: false,
+        })
+    }
+
+    /// Set the reset (latch) time in microseconds
+    ///
+    /// Older WS2812 parts latch after 50us, WS2812B revisions from 2017 onwards need 280us
+    pub fn set_reset_time(&mut self, reset_us: u32) {
+        // 频率已在创建时检查过，只需重新计算复位字节数
+        self.timing.reset_bytes = Timing::reset_bytes(self.timing.frequency, reset_us);
+    }
+
+    /// Set the global brightness (0~255), applied when the frame is encoded
+    pub fn set_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness;
+    }
+
+    /// Get the global brightness
+    pub fn brightness(&self) -> u8 {
+        self.brightness
+    }
+
+    /// Enable or disable gamma correction (gamma = 2.8), applied when the frame is encoded
+    pub fn set_gamma_correction(&mut self, enabled: bool) {
+        self.gamma = enabled;
+    }
+
+    /// Get the frame buffer
+    pub fn pixels(&self) -> &[Color; N] {
+        &self.pixels
+    }
+
+    /// Get the mutable frame buffer
+    pub fn pixels_mut(&mut self) -> &mut [Color; N] {
+        &mut self.pixels
+    }
+
+    /// Set the color of one pixel in the frame buffer
+    ///
+    /// Note: Out of range index is ignored
+    pub fn set_pixel(&mut self, index: usize, color: Color) {
+        if let Some(pixel) = self.pixels.get_mut(index) {
+            *pixel = color;
+        }
+    }
+
+    /// Set all pixels in the frame buffer to the same color
+    pub fn fill(&mut self, color: Color) {
+        self.pixels = [color; N];
+    }
+
+    /// Turn off all pixels in the frame buffer
+    pub fn clear(&mut self) {
+        self.fill(Color::OFF);
+    }
+
+    /// Size of the encoded frame in bytes, including the reset signal
+    pub fn encoded_len(&self) -> usize {
+        N * self.pixel_len() + self.timing.reset_bytes
+    }
+
+    /// Encode the frame buffer into the SPI bit stream
+    ///
+    /// Returns the number of bytes written to the buffer
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, Error<S>> {
+        let len = self.encoded_len();
+        if buf.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        let pixel_len = self.pixel_len();
+        for (pixel, chunk) in self.pixels.iter().zip(buf.chunks_exact_mut(pixel_len)) {
+            self.encode_pixel(*pixel, chunk);
+        }
+        // 末尾填充复位信号
+        buf[N * pixel_len..len].fill(0);
+        // OK
+        Ok(len)
+    }
+
+    /// Send the frame buffer to the strip
+    ///
+    /// Pixels are encoded and sent one at a time, so no extra memory is required.
+    /// If the SPI implementation leaves long gaps between transfers (longer than ~5us),
+    /// the strip may latch early, use [`write_buffered`](Self::write_buffered) instead.
+    pub fn write(&mut self) -> Result<(), Error<S>> {
+        let pixel_len = self.pixel_len();
+        let mut chunk = [0u8; MAX_PIXEL_BYTES];
+        for pixel in self.pixels.iter() {
+            self.encode_pixel(*pixel, &mut chunk[..pixel_len]);
+            self.spi
+                .write(&chunk[..pixel_len])
+                .map_err(|err| Error::Raw(err))?;
+        }
+        // 发送复位信号，锁存数据
+        self.write_reset()
+    }
+
+    /// Send the frame buffer to the strip in a single SPI transfer
+    ///
+    /// The buffer must hold at least [`encoded_len`](Self::encoded_len) bytes
+    pub fn write_buffered(&mut self, buf: &mut [u8]) -> Result<(), Error<S>> {
+        let len = self.encode(buf)?;
+        self.spi.write(&buf[..len]).map_err(|err| Error::Raw(err))?;
+        self.spi.flush().map_err(|err| Error::Raw(err))
+    }
+
+    /// Size of one encoded pixel in bytes
+    fn pixel_len(&self) -> usize {
+        self.chip.channels() * self.timing.symbol_bits as usize
+    }
+
+    /// Send the reset signal
+    fn write_reset(&mut self) -> Result<(), Error<S>> {
+        let zeros = [0u8; MAX_PIXEL_BYTES];
+        let mut remaining = self.timing.reset_bytes;
+        while remaining > 0 {
+            let len = remaining.min(zeros.len());
+            self.spi
+                .write(&zeros[..len])
+                .map_err(|err| Error::Raw(err))?;
+            remaining -= len;
+        }
+        self.spi.flush().map_err(|err| Error::Raw(err))
+    }
+
+    /// Apply brightness and gamma correction to one channel value
+    fn correct(&self, value: u8) -> u8 {
+        let value = (value as u16 * self.brightness as u16 / 255) as u8;
+        if self.gamma {
+            GAMMA8[value as usize]
+        } else {
+            value
+        }
+    }
+
+    /// Encode one pixel into the SPI bit stream
+    fn encode_pixel(&self, color: Color, out: &mut [u8]) {
+        // WS2812的发送顺序为GRB(W)，高位先出
+        let channels = [color.green, color.red, color.blue, color.white];
+        let mut acc: u32 = 0;
+        let mut acc_bits = 0;
+        let mut pos = 0;
+        for value in channels.iter().take(self.chip.channels()) {
+            let value = self.correct(*value);
+            for bit in (0..8).rev() {
+                // 将每一位数据编码为若干个SPI位
+                acc = (acc << self.timing.symbol_bits)
+                    | self.timing.symbol(value & (1 << bit) != 0) as u32;
+                acc_bits += self.timing.symbol_bits;
+                // 凑满一个字节就输出
+                while acc_bits >= 8 {
+                    acc_bits -= 8;
+                    out[pos] = (acc >> acc_bits) as u8;
+                    pos += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use embedded_hal::spi::{ErrorType, SpiBus};
+
+    use super::{ChipType, Color, Driver};
+
+    /// SPI bus recording all written bytes
+    struct RecordingSpi {
+        data: [u8; 512],
+        len: usize,
+    }
+
+    impl RecordingSpi {
+        fn new() -> Self {
+            Self {
+                data: [0; 512],
+                len: 0,
+            }
+        }
+
+        fn written(&self) -> &[u8] {
+            &self.data[..self.len]
+        }
+    }
+
+    impl ErrorType for RecordingSpi {
+        type Error = Infallible;
+    }
+
+    impl SpiBus for RecordingSpi {
+        fn read(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+            self.data[self.len..self.len + words.len()].copy_from_slice(words);
+            self.len += words.len();
+            Ok(())
+        }
+
+        fn transfer(&mut self, _read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+            self.write(write)
+        }
+
+        fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    // 2.4MHz下，数据位“0”编码为0b100，数据位“1”编码为0b110
+    const ONES_3BIT: [u8; 3] = [0xDB, 0x6D, 0xB6];
+    const ZEROS_3BIT: [u8; 3] = [0x92, 0x49, 0x24];
+
+    #[test]
+    fn rejects_unsupported_frequency() {
+        assert!(Driver::<_, 1>::new(RecordingSpi::new(), 1_000_000, ChipType::Ws2812).is_err());
+        assert!(Driver::<_, 1>::new(RecordingSpi::new(), 8_000_000, ChipType::Ws2812).is_err());
+    }
+
+    #[test]
+    fn accepts_frequencies_below_6_8mhz() {
+        assert!(Driver::<_, 1>::new(RecordingSpi::new(), 2_000_000, ChipType::Ws2812).is_ok());
+        assert!(Driver::<_, 1>::new(RecordingSpi::new(), 6_799_999, ChipType::Ws2812).is_ok());
+        // 6.8MHz时每个数据位需要9个SPI位，超出一个字节
+        assert!(Driver::<_, 1>::new(RecordingSpi::new(), 6_800_000, ChipType::Ws2812).is_err());
+    }
+
+    #[test]
+    fn changes_reset_time() {
+        let mut driver =
+            Driver::<_, 1>::new(RecordingSpi::new(), 2_400_000, ChipType::Ws2812).unwrap();
+        driver.set_reset_time(50);
+
+        let mut buf = [0; 128];
+        // 50us复位信号为15字节
+        assert_eq!(driver.encode(&mut buf).unwrap(), 9 + 15);
+    }
+
+    #[test]
+    fn encodes_grb_order_at_2_4mhz() {
+        let mut driver =
+            Driver::<_, 1>::new(RecordingSpi::new(), 2_400_000, ChipType::Ws2812).unwrap();
+        driver.set_pixel(0, Color::rgb(0x00, 0xFF, 0x00));
+
+        let mut buf = [0xAA; 128];
+        let len = driver.encode(&mut buf).unwrap();
+        // 1个像素9字节 + 300us复位信号90字节
+        assert_eq!(len, 9 + 90);
+        assert_eq!(&buf[0..3], &ONES_3BIT);
+        assert_eq!(&buf[3..6], &ZEROS_3BIT);
+        assert_eq!(&buf[6..9], &ZEROS_3BIT);
+        assert!(buf[9..len].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn encodes_single_bits_at_2_4mhz() {
+        let mut driver =
+            Driver::<_, 1>::new(RecordingSpi::new(), 2_400_000, ChipType::Ws2812).unwrap();
+        // 0xA5 = 0b10100101 => 110 100 110 100 100 110 100 110
+        driver.set_pixel(0, Color::rgb(0, 0xA5, 0));
+
+        let mut buf = [0; 128];
+        driver.encode(&mut buf).unwrap();
+        assert_eq!(&buf[0..3], &[0b1101_0011, 0b0100_1001, 0b1010_0110]);
+    }
+
+    #[test]
+    fn encodes_four_bit_symbols_at_3_2mhz() {
+        let mut driver =
+            Driver::<_, 1>::new(RecordingSpi::new(), 3_200_000, ChipType::Ws2812).unwrap();
+        // 3.2MHz下，数据位“0”编码为0b1000，数据位“1”编码为0b1110
+        driver.set_pixel(0, Color::rgb(0x0F, 0xF0, 0x00));
+
+        let mut buf = [0; 256];
+        let len = driver.encode(&mut buf).unwrap();
+        assert_eq!(len, 12 + 120);
+        assert_eq!(&buf[0..4], &[0xEE, 0xEE, 0x88, 0x88]);
+        assert_eq!(&buf[4..8], &[0x88, 0x88, 0xEE, 0xEE]);
+        assert_eq!(&buf[8..12], &[0x88, 0x88, 0x88, 0x88]);
+    }
+
+    #[test]
+    fn encodes_white_channel_for_rgbw() {
+        let mut driver =
+            Driver::<_, 1>::new(RecordingSpi::new(), 2_400_000, ChipType::Sk6812Rgbw).unwrap();
+        driver.set_pixel(0, Color::rgbw(0, 0, 0, 0xFF));
+
+        let mut buf = [0; 128];
+        let len = driver.encode(&mut buf).unwrap();
+        assert_eq!(len, 12 + 90);
+        assert_eq!(&buf[0..3], &ZEROS_3BIT);
+        assert_eq!(&buf[3..6], &ZEROS_3BIT);
+        assert_eq!(&buf[6..9], &ZEROS_3BIT);
+        assert_eq!(&buf[9..12], &ONES_3BIT);
+    }
+
+    #[test]
+    fn applies_brightness_and_gamma() {
+        let mut driver =
+            Driver::<_, 1>::new(RecordingSpi::new(), 2_400_000, ChipType::Ws2812).unwrap();
+        driver.set_pixel(0, Color::rgb(0, 0xFF, 0));
+        driver.set_brightness(0);
+
+        let mut buf = [0; 128];
+        driver.encode(&mut buf).unwrap();
+        assert_eq!(&buf[0..3], &ZEROS_3BIT);
+
+        // 伽马校正后的值为GAMMA8[127] = 36 = 0b00100100
+        driver.set_brightness(255);
+        driver.set_pixel(0, Color::rgb(0, 127, 0));
+        driver.set_gamma_correction(true);
+        driver.encode(&mut buf).unwrap();
+        assert_eq!(&buf[0..3], &[0b1001_0011, 0b0100_1001, 0b1010_0100]);
+    }
+
+    #[test]
+    fn rejects_small_buffer() {
+        let driver = Driver::<_, 2>::new(RecordingSpi::new(), 2_400_000, ChipType::Ws2812).unwrap();
+        let mut buf = [0; 16];
+        assert!(driver.encode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn write_matches_encoded_frame() {
+        let mut driver =
+            Driver::<_, 3>::new(RecordingSpi::new(), 2_400_000, ChipType::Ws2812).unwrap();
+        driver.set_pixel(0, Color::rgb(1, 2, 3));
+        driver.set_pixel(1, Color::rgb(0x80, 0x40, 0x20));
+        driver.set_pixel(2, Color::rgb(0xFF, 0x00, 0x7F));
+
+        let mut buf = [0; 256];
+        let len = driver.encode(&mut buf).unwrap();
+        driver.write().unwrap();
+        assert_eq!(driver.spi.written(), &buf[..len]);
+    }
+}
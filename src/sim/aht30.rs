@@ -0,0 +1,277 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use super::{fault::Faults, I2cTarget, SimClock, SimError, Waveform};
+
+/// Time the AHT30 needs for one measurement
+const MEASUREMENT_TIME: Duration = Duration::from_millis(80);
+
+/// Faults of a simulated AHT30
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aht30Fault {
+    /// Do not acknowledge, counted per transaction
+    Nack,
+    /// Keep the calibration bit cleared, counted per initialization command
+    Uncalibrated,
+    /// Report busy in the measurement result, counted per result read
+    Busy,
+    /// Corrupt the CRC of the measurement result, counted per result read
+    Crc,
+}
+
+/// Shared state of a simulated AHT30
+struct State {
+    /// Time source
+    clock: SimClock,
+    /// 7bit address the device answers on
+    address: u8,
+    /// Temperature in °C
+    temperature: Waveform,
+    /// Relative humidity in %RH
+    humidity: Waveform,
+    /// Whether the calibration bit is set
+    calibrated: bool,
+    /// Time the last measurement was triggered
+    triggered_at: Option<u64>,
+    /// Raw 20 bit humidity and temperature of the last measurement
+    sample: (u32, u32),
+    /// Injected faults
+    faults: Faults<Aht30Fault>,
+}
+
+/// Simulated AHT30 temperature and humidity sensor
+///
+/// A measurement samples the waveforms when it is triggered and takes 80ms, the status
+/// reports busy until it is finished.
+#[derive(Clone)]
+pub struct Aht30Device {
+    /// Shared state
+    state: Arc<Mutex<State>>,
+}
+
+impl Aht30Device {
+    /// Create a device at the default address 0x38 reporting 25°C and 50%RH
+    ///
+    /// Like a real part it powers up with the calibration bit set.
+    pub fn new(clock: &SimClock) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                clock: clock.clone(),
+                address: 0x38,
+                temperature: Waveform::Constant(25.0),
+                humidity: Waveform::Constant(50.0),
+                calibrated: true,
+                triggered_at: None,
+                sample: (0, 0),
+                faults: Faults::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Change the address the device answers on
+    pub fn set_address(&self, address: u8) {
+        self.state().address = address;
+    }
+
+    /// Set the temperature waveform in °C
+    pub fn set_temperature(&self, waveform: impl Into<Waveform>) {
+        self.state().temperature = waveform.into();
+    }
+
+    /// Set the relative humidity waveform in %RH
+    pub fn set_humidity(&self, waveform: impl Into<Waveform>) {
+        self.state().humidity = waveform.into();
+    }
+
+    /// Inject a fault until [`clear_faults`](Self::clear_faults) is called
+    pub fn inject(&self, fault: Aht30Fault) {
+        self.state().faults.inject(fault, None);
+    }
+
+    /// Inject a fault for the given number of occurrences
+    pub fn inject_times(&self, fault: Aht30Fault, times: u32) {
+        self.state().faults.inject(fault, Some(times));
+    }
+
+    /// Remove all injected faults
+    pub fn clear_faults(&self) {
+        self.state().faults.clear();
+    }
+}
+
+impl State {
+    fn is_measuring(&self) -> bool {
+        self.triggered_at
+            .is_some_and(|at| self.clock.micros() - at < MEASUREMENT_TIME.as_micros() as u64)
+    }
+
+    fn status(&self, busy: bool) -> u8 {
+        let mut status = 0b0001_0000;
+        if self.calibrated {
+            status |= 0b0000_1000;
+        }
+        if busy {
+            status |= 0b1000_0000;
+        }
+        status
+    }
+
+    fn crc8(data: &[u8]) -> u8 {
+        let mut crc = 0xFFu8;
+        for b in data {
+            crc ^= b;
+            for _ in 0..8 {
+                crc = if crc & 0x80 != 0 {
+                    (crc << 1) ^ 0x31
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+
+    fn handle_write(&mut self, data: &[u8]) {
+        match data {
+            // 初始化命令
+            [0xBE, ..] => self.calibrated = !self.faults.take(Aht30Fault::Uncalibrated),
+            // 触发测量命令，在触发时刻对波形进行采样
+            [0xAC, ..] => {
+                let t = self.clock.elapsed();
+                let humidity = self.humidity.value_at(t).clamp(0.0, 100.0);
+                let temperature = self.temperature.value_at(t).clamp(-50.0, 150.0);
+                let full = ((1u32 << 20) - 1) as f32;
+                self.sample = (
+                    (humidity / 100.0 * full) as u32,
+                    ((temperature + 50.0) / 200.0 * full) as u32,
+                );
+                self.triggered_at = Some(self.clock.micros());
+            }
+            // 软复位命令
+            [0xBA, ..] => {
+                self.calibrated = false;
+                self.triggered_at = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_read(&mut self, buf: &mut [u8]) {
+        if buf.len() == 1 {
+            buf[0] = self.status(self.is_measuring());
+            return;
+        }
+        let busy = self.is_measuring() | self.faults.take(Aht30Fault::Busy);
+        let (h, t) = self.sample;
+        let mut data = [
+            self.status(busy),
+            (h >> 12) as u8,
+            (h >> 4) as u8,
+            (((h & 0x0F) << 4) | ((t >> 16) & 0x0F)) as u8,
+            (t >> 8) as u8,
+            t as u8,
+            0,
+        ];
+        data[6] = Self::crc8(&data[..6]);
+        if self.faults.take(Aht30Fault::Crc) {
+            data[6] ^= 0xFF;
+        }
+        let len = buf.len().min(data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+    }
+}
+
+impl ErrorType for Aht30Device {
+    type Error = SimError;
+}
+
+impl I2c<SevenBitAddress> for Aht30Device {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut state = self.state();
+        if address != state.address || state.faults.take(Aht30Fault::Nack) {
+            return Err(SimError::Nack);
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => state.handle_write(data),
+                Operation::Read(buf) => state.handle_read(buf),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl I2cTarget for Aht30Device {
+    fn address(&self) -> u8 {
+        self.state().address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Aht30Device, Aht30Fault};
+    use crate::{
+        aht30::{Driver, Error},
+        sim::{SimClock, Waveform},
+    };
+
+    #[test]
+    fn driver_follows_waveforms() {
+        let clock = SimClock::virtual_time();
+        let mut device = Aht30Device::new(&clock);
+        device.set_temperature(Waveform::Ramp {
+            from: 20.0,
+            to: 30.0,
+            duration: Duration::from_secs(100),
+        });
+        device.set_humidity(40.0);
+
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+        let (temperature, humidity) = driver.read(&mut device).unwrap();
+        assert!((temperature - 20.0).abs() < 0.01);
+        assert!((humidity - 40.0).abs() < 0.01);
+
+        clock.advance(Duration::from_secs(50));
+        let (temperature, _) = driver.read(&mut device).unwrap();
+        assert!((temperature - 25.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn injected_faults_are_consumed() {
+        let clock = SimClock::virtual_time();
+        let mut device = Aht30Device::new(&clock);
+
+        device.inject_times(Aht30Fault::Uncalibrated, 1);
+        assert!(matches!(
+            Driver::new(&clock, &mut device, None),
+            Err(Error::Init)
+        ));
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        device.inject_times(Aht30Fault::Crc, 1);
+        device.inject_times(Aht30Fault::Busy, 2);
+        assert!(matches!(driver.read(&mut device), Err(Error::Crc)));
+        assert!(matches!(driver.read(&mut device), Err(Error::Busy)));
+        assert!(driver.read(&mut device).is_ok());
+
+        device.inject(Aht30Fault::Nack);
+        assert!(matches!(driver.read(&mut device), Err(Error::Raw(_))));
+        assert!(matches!(driver.read(&mut device), Err(Error::Raw(_))));
+        device.clear_faults();
+        assert!(driver.read(&mut device).is_ok());
+    }
+}
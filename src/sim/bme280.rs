@@ -0,0 +1,371 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use super::{fault::Faults, I2cTarget, SimClock, SimError, Waveform};
+use crate::sensor::bme280::Calibration;
+
+/// Calibration NVM used by [`Bme280Device::new`] (0x88 ~ 0xA1, 0xE1 ~ 0xE7)
+///
+/// Temperature and pressure coefficients are the example values from the Bosch datasheet.
+/// The datasheet has no humidity example, so dig_H1 ~ dig_H6 are made up values in the range
+/// of real sensors (75, 362, 0, 313, 50, 30). dig_H4 and dig_H5 both use the shared nibble
+/// of 0xE5.
+pub const CALIBRATION: ([u8; 26], [u8; 7]) = (
+    [
+        0x70, 0x6B, 0x43, 0x67, 0x18, 0xFC, 0x7D, 0x8E, 0x43, 0xD6, 0xD0, 0x0B, 0x27, 0x0B, 0x8C,
+        0x00, 0xF9, 0xFF, 0x8C, 0x3C, 0xF8, 0xC6, 0x70, 0x17, 0x00, 0x4B,
+    ],
+    [0x6A, 0x01, 0x00, 0x13, 0x29, 0x03, 0x1E],
+);
+
+/// Faults of a simulated BME280
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bme280Fault {
+    /// Do not acknowledge, counted per transaction
+    Nack,
+    /// Report the NVM copy in progress in the status register, counted per status read
+    NvmCopy,
+    /// Return the reset values of the data registers, counted per data read
+    Skipped,
+}
+
+/// Shared state of a simulated BME280
+struct State {
+    /// Time source
+    clock: SimClock,
+    /// 7bit address the device answers on
+    address: u8,
+    /// Register map
+    registers: [u8; 256],
+    /// Register pointer
+    pointer: u8,
+    /// Calibration parsed from the NVM, used to derive the raw ADC values
+    calib: Calibration,
+    /// Temperature in °C
+    temperature: Waveform,
+    /// Pressure in Pa
+    pressure: Waveform,
+    /// Relative humidity in %RH
+    humidity: Waveform,
+    /// Injected faults
+    faults: Faults<Bme280Fault>,
+}
+
+/// Simulated BME280 temperature, pressure and humidity sensor
+///
+/// The raw ADC values are derived from the waveforms by inverting the compensation formulas
+/// of the datasheet with the calibration in the NVM, so the driver reads back the waveform
+/// values within the resolution of the sensor. In normal mode every data read takes a new
+/// sample, in forced mode one sample is taken and the device returns to sleep mode.
+#[derive(Clone)]
+pub struct Bme280Device {
+    /// Shared state
+    state: Arc<Mutex<State>>,
+}
+
+impl Bme280Device {
+    /// Create a device at the default address 0x76 with [`CALIBRATION`]
+    ///
+    /// It reports 25°C, 101325Pa and 50%RH.
+    pub fn new(clock: &SimClock) -> Self {
+        Self::with_calibration(clock, &CALIBRATION.0, &CALIBRATION.1)
+    }
+
+    /// Create a device with the given calibration NVM
+    ///
+    /// - tp_calib: Registers 0x88 ~ 0xA1
+    /// - h_calib: Registers 0xE1 ~ 0xE7
+    pub fn with_calibration(clock: &SimClock, tp_calib: &[u8; 26], h_calib: &[u8; 7]) -> Self {
+        let mut registers = [0u8; 256];
+        registers[0x88..0xA2].copy_from_slice(tp_calib);
+        registers[0xE1..0xE8].copy_from_slice(h_calib);
+        registers[0xD0] = 0x60;
+
+        let mut tp = [0u8; 24];
+        tp.copy_from_slice(&tp_calib[..24]);
+        let mut h = [0u8; 8];
+        h[0] = tp_calib[25];
+        h[1..].copy_from_slice(h_calib);
+
+        let mut state = State {
+            clock: clock.clone(),
+            address: 0x76,
+            registers,
+            pointer: 0,
+            calib: Calibration::from(&tp, &h),
+            temperature: Waveform::Constant(25.0),
+            pressure: Waveform::Constant(101_325.0),
+            humidity: Waveform::Constant(50.0),
+            faults: Faults::new(),
+        };
+        state.reset();
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Change the address the device answers on
+    pub fn set_address(&self, address: u8) {
+        self.state().address = address;
+    }
+
+    /// Set the chip ID register (0xD0), e.g. 0x58 to pose as a BMP280
+    pub fn set_chip_id(&self, id: u8) {
+        self.state().registers[0xD0] = id;
+    }
+
+    /// Set the temperature waveform in °C
+    pub fn set_temperature(&self, waveform: impl Into<Waveform>) {
+        self.state().temperature = waveform.into();
+    }
+
+    /// Set the pressure waveform in Pa
+    pub fn set_pressure(&self, waveform: impl Into<Waveform>) {
+        self.state().pressure = waveform.into();
+    }
+
+    /// Set the relative humidity waveform in %RH
+    pub fn set_humidity(&self, waveform: impl Into<Waveform>) {
+        self.state().humidity = waveform.into();
+    }
+
+    /// Read a register
+    pub fn register(&self, reg: u8) -> u8 {
+        self.state().registers[reg as usize]
+    }
+
+    /// Inject a fault until [`clear_faults`](Self::clear_faults) is called
+    pub fn inject(&self, fault: Bme280Fault) {
+        self.state().faults.inject(fault, None);
+    }
+
+    /// Inject a fault for the given number of occurrences
+    pub fn inject_times(&self, fault: Bme280Fault, times: u32) {
+        self.state().faults.inject(fault, Some(times));
+    }
+
+    /// Remove all injected faults
+    pub fn clear_faults(&self) {
+        self.state().faults.clear();
+    }
+}
+
+/// Smallest value in 0 ~ max for which the predicate holds, or max
+fn lower_bound(max: i32, pred: impl Fn(i32) -> bool) -> i32 {
+    let (mut low, mut high) = (0, max);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    low
+}
+
+impl State {
+    /// Power on reset values of the control and data registers
+    fn reset(&mut self) {
+        for reg in [0xF2, 0xF3, 0xF4, 0xF5] {
+            self.registers[reg] = 0;
+        }
+        self.set_raw(0x80000, 0x80000, 0x8000);
+    }
+
+    fn set_raw(&mut self, adc_p: u32, adc_t: u32, adc_h: u32) {
+        let r = &mut self.registers;
+        r[0xF7] = (adc_p >> 12) as u8;
+        r[0xF8] = (adc_p >> 4) as u8;
+        r[0xF9] = ((adc_p & 0x0F) << 4) as u8;
+        r[0xFA] = (adc_t >> 12) as u8;
+        r[0xFB] = (adc_t >> 4) as u8;
+        r[0xFC] = ((adc_t & 0x0F) << 4) as u8;
+        r[0xFD] = (adc_h >> 8) as u8;
+        r[0xFE] = adc_h as u8;
+    }
+
+    /// Take a sample of the waveforms into the data registers
+    fn sample(&mut self) {
+        let t = self.clock.elapsed();
+        let temperature = self.temperature.value_at(t);
+        let pressure = self.pressure.value_at(t);
+        let humidity = self.humidity.value_at(t);
+        let calib = &self.calib;
+
+        // 通过二分查找反推补偿公式，得到原始ADC值
+        let adc_t = lower_bound(0xFFFFF, |adc| {
+            calib.compensate_temperature(adc).0 >= temperature
+        });
+        let t_fine = calib.compensate_temperature(adc_t).1;
+        // 压力随ADC值增大而减小
+        let adc_p = lower_bound(0xFFFFF, |adc| {
+            calib.compensate_pressure(adc, t_fine) <= pressure
+        });
+        let adc_h = lower_bound(0xFFFF, |adc| {
+            calib.compensate_humidity(adc, t_fine) >= humidity
+        });
+        self.set_raw(adc_p as u32, adc_t as u32, adc_h as u32);
+    }
+
+    fn handle_write(&mut self, data: &[u8]) {
+        let Some((reg, values)) = data.split_first() else {
+            return;
+        };
+        self.pointer = *reg;
+        // BME280的写操作为“寄存器地址 + 数据”成对出现
+        let mut reg = *reg;
+        for (i, value) in values.iter().enumerate() {
+            if i % 2 == 1 {
+                reg = *value;
+                continue;
+            }
+            match reg {
+                0xE0 if *value == 0xB6 => self.reset(),
+                0xF2 | 0xF4 | 0xF5 => self.registers[reg as usize] = *value,
+                _ => {}
+            }
+        }
+    }
+
+    fn handle_read(&mut self, buf: &mut [u8]) {
+        if self.pointer == 0xF3 && self.faults.take(Bme280Fault::NvmCopy) {
+            self.registers[0xF3] |= 0x01;
+        }
+        if (0xF7..=0xFE).contains(&self.pointer) {
+            if self.faults.take(Bme280Fault::Skipped) {
+                self.set_raw(0x80000, 0x80000, 0x8000);
+            } else {
+                match self.registers[0xF4] & 0x03 {
+                    // 睡眠模式保持上一次的数据
+                    0b00 => {}
+                    // 正常模式每次读取都是新的数据
+                    0b11 => self.sample(),
+                    // 强制模式测量一次后回到睡眠模式
+                    _ => {
+                        self.sample();
+                        self.registers[0xF4] &= !0x03;
+                    }
+                }
+            }
+        }
+        for b in buf.iter_mut() {
+            *b = self.registers[self.pointer as usize];
+            self.pointer = self.pointer.wrapping_add(1);
+        }
+        self.registers[0xF3] &= !0x01;
+    }
+}
+
+impl ErrorType for Bme280Device {
+    type Error = SimError;
+}
+
+impl I2c<SevenBitAddress> for Bme280Device {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut state = self.state();
+        if address != state.address || state.faults.take(Bme280Fault::Nack) {
+            return Err(SimError::Nack);
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(data) => state.handle_write(data),
+                Operation::Read(buf) => state.handle_read(buf),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl I2cTarget for Bme280Device {
+    fn address(&self) -> u8 {
+        self.state().address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Bme280Device, Bme280Fault};
+    use crate::{
+        bme280::{Driver, Error},
+        sim::{SimClock, Waveform},
+    };
+
+    #[test]
+    fn driver_reads_back_waveforms() {
+        let clock = SimClock::virtual_time();
+        let mut device = Bme280Device::new(&clock);
+        device.set_temperature(-12.5);
+        device.set_pressure(Waveform::Sine {
+            offset: 95_000.0,
+            amplitude: 500.0,
+            period: Duration::from_secs(60),
+        });
+        device.set_humidity(73.2);
+
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+        let (temperature, pressure, humidity) = driver.read(&mut device).unwrap();
+        assert!((temperature + 12.5).abs() <= 0.01);
+        assert!((pressure - 95_000.0).abs() < 5.0);
+        assert!((humidity - 73.2).abs() < 0.05);
+
+        clock.advance(Duration::from_secs(15));
+        let (_, pressure, _) = driver.read(&mut device).unwrap();
+        assert!((pressure - 95_500.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn exposes_calibration_and_chip_id() {
+        let clock = SimClock::virtual_time();
+        let device = Bme280Device::new(&clock);
+        assert_eq!(device.register(0xD0), 0x60);
+        assert_eq!(device.register(0x88), 0x70);
+        assert_eq!(device.register(0xA1), 75);
+        assert_eq!(device.register(0xE7), 30);
+    }
+
+    #[test]
+    fn reset_restores_sleep_mode() {
+        let clock = SimClock::virtual_time();
+        let mut device = Bme280Device::new(&clock);
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+        assert_eq!(device.register(0xF4), 0x27);
+        driver.reset(&mut device).unwrap();
+        assert_eq!(device.register(0xF4), 0x00);
+        assert_eq!(device.register(0xF7), 0x80);
+    }
+
+    #[test]
+    fn injected_faults() {
+        let clock = SimClock::virtual_time();
+        let mut device = Bme280Device::new(&clock);
+
+        device.inject_times(Bme280Fault::NvmCopy, 1);
+        assert!(matches!(
+            Driver::new(&clock, &mut device, None),
+            Err(Error::Init)
+        ));
+        let mut driver = Driver::new(&clock, &mut device, None).unwrap();
+
+        device.inject_times(Bme280Fault::Skipped, 1);
+        let (temperature, _, _) = driver.read(&mut device).unwrap();
+        assert!((temperature - 25.0).abs() > 1.0);
+        let (temperature, _, _) = driver.read(&mut device).unwrap();
+        assert!((temperature - 25.0).abs() <= 0.01);
+
+        device.inject_times(Bme280Fault::Nack, 1);
+        assert!(driver.read(&mut device).is_err());
+        assert!(driver.read(&mut device).is_ok());
+    }
+}
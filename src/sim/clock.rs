@@ -0,0 +1,92 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant as StdInstant},
+};
+
+use embedded_timers::{clock::Clock, instant::Instant64};
+
+/// Time source of a [`SimClock`]
+enum Source {
+    /// Host monotonic clock
+    RealTime(StdInstant),
+    /// Virtual time in microseconds
+    Virtual {
+        /// Current time in microseconds
+        now: AtomicU64,
+        /// Microseconds added on every call to `now`
+        step: u64,
+    },
+}
+
+/// Microsecond clock shared by the simulated devices and the drivers
+///
+/// The handle is cheap to clone, every clone reads the same time. A real-time clock follows the
+/// host monotonic clock. A virtual clock advances 1us on every call to [`Clock::now`], so the
+/// blocking delays of the drivers finish instantly, and can be moved forward with
+/// [`advance`](Self::advance).
+#[derive(Clone)]
+pub struct SimClock {
+    /// Shared time source
+    source: Arc<Source>,
+}
+
+impl SimClock {
+    /// Create a clock following the host monotonic clock
+    pub fn real_time() -> Self {
+        Self {
+            source: Arc::new(Source::RealTime(StdInstant::now())),
+        }
+    }
+
+    /// Create a virtual clock starting at 0
+    pub fn virtual_time() -> Self {
+        Self {
+            source: Arc::new(Source::Virtual {
+                now: AtomicU64::new(0),
+                step: 1,
+            }),
+        }
+    }
+
+    /// Whether this clock follows the host monotonic clock
+    pub fn is_real_time(&self) -> bool {
+        matches!(*self.source, Source::RealTime(_))
+    }
+
+    /// Advance a virtual clock
+    ///
+    /// Note: This has no effect on a real-time clock
+    pub fn advance(&self, duration: Duration) {
+        if let Source::Virtual { now, .. } = &*self.source {
+            now.fetch_add(duration.as_micros() as u64, Ordering::SeqCst);
+        }
+    }
+
+    /// Time since the clock was created, without advancing a virtual clock
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_micros(self.micros())
+    }
+
+    /// Current time in microseconds, without advancing a virtual clock
+    pub(crate) fn micros(&self) -> u64 {
+        match &*self.source {
+            Source::RealTime(start) => start.elapsed().as_micros() as u64,
+            Source::Virtual { now, .. } => now.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl Clock for SimClock {
+    type Instant = Instant64<1_000_000>;
+
+    fn now(&self) -> Self::Instant {
+        let micros = match &*self.source {
+            Source::RealTime(start) => start.elapsed().as_micros() as u64,
+            Source::Virtual { now, step } => now.fetch_add(*step, Ordering::SeqCst),
+        };
+        Instant64::new(micros)
+    }
+}
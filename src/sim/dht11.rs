@@ -0,0 +1,250 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+use super::{fault::Faults, SimClock, SimError, Waveform};
+
+/// Faults of a simulated DHT11
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dht11Fault {
+    /// Do not answer the start signal, counted per start signal
+    NoResponse,
+    /// Send a wrong checksum, counted per start signal
+    CheckSum,
+    /// Fail the pin operation, counted per pin operation
+    Io,
+}
+
+/// Shared state of a simulated DHT11
+struct State {
+    /// Time source
+    clock: SimClock,
+    /// Temperature in °C
+    temperature: Waveform,
+    /// Relative humidity in %RH
+    humidity: Waveform,
+    /// Data bytes of the last measurement
+    /// (humidity, humidity decimal, temperature, temperature decimal, checksum)
+    data: [u8; 5],
+    /// Time the host pulled the line low
+    host_low_since: Option<u64>,
+    /// Time the sensor started answering
+    response_start: Option<u64>,
+    /// Frame being sent
+    frame: [u8; 5],
+    /// Injected faults
+    faults: Faults<Dht11Fault>,
+}
+
+/// Simulated DHT11 1-Wire data line
+///
+/// When the host holds the line low for at least 18ms and releases it, the sensor answers with
+/// the response signal followed by the 40 data bits, timed against the [`SimClock`]. Like the
+/// real sensor it sends the result of the previous measurement and then measures again, the
+/// first measurement is taken on power up.
+#[derive(Clone)]
+pub struct Dht11Device {
+    /// Shared state
+    state: Arc<Mutex<State>>,
+}
+
+impl Dht11Device {
+    /// Create a sensor reporting 25°C and 50%RH
+    pub fn new(clock: &SimClock) -> Self {
+        let mut state = State {
+            clock: clock.clone(),
+            temperature: Waveform::Constant(25.0),
+            humidity: Waveform::Constant(50.0),
+            data: [0; 5],
+            host_low_since: None,
+            response_start: None,
+            frame: [0; 5],
+            faults: Faults::new(),
+        };
+        state.measure();
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Set the temperature waveform in °C
+    pub fn set_temperature(&self, waveform: impl Into<Waveform>) {
+        self.state().temperature = waveform.into();
+    }
+
+    /// Set the relative humidity waveform in %RH
+    pub fn set_humidity(&self, waveform: impl Into<Waveform>) {
+        self.state().humidity = waveform.into();
+    }
+
+    /// Take a new measurement now, instead of waiting for the next start signal
+    pub fn measure(&self) {
+        self.state().measure();
+    }
+
+    /// Inject a fault until [`clear_faults`](Self::clear_faults) is called
+    pub fn inject(&self, fault: Dht11Fault) {
+        self.state().faults.inject(fault, None);
+    }
+
+    /// Inject a fault for the given number of occurrences
+    pub fn inject_times(&self, fault: Dht11Fault, times: u32) {
+        self.state().faults.inject(fault, Some(times));
+    }
+
+    /// Remove all injected faults
+    pub fn clear_faults(&self) {
+        self.state().faults.clear();
+    }
+}
+
+impl State {
+    /// Sample the waveforms with the resolution of the sensor
+    fn measure(&mut self) {
+        let t = self.clock.elapsed();
+        let humidity = self.humidity.value_at(t).round().clamp(0.0, 100.0) as u8;
+        // 温度保留一位小数，小数字节最高位表示负数
+        let tenths = (self.temperature.value_at(t) * 10.0)
+            .round()
+            .clamp(-1279.0, 1279.0) as i32;
+        let mut decimal = (tenths.unsigned_abs() % 10) as u8;
+        if tenths < 0 {
+            decimal |= 0x80;
+        }
+        self.data = [humidity, 0, (tenths.unsigned_abs() / 10) as u8, decimal, 0];
+        self.data[4] = self.data[..4]
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_add(*b));
+    }
+
+    /// Line level driven by the sensor at the given time
+    fn sensor_level(&self, now: u64) -> bool {
+        let Some(start) = self.response_start else {
+            return true;
+        };
+        // 响应信号：83us低电平 + 87us高电平
+        let mut t = now - start;
+        if t < 83 {
+            return false;
+        }
+        if t < 170 {
+            return true;
+        }
+        t -= 170;
+        // 数据位：54us低电平 + 26us(0)或70us(1)高电平
+        for byte in self.frame {
+            for bit in (0..8).rev() {
+                let high = if byte & (1 << bit) != 0 { 70 } else { 26 };
+                if t < 54 {
+                    return false;
+                }
+                if t < 54 + high {
+                    return true;
+                }
+                t -= 54 + high;
+            }
+        }
+        // 结束信号：54us低电平后释放总线
+        t >= 54
+    }
+
+    fn check_io(&mut self) -> Result<(), SimError> {
+        if self.faults.take(Dht11Fault::Io) {
+            return Err(SimError::Io);
+        }
+        Ok(())
+    }
+}
+
+impl ErrorType for Dht11Device {
+    type Error = SimError;
+}
+
+impl OutputPin for Dht11Device {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.state();
+        state.check_io()?;
+        state.host_low_since = Some(state.clock.micros());
+        state.response_start = None;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.state();
+        state.check_io()?;
+        // 主机释放总线，起始信号足够长时传感器开始响应
+        if let Some(since) = state.host_low_since.take() {
+            let now = state.clock.micros();
+            if now - since >= 18_000 && !state.faults.take(Dht11Fault::NoResponse) {
+                state.frame = state.data;
+                if state.faults.take(Dht11Fault::CheckSum) {
+                    state.frame[4] = state.frame[4].wrapping_add(1);
+                }
+                state.response_start = Some(now);
+                // 发送上一次的测量结果后，重新测量
+                state.measure();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InputPin for Dht11Device {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let mut state = self.state();
+        state.check_io()?;
+        if state.host_low_since.is_some() {
+            return Ok(false);
+        }
+        Ok(state.sensor_level(state.clock.micros()))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dht11Device, Dht11Fault};
+    use crate::{
+        dht11::{Driver, Error},
+        sim::SimClock,
+    };
+
+    #[test]
+    fn sends_previous_measurement() {
+        let clock = SimClock::virtual_time();
+        let device = Dht11Device::new(&clock);
+        let mut driver = Driver::new(&clock, device.clone()).unwrap();
+
+        device.set_temperature(-3.4);
+        device.set_humidity(61.6);
+        let (temperature, humidity) = driver.read().unwrap();
+        assert!((temperature - 25.0).abs() < 0.01);
+        assert_eq!(humidity, 50.0);
+
+        let (temperature, humidity) = driver.read().unwrap();
+        assert!((temperature + 3.4).abs() < 0.01);
+        assert_eq!(humidity, 62.0);
+    }
+
+    #[test]
+    fn injected_faults() {
+        let clock = SimClock::virtual_time();
+        let device = Dht11Device::new(&clock);
+        let mut driver = Driver::new(&clock, device.clone()).unwrap();
+
+        device.inject_times(Dht11Fault::NoResponse, 1);
+        assert!(matches!(driver.read(), Err(Error::NotReady)));
+        device.inject_times(Dht11Fault::CheckSum, 1);
+        assert!(matches!(driver.read(), Err(Error::CheckSum)));
+        device.inject_times(Dht11Fault::Io, 1);
+        assert!(matches!(driver.read(), Err(Error::Output(_))));
+        assert!(driver.read().is_ok());
+    }
+}
@@ -0,0 +1,46 @@
+use std::vec::Vec;
+
+/// Injected faults of a simulated device
+///
+/// Every fault is either permanent or armed for a number of occurrences, each time the device
+/// applies the fault one occurrence is consumed.
+pub(crate) struct Faults<F> {
+    /// Active faults with the remaining occurrences
+    entries: Vec<(F, Option<u32>)>,
+}
+
+impl<F: Copy + PartialEq> Faults<F> {
+    /// Create an empty fault list
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Arm a fault, replacing an already armed one of the same kind
+    pub fn inject(&mut self, fault: F, times: Option<u32>) {
+        self.entries.retain(|(armed, _)| *armed != fault);
+        if times != Some(0) {
+            self.entries.push((fault, times));
+        }
+    }
+
+    /// Disarm all faults
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Consume one occurrence of the fault, returns whether it applies
+    pub fn take(&mut self, fault: F) -> bool {
+        let Some(index) = self.entries.iter().position(|(armed, _)| *armed == fault) else {
+            return false;
+        };
+        if let Some(remaining) = &mut self.entries[index].1 {
+            *remaining -= 1;
+            if *remaining == 0 {
+                self.entries.remove(index);
+            }
+        }
+        true
+    }
+}
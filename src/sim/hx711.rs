@@ -0,0 +1,314 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+use super::{fault::Faults, SimClock, SimError, Waveform};
+
+/// Largest conversion result of the 24 bit ADC
+const MAX_VALUE: i32 = 0x7F_FFFF;
+
+/// Faults of a simulated HX711
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hx711Fault {
+    /// Keep DOUT high as if no conversion finished, counted per ready check
+    NotReady,
+    /// Output the largest positive value, counted per conversion read
+    Saturated,
+    /// Fail the pin operation, counted per pin operation
+    Io,
+}
+
+/// Shared state of a simulated HX711
+struct State {
+    /// Time source
+    clock: SimClock,
+    /// Load on the bridge, in the unit of the scale
+    weight: Waveform,
+    /// Conversion result without load
+    offset: i32,
+    /// Counts per unit of weight at channel A gain 128
+    scale: f32,
+    /// Time between two conversions
+    period: Duration,
+    /// Time the next conversion result is ready
+    ready_at: u64,
+    /// Gain of the next conversion (128, 64 or 32)
+    gain: u8,
+    /// Conversion result being shifted out
+    value: i32,
+    /// Level of the clock pin
+    clock_high: bool,
+    /// Time the clock pin went high
+    high_since: u64,
+    /// Clock pulses of the read in progress
+    pulses: u8,
+    /// Injected faults
+    faults: Faults<Hx711Fault>,
+}
+
+/// Simulated HX711 24 bit ADC with a load cell
+///
+/// A new conversion result is ready one period (100ms by default, the 10SPS mode) after the
+/// previous one was read. It equals `offset + weight * scale` at gain 128, channel A gain 64
+/// halves and channel B gain 32 quarters the signal. Use [`clock_pin`](Self::clock_pin) and
+/// [`data_pin`](Self::data_pin) to create the pins for the driver.
+#[derive(Clone)]
+pub struct Hx711Device {
+    /// Shared state
+    state: Arc<Mutex<State>>,
+}
+
+impl Hx711Device {
+    /// Create a device without load, an offset of 0 and 1 count per unit
+    pub fn new(clock: &SimClock) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                clock: clock.clone(),
+                weight: Waveform::Constant(0.0),
+                offset: 0,
+                scale: 1.0,
+                period: Duration::from_millis(100),
+                ready_at: clock.micros(),
+                gain: 128,
+                value: 0,
+                clock_high: false,
+                high_since: 0,
+                pulses: 0,
+                faults: Faults::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Set the weight waveform
+    pub fn set_weight(&self, waveform: impl Into<Waveform>) {
+        self.state().weight = waveform.into();
+    }
+
+    /// Set the conversion result without load and the counts per unit of weight
+    pub fn set_scale(&self, offset: i32, scale: f32) {
+        let mut state = self.state();
+        state.offset = offset;
+        state.scale = scale;
+    }
+
+    /// Set the time between two conversions
+    pub fn set_period(&self, period: Duration) {
+        self.state().period = period;
+    }
+
+    /// Create the clock (PD_SCK) pin
+    pub fn clock_pin(&self) -> Hx711ClockPin {
+        Hx711ClockPin(self.clone())
+    }
+
+    /// Create the data (DOUT) pin
+    pub fn data_pin(&self) -> Hx711DataPin {
+        Hx711DataPin(self.clone())
+    }
+
+    /// Inject a fault until [`clear_faults`](Self::clear_faults) is called
+    pub fn inject(&self, fault: Hx711Fault) {
+        self.state().faults.inject(fault, None);
+    }
+
+    /// Inject a fault for the given number of occurrences
+    pub fn inject_times(&self, fault: Hx711Fault, times: u32) {
+        self.state().faults.inject(fault, Some(times));
+    }
+
+    /// Remove all injected faults
+    pub fn clear_faults(&self) {
+        self.state().faults.clear();
+    }
+}
+
+impl State {
+    fn check_io(&mut self) -> Result<(), SimError> {
+        if self.faults.take(Hx711Fault::Io) {
+            return Err(SimError::Io);
+        }
+        Ok(())
+    }
+
+    /// Return to idle once the next conversion is ready
+    fn idle_if_ready(&mut self) {
+        if self.pulses > 24 && self.clock.micros() >= self.ready_at {
+            self.pulses = 0;
+        }
+    }
+
+    /// Latch the conversion result at the first clock pulse
+    fn convert(&mut self) {
+        if self.faults.take(Hx711Fault::Saturated) {
+            self.value = MAX_VALUE;
+            return;
+        }
+        let weight = self.weight.value_at(self.clock.elapsed());
+        let signal = weight * self.scale * self.gain as f32 / 128.0;
+        self.value = (self.offset as f32 + signal)
+            .round()
+            .clamp(-(MAX_VALUE as f32) - 1.0, MAX_VALUE as f32) as i32;
+    }
+
+    fn data_level(&mut self) -> bool {
+        self.idle_if_ready();
+        match self.pulses {
+            // 空闲时，数据就绪输出低电平
+            0 => self.clock.micros() < self.ready_at || self.faults.take(Hx711Fault::NotReady),
+            // 每个上升沿输出一位数据，高位在前
+            1..=24 => self.value & (1 << (24 - self.pulses)) != 0,
+            // 第25个脉冲后输出高电平，直到下一次转换完成
+            _ => true,
+        }
+    }
+}
+
+/// HX711 clock (PD_SCK) pin
+pub struct Hx711ClockPin(Hx711Device);
+
+impl ErrorType for Hx711ClockPin {
+    type Error = SimError;
+}
+
+impl OutputPin for Hx711ClockPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.0.state();
+        state.check_io()?;
+        if state.clock_high {
+            state.clock_high = false;
+            // 时钟高电平保持60us以上则进入断电模式，再次拉低后重新上电并恢复A通道128增益
+            if state.clock.micros() - state.high_since >= 60 {
+                state.pulses = 0;
+                state.gain = 128;
+                state.ready_at = state.clock.micros() + state.period.as_micros() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.0.state();
+        state.check_io()?;
+        if !state.clock_high {
+            state.idle_if_ready();
+            state.clock_high = true;
+            state.high_since = state.clock.micros();
+            if state.pulses == 0 {
+                state.convert();
+            }
+            state.pulses = state.pulses.saturating_add(1);
+            // 第25~27个脉冲选择下一次转换的通道和增益，并开始下一次转换
+            match state.pulses {
+                25 => {
+                    state.gain = 128;
+                    state.ready_at = state.high_since + state.period.as_micros() as u64;
+                }
+                26 => state.gain = 32,
+                27 => state.gain = 64,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// HX711 data (DOUT) pin
+pub struct Hx711DataPin(Hx711Device);
+
+impl ErrorType for Hx711DataPin {
+    type Error = SimError;
+}
+
+impl InputPin for Hx711DataPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let mut state = self.0.state();
+        state.check_io()?;
+        Ok(state.data_level())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Hx711Device, Hx711Fault};
+    use crate::{
+        hx711::{ChannelGain, Driver, Error},
+        sim::SimClock,
+    };
+
+    #[test]
+    fn converts_weight_with_gain() {
+        let clock = SimClock::virtual_time();
+        let device = Hx711Device::new(&clock);
+        device.set_scale(-8_000, 420.0);
+        device.set_weight(100.0);
+
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelA64,
+        )
+        .unwrap();
+        assert_eq!(driver.read().unwrap(), 34_000);
+        assert!(matches!(driver.read(), Err(Error::NotReady)));
+
+        // 下一次转换使用64增益
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(driver.read().unwrap(), 13_000);
+    }
+
+    #[test]
+    fn reset_restores_default_gain() {
+        let clock = SimClock::virtual_time();
+        let device = Hx711Device::new(&clock);
+        device.set_weight(1000.0);
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelB32,
+        )
+        .unwrap();
+        driver.read().unwrap();
+        driver.reset().unwrap();
+        assert!(!driver.is_ready().unwrap());
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(driver.read().unwrap(), 1000);
+    }
+
+    #[test]
+    fn injected_faults() {
+        let clock = SimClock::virtual_time();
+        let device = Hx711Device::new(&clock);
+        device.set_period(Duration::ZERO);
+        let mut driver = Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            ChannelGain::ChannelA128,
+        )
+        .unwrap();
+
+        device.inject_times(Hx711Fault::NotReady, 1);
+        assert!(matches!(driver.read(), Err(Error::NotReady)));
+        device.inject_times(Hx711Fault::Saturated, 1);
+        assert_eq!(driver.read().unwrap(), 0x7F_FFFF);
+        device.inject(Hx711Fault::Io);
+        assert!(matches!(driver.read(), Err(Error::Input(_))));
+        device.clear_faults();
+        assert_eq!(driver.read().unwrap(), 0);
+    }
+}
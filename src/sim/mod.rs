@@ -0,0 +1,138 @@
+//! Software simulated sensors for running applications on a host
+//!
+//! The simulated devices implement the `embedded_hal` I2C and GPIO traits, so the drivers of
+//! this crate run on them unchanged. Temperature, humidity, pressure and weight follow a
+//! [`Waveform`] over the [`SimClock`] time, and faults can be injected on every device.
+//!
+//! Every device is a cheap handle to shared state: keep a clone to change the waveforms or
+//! inject faults while a driver owns the other one.
+//!
+//! Only available with the `std` feature.
+
+mod aht30;
+mod bme280;
+mod clock;
+mod dht11;
+mod fault;
+mod hx711;
+mod waveform;
+
+use std::{boxed::Box, fmt::Display, vec::Vec};
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+pub use aht30::{Aht30Device, Aht30Fault};
+pub use bme280::{Bme280Device, Bme280Fault, CALIBRATION};
+pub use clock::SimClock;
+pub use dht11::{Dht11Device, Dht11Fault};
+pub use hx711::{Hx711ClockPin, Hx711DataPin, Hx711Device, Hx711Fault};
+pub use waveform::Waveform;
+
+/// Error returned by the simulated devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimError {
+    /// The addressed I2C device did not acknowledge
+    Nack,
+    /// Injected I/O failure
+    Io,
+}
+
+impl Display for SimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nack => write!(f, "The simulated I2C device did not acknowledge."),
+            Self::Io => write!(f, "The simulated device reported an I/O failure."),
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+impl embedded_hal::digital::Error for SimError {
+    fn kind(&self) -> embedded_hal::digital::ErrorKind {
+        embedded_hal::digital::ErrorKind::Other
+    }
+}
+
+impl embedded_hal::i2c::Error for SimError {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+        match self {
+            Self::Nack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Self::Io => ErrorKind::Other,
+        }
+    }
+}
+
+/// Simulated I2C device that can be attached to a [`SimBus`]
+pub trait I2cTarget: I2c<SevenBitAddress, Error = SimError> + Send {
+    /// 7bit address the device answers on
+    fn address(&self) -> u8;
+}
+
+/// Simulated I2C bus with several devices attached
+///
+/// Transactions are routed by address, addresses without a device are not acknowledged.
+#[derive(Default)]
+pub struct SimBus {
+    /// Attached devices
+    devices: Vec<Box<dyn I2cTarget>>,
+}
+
+impl SimBus {
+    /// Create a bus without devices
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a device
+    pub fn attach<D: I2cTarget + 'static>(&mut self, device: D) -> &mut Self {
+        self.devices.push(Box::new(device));
+        self
+    }
+}
+
+impl ErrorType for SimBus {
+    type Error = SimError;
+}
+
+impl I2c<SevenBitAddress> for SimBus {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        // 按地址将传输转发给对应的设备
+        match self
+            .devices
+            .iter_mut()
+            .find(|device| device.address() == address)
+        {
+            Some(device) => device.transaction(address, operations),
+            None => Err(SimError::Nack),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::I2c;
+
+    use super::{Aht30Device, Bme280Device, SimBus, SimClock, SimError};
+    use crate::{aht30, bme280};
+
+    #[test]
+    fn routes_transactions_by_address() {
+        let clock = SimClock::virtual_time();
+        let bme280_device = Bme280Device::new(&clock);
+        bme280_device.set_address(0x77);
+        let mut bus = SimBus::new();
+        bus.attach(Aht30Device::new(&clock)).attach(bme280_device);
+
+        let mut aht30 = aht30::Driver::new(&clock, &mut bus, None).unwrap();
+        let mut bme280 = bme280::Driver::new(&clock, &mut bus, Some(0x77)).unwrap();
+        assert!(aht30.read(&mut bus).is_ok());
+        assert!(bme280.read(&mut bus).is_ok());
+        assert_eq!(bus.write(0x76, &[0xD0]), Err(SimError::Nack));
+    }
+}
@@ -0,0 +1,175 @@
+use std::{boxed::Box, time::Duration, vec::Vec};
+
+/// Value of a simulated physical quantity over time
+///
+/// The time passed to [`value_at`](Self::value_at) is the [`SimClock`](super::SimClock) time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Waveform {
+    /// Fixed value
+    Constant(f32),
+    /// Sine wave around `offset`
+    Sine {
+        offset: f32,
+        amplitude: f32,
+        period: Duration,
+    },
+    /// Square wave starting with `low` for the first half period
+    Square {
+        low: f32,
+        high: f32,
+        period: Duration,
+    },
+    /// Linear change from `from` to `to`, holding `to` afterwards
+    Ramp {
+        from: f32,
+        to: f32,
+        duration: Duration,
+    },
+    /// Piecewise constant value, each step applies from its time on
+    ///
+    /// The steps must be sorted by time, the value before the first step is the first value.
+    Steps(Vec<(Duration, f32)>),
+    /// Another waveform with uniform noise of ±`amplitude` added
+    Noisy {
+        base: Box<Waveform>,
+        amplitude: f32,
+        seed: u64,
+    },
+}
+
+impl Waveform {
+    /// Add deterministic uniform noise of ±amplitude
+    pub fn with_noise(self, amplitude: f32, seed: u64) -> Self {
+        Self::Noisy {
+            base: Box::new(self),
+            amplitude,
+            seed,
+        }
+    }
+
+    /// Value at the given time
+    pub fn value_at(&self, t: Duration) -> f32 {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Sine {
+                offset,
+                amplitude,
+                period,
+            } => {
+                let phase = Self::phase(t, *period);
+                offset + amplitude * (phase * 2.0 * core::f64::consts::PI).sin() as f32
+            }
+            Self::Square { low, high, period } => {
+                if Self::phase(t, *period) < 0.5 {
+                    *low
+                } else {
+                    *high
+                }
+            }
+            Self::Ramp { from, to, duration } => {
+                if t >= *duration {
+                    return *to;
+                }
+                let progress = t.as_secs_f64() / duration.as_secs_f64();
+                from + (to - from) * progress as f32
+            }
+            Self::Steps(steps) => {
+                let mut value = steps.first().map(|(_, value)| *value).unwrap_or(0.0);
+                for (start, step) in steps {
+                    if t < *start {
+                        break;
+                    }
+                    value = *step;
+                }
+                value
+            }
+            Self::Noisy {
+                base,
+                amplitude,
+                seed,
+            } => base.value_at(t) + amplitude * Self::noise(t, *seed),
+        }
+    }
+
+    /// Position in the period, 0.0 ~ 1.0
+    fn phase(t: Duration, period: Duration) -> f64 {
+        if period.is_zero() {
+            return 0.0;
+        }
+        (t.as_secs_f64() / period.as_secs_f64()).fract()
+    }
+
+    /// Uniform noise in -1.0 ~ 1.0, derived from the time with splitmix64
+    fn noise(t: Duration, seed: u64) -> f32 {
+        let mut z = (t.as_micros() as u64)
+            .wrapping_add(seed)
+            .wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        ((z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0) as f32
+    }
+}
+
+impl From<f32> for Waveform {
+    fn from(value: f32) -> Self {
+        Self::Constant(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{time::Duration, vec};
+
+    use super::Waveform;
+
+    #[test]
+    fn evaluates_periodic_waveforms() {
+        let sine = Waveform::Sine {
+            offset: 20.0,
+            amplitude: 5.0,
+            period: Duration::from_secs(4),
+        };
+        assert!((sine.value_at(Duration::ZERO) - 20.0).abs() < 1e-4);
+        assert!((sine.value_at(Duration::from_secs(1)) - 25.0).abs() < 1e-4);
+        assert!((sine.value_at(Duration::from_secs(7)) - 15.0).abs() < 1e-4);
+
+        let square = Waveform::Square {
+            low: 1.0,
+            high: 2.0,
+            period: Duration::from_secs(2),
+        };
+        assert_eq!(square.value_at(Duration::from_millis(500)), 1.0);
+        assert_eq!(square.value_at(Duration::from_millis(1500)), 2.0);
+    }
+
+    #[test]
+    fn ramps_and_steps_hold_last_value() {
+        let ramp = Waveform::Ramp {
+            from: 0.0,
+            to: 10.0,
+            duration: Duration::from_secs(10),
+        };
+        assert_eq!(ramp.value_at(Duration::from_secs(3)), 3.0);
+        assert_eq!(ramp.value_at(Duration::from_secs(30)), 10.0);
+
+        let steps = Waveform::Steps(vec![
+            (Duration::from_secs(1), 1.0),
+            (Duration::from_secs(5), 2.0),
+        ]);
+        assert_eq!(steps.value_at(Duration::ZERO), 1.0);
+        assert_eq!(steps.value_at(Duration::from_secs(4)), 1.0);
+        assert_eq!(steps.value_at(Duration::from_secs(5)), 2.0);
+    }
+
+    #[test]
+    fn noise_is_bounded_and_deterministic() {
+        let noisy = Waveform::Constant(50.0).with_noise(0.5, 7);
+        for ms in 0..1000 {
+            let t = Duration::from_millis(ms);
+            let value = noisy.value_at(t);
+            assert!((49.5..=50.5).contains(&value));
+            assert_eq!(value, noisy.value_at(t));
+        }
+    }
+}
@@ -0,0 +1,563 @@
+//! Persistent calibration records
+//!
+//! Calibration data is stored as a compact binary record:
+//!
+//! | Offset | Size | Content                                   |
+//! |--------|------|-------------------------------------------|
+//! | 0      | 2    | Magic `"SH"`                              |
+//! | 2      | 1    | Record kind, see [`Persist::KIND`]        |
+//! | 3      | 1    | Layout version, see [`Persist::VERSION`]  |
+//! | 4      | 1    | Sequence number, see [`save_nor`]         |
+//! | 5      | 1    | Payload length `n`                        |
+//! | 6      | n    | Payload, little endian                    |
+//! | 6 + n  | 2    | CRC-16/CCITT-FALSE of the bytes before it |
+//!
+//! Records are read and written through `embedded_storage`, either on byte addressable
+//! storage such as an EEPROM ([`load`]/[`save`]) or on NOR flash ([`load_nor`]/[`save_nor`]).
+//! On NOR flash two slots are written alternately, so a power loss during a save keeps the
+//! previous record.
+
+use core::fmt::{Debug, Formatter};
+
+use embedded_storage::{nor_flash::NorFlash, ReadStorage, Storage};
+
+use crate::calibration::{Correction, Record, Scale};
+
+/// Record magic
+const MAGIC: [u8; 2] = *b"SH";
+
+/// Size of the header before the payload
+const HEADER_SIZE: usize = 6;
+
+/// Size of the CRC after the payload
+const CRC_SIZE: usize = 2;
+
+/// Largest supported record including the padding to the flash write size
+const BUFFER_SIZE: usize = 128;
+
+/// Calibration data that can be stored as a record
+pub trait Persist: Sized {
+    /// Record kind, a record of another kind is rejected when loading
+    const KIND: u8;
+    /// Layout version written with the record
+    const VERSION: u8;
+    /// Payload size in bytes
+    const SIZE: usize;
+
+    /// Write the payload, `payload` is exactly [`SIZE`](Self::SIZE) bytes long
+    fn encode(&self, payload: &mut [u8]);
+
+    /// Read a payload written with `version`
+    ///
+    /// Returns `None` for an unsupported version or payload length. Older layouts can be
+    /// migrated here when the version is increased.
+    fn decode(version: u8, payload: &[u8]) -> Option<Self>;
+}
+
+/// Calibration storage error
+pub enum Error<E> {
+    /// Storage raw error
+    Storage(E),
+    /// No record at this offset, e.g. erased flash
+    NotFound,
+    /// The record holds another kind of calibration data
+    Kind(u8),
+    /// Unsupported record version
+    Version(u8),
+    /// The CRC verification of the record failed
+    Crc,
+    /// The record does not fit the buffer or the flash geometry
+    Size,
+}
+
+impl<E: Debug> Debug for Error<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Storage(err) => write!(f, "Storage error: {:?}", err),
+            Self::NotFound => write!(f, "No calibration record found."),
+            Self::Kind(kind) => write!(f, "Unexpected calibration record kind {}.", kind),
+            Self::Version(version) => {
+                write!(f, "Unsupported calibration record version {}.", version)
+            }
+            Self::Crc => write!(f, "The CRC verification of the record failed."),
+            Self::Size => write!(f, "The calibration record does not fit."),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Debug> std::fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Debug> std::error::Error for Error<E> {}
+
+#[cfg(feature = "defmt")]
+impl<E> defmt::Format for Error<E> {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Self::Storage(_) => defmt::write!(f, "Storage error."),
+            Self::NotFound => defmt::write!(f, "No calibration record found."),
+            Self::Kind(kind) => defmt::write!(f, "Unexpected calibration record kind {}.", kind),
+            Self::Version(version) => {
+                defmt::write!(f, "Unsupported calibration record version {}.", version)
+            }
+            Self::Crc => defmt::write!(f, "The CRC verification of the record failed."),
+            Self::Size => defmt::write!(f, "The calibration record does not fit."),
+        }
+    }
+}
+
+/// Size of the record of `T` in bytes, without flash padding
+pub const fn record_size<T: Persist>() -> usize {
+    HEADER_SIZE + T::SIZE + CRC_SIZE
+}
+
+/// Load a record from byte addressable storage
+pub fn load<S: ReadStorage, T: Persist>(
+    storage: &mut S,
+    offset: u32,
+) -> Result<T, Error<S::Error>> {
+    read_record(offset, 1, |offset, bytes| storage.read(offset, bytes)).map(|(_, value)| value)
+}
+
+/// Save a record to byte addressable storage
+pub fn save<S: Storage, T: Persist>(
+    storage: &mut S,
+    offset: u32,
+    value: &T,
+) -> Result<(), Error<S::Error>> {
+    let mut buffer = [0xFF; BUFFER_SIZE];
+    let len = encode(value, 0, &mut buffer)?;
+    storage
+        .write(offset, &buffer[..len])
+        .map_err(Error::Storage)
+}
+
+/// Size of one of the two NOR flash slots used by [`load_nor`] and [`save_nor`]
+///
+/// The record padded to whole erase sectors of the flash.
+pub fn nor_slot_size<F: NorFlash, T: Persist>() -> usize {
+    align_up(align_up(record_size::<T>(), F::WRITE_SIZE), F::ERASE_SIZE)
+}
+
+/// Load the newest valid record from the two NOR flash slots at `offset`
+///
+/// When neither slot holds a valid record, the error of the first slot is returned unless
+/// it is empty.
+///
+/// Note: `offset` must be aligned to the read size of the flash
+pub fn load_nor<F: NorFlash, T: Persist>(flash: &mut F, offset: u32) -> Result<T, Error<F::Error>> {
+    let [first, second] = read_slots::<F, T>(flash, offset);
+    match (first, second) {
+        (Ok((a, first)), Ok((b, second))) => Ok(if newer(b, a) { second } else { first }),
+        (Ok((_, value)), Err(_)) | (Err(_), Ok((_, value))) => Ok(value),
+        (Err(Error::NotFound), Err(err)) | (Err(err), Err(_)) => Err(err),
+    }
+}
+
+/// Save a record to the older of the two NOR flash slots at `offset`
+///
+/// The slots take [`nor_slot_size`] bytes each. Only the slot being written is erased and
+/// the record carries a sequence number one higher than the other slot, so [`load_nor`]
+/// returns the previous record if the power fails before the new one is complete.
+/// A read error of either slot is returned before anything is erased.
+///
+/// Note: `offset` must be aligned to the erase size. Do not keep other data in the slots.
+pub fn save_nor<F: NorFlash, T: Persist>(
+    flash: &mut F,
+    offset: u32,
+    value: &T,
+) -> Result<(), Error<F::Error>> {
+    let slot_size = nor_slot_size::<F, T>();
+    // 写入长度需要按写入单元对齐，补齐部分保持擦除状态
+    let len = align_up(record_size::<T>(), F::WRITE_SIZE);
+    if len > BUFFER_SIZE {
+        return Err(Error::Size);
+    }
+
+    // 写入较旧或无效的槽位，保留最新的记录
+    let [first, second] = read_slots::<F, T>(flash, offset);
+    let (slot, sequence) = match (sequence(first)?, sequence(second)?) {
+        (Some(a), Some(b)) if newer(b, a) => (0, b.wrapping_add(1)),
+        (Some(a), _) => (1, a.wrapping_add(1)),
+        (None, Some(b)) => (0, b.wrapping_add(1)),
+        (None, None) => (0, 0),
+    };
+    let mut buffer = [0xFF; BUFFER_SIZE];
+    encode(value, sequence, &mut buffer)?;
+
+    let start = offset as usize + slot * slot_size;
+    flash
+        .erase(start as u32, (start + slot_size) as u32)
+        .map_err(Error::Storage)?;
+    flash
+        .write(start as u32, &buffer[..len])
+        .map_err(Error::Storage)
+}
+
+/// A record read from a NOR flash slot with its sequence number
+type Slot<T, E> = Result<(u8, T), Error<E>>;
+
+/// Read the records of both NOR flash slots with their sequence numbers
+fn read_slots<F: NorFlash, T: Persist>(flash: &mut F, offset: u32) -> [Slot<T, F::Error>; 2] {
+    let slot_size = nor_slot_size::<F, T>() as u32;
+    [offset, offset.wrapping_add(slot_size)].map(|offset| {
+        read_record(offset, F::READ_SIZE, |offset, bytes| {
+            flash.read(offset, bytes)
+        })
+    })
+}
+
+/// Sequence number of a valid slot, `None` for a blank or invalid slot
+///
+/// Read errors are returned, the slot contents are unknown and must not be erased.
+fn sequence<T, E>(slot: Slot<T, E>) -> Result<Option<u8>, Error<E>> {
+    match slot {
+        Ok((sequence, _)) => Ok(Some(sequence)),
+        Err(Error::Storage(err)) => Err(Error::Storage(err)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Whether sequence number `a` was written after `b`, allowing for wrap around
+fn newer(a: u8, b: u8) -> bool {
+    (a.wrapping_sub(b) as i8) > 0
+}
+
+/// Round `len` up to a multiple of `align`
+fn align_up(len: usize, align: usize) -> usize {
+    len.div_ceil(align.max(1)) * align.max(1)
+}
+
+/// Write the record of `value` to the start of `buffer`, returns the record size
+fn encode<T: Persist, E>(value: &T, sequence: u8, buffer: &mut [u8]) -> Result<usize, Error<E>> {
+    let len = record_size::<T>();
+    if len > buffer.len() || T::SIZE > u8::MAX as usize {
+        return Err(Error::Size);
+    }
+    buffer[..2].copy_from_slice(&MAGIC);
+    buffer[2] = T::KIND;
+    buffer[3] = T::VERSION;
+    buffer[4] = sequence;
+    buffer[5] = T::SIZE as u8;
+    value.encode(&mut buffer[HEADER_SIZE..HEADER_SIZE + T::SIZE]);
+    let crc = crc16(&buffer[..HEADER_SIZE + T::SIZE]);
+    buffer[HEADER_SIZE + T::SIZE..len].copy_from_slice(&crc.to_le_bytes());
+    // OK
+    Ok(len)
+}
+
+/// Read and verify a record with reads aligned to `read_size`, returns its sequence number
+fn read_record<T: Persist, E>(
+    offset: u32,
+    read_size: usize,
+    mut read: impl FnMut(u32, &mut [u8]) -> Result<(), E>,
+) -> Result<(u8, T), Error<E>> {
+    let mut buffer = [0u8; BUFFER_SIZE];
+
+    // 先读取头部以获取负载长度
+    let header_len = align_up(HEADER_SIZE, read_size);
+    if header_len > BUFFER_SIZE {
+        return Err(Error::Size);
+    }
+    read(offset, &mut buffer[..header_len]).map_err(Error::Storage)?;
+    if buffer[..2] != MAGIC {
+        return Err(Error::NotFound);
+    }
+    let payload_len = buffer[5] as usize;
+
+    // 读取完整记录
+    let len = HEADER_SIZE + payload_len + CRC_SIZE;
+    let aligned_len = align_up(len, read_size);
+    if aligned_len > BUFFER_SIZE {
+        return Err(Error::Size);
+    }
+    if aligned_len > header_len {
+        read(
+            offset + header_len as u32,
+            &mut buffer[header_len..aligned_len],
+        )
+        .map_err(Error::Storage)?;
+    }
+
+    // 先校验CRC，再检查头部内容，避免把损坏的记录报告为其他类型
+    let crc = u16::from_le_bytes([buffer[len - 2], buffer[len - 1]]);
+    if crc != crc16(&buffer[..len - CRC_SIZE]) {
+        return Err(Error::Crc);
+    }
+    if buffer[2] != T::KIND {
+        return Err(Error::Kind(buffer[2]));
+    }
+    let version = buffer[3];
+    let sequence = buffer[4];
+
+    T::decode(version, &buffer[HEADER_SIZE..HEADER_SIZE + payload_len])
+        .map(|value| (sequence, value))
+        .ok_or(Error::Version(version))
+}
+
+/// Calculate the CRC-16/CCITT-FALSE checksum
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for b in data {
+        crc ^= (*b as u16) << 8;
+        for _ in 0..8 {
+            if crc & 0x8000 != 0 {
+                crc = (crc << 1) ^ 0x1021;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// Read a little endian f32 at `index`
+fn read_f32(bytes: &[u8], index: usize) -> f32 {
+    let mut data = [0u8; 4];
+    data.copy_from_slice(&bytes[index..index + 4]);
+    f32::from_le_bytes(data)
+}
+
+impl Persist for Record {
+    const KIND: u8 = 1;
+    const VERSION: u8 = 1;
+    const SIZE: usize = 24;
+
+    fn encode(&self, payload: &mut [u8]) {
+        let corrections = [self.temperature, self.humidity, self.pressure];
+        for (chunk, correction) in payload.chunks_exact_mut(8).zip(corrections) {
+            chunk[..4].copy_from_slice(&correction.gain.to_le_bytes());
+            chunk[4..].copy_from_slice(&correction.offset.to_le_bytes());
+        }
+    }
+
+    fn decode(version: u8, payload: &[u8]) -> Option<Self> {
+        if version != Self::VERSION || payload.len() != Self::SIZE {
+            return None;
+        }
+        let correction =
+            |index| Correction::new(read_f32(payload, index), read_f32(payload, index + 4));
+        Some(Self {
+            temperature: correction(0),
+            humidity: correction(8),
+            pressure: correction(16),
+        })
+    }
+}
+
+impl Persist for Scale {
+    const KIND: u8 = 2;
+    const VERSION: u8 = 1;
+    const SIZE: usize = 8;
+
+    fn encode(&self, payload: &mut [u8]) {
+        payload[..4].copy_from_slice(&self.offset.to_le_bytes());
+        payload[4..].copy_from_slice(&self.factor.to_le_bytes());
+    }
+
+    fn decode(version: u8, payload: &[u8]) -> Option<Self> {
+        if version != Self::VERSION || payload.len() != Self::SIZE {
+            return None;
+        }
+        let mut offset = [0u8; 4];
+        offset.copy_from_slice(&payload[..4]);
+        Some(Self::new(i32::from_le_bytes(offset), read_f32(payload, 4)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_storage::{
+        nor_flash::{NorFlash, NorFlashErrorKind, ReadNorFlash},
+        Storage,
+    };
+
+    use super::{
+        crc16, load, load_nor, nor_slot_size, read_record, record_size, save, save_nor, Error,
+        Persist,
+    };
+    use crate::{
+        calibration::{Correction, Record, Scale},
+        mock::{MockEeprom, MockFlash},
+    };
+
+    fn record() -> Record {
+        Record {
+            temperature: Correction::from_offset(-1.5),
+            humidity: Correction::new(1.05, 2.0),
+            pressure: Correction::from_offset(-53.25),
+        }
+    }
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn record_round_trips_through_eeprom() {
+        let mut eeprom = MockEeprom::<64>::new();
+        assert!(matches!(
+            load::<_, Record>(&mut eeprom, 3),
+            Err(Error::NotFound)
+        ));
+
+        save(&mut eeprom, 3, &record()).unwrap();
+        assert_eq!(load::<_, Record>(&mut eeprom, 3).unwrap(), record());
+        assert_eq!(record_size::<Record>(), 32);
+        assert_eq!(eeprom.data()[3..5], *b"SH");
+        assert_eq!(eeprom.data()[35], 0xFF);
+    }
+
+    #[test]
+    fn scale_round_trips_through_nor_flash() {
+        let mut flash = MockFlash::new();
+        let scale = Scale::new(8_388, 420.0);
+        save_nor(&mut flash, 512, &scale).unwrap();
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 512).unwrap(), scale);
+
+        // 覆盖保存需要先擦除
+        let scale = Scale::new(-1_200, -415.5);
+        save_nor(&mut flash, 512, &scale).unwrap();
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 512).unwrap(), scale);
+        assert_eq!(flash.erases(), 2);
+
+        assert!(matches!(
+            load_nor::<_, Scale>(&mut flash, 0),
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            load_nor::<_, Scale>(&mut flash, 2),
+            Err(Error::Storage(_))
+        ));
+        assert!(save_nor(&mut flash, 4, &scale).is_err());
+    }
+
+    /// Offset of the scale stored in the NOR flash slot at `offset`
+    fn slot_offset(flash: &mut MockFlash, offset: u32) -> i32 {
+        let (_, scale) = read_record::<Scale, _>(offset, MockFlash::READ_SIZE, |offset, bytes| {
+            flash.read(offset, bytes)
+        })
+        .unwrap();
+        scale.offset
+    }
+
+    #[test]
+    fn nor_save_alternates_slots() {
+        let mut flash = MockFlash::new();
+        assert_eq!(nor_slot_size::<MockFlash, Record>(), 256);
+
+        save_nor(&mut flash, 0, &Scale::new(1, 1.0)).unwrap();
+        save_nor(&mut flash, 0, &Scale::new(2, 1.0)).unwrap();
+        // 两个槽位各保存一份记录
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 0).unwrap().offset, 2);
+        assert_eq!(slot_offset(&mut flash, 0), 1);
+        assert_eq!(slot_offset(&mut flash, 256), 2);
+
+        save_nor(&mut flash, 0, &Scale::new(3, 1.0)).unwrap();
+        assert_eq!(slot_offset(&mut flash, 0), 3);
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 0).unwrap().offset, 3);
+
+        // 序号回绕后仍然选择最新的记录
+        for offset in 4..600 {
+            save_nor(&mut flash, 0, &Scale::new(offset, 1.0)).unwrap();
+            assert_eq!(load_nor::<_, Scale>(&mut flash, 0).unwrap().offset, offset);
+        }
+    }
+
+    #[test]
+    fn nor_keeps_previous_record_on_power_loss() {
+        let mut flash = MockFlash::new();
+        save_nor(&mut flash, 0, &Scale::new(1, 1.0)).unwrap();
+        save_nor(&mut flash, 0, &Scale::new(2, 1.0)).unwrap();
+
+        // 下一次保存写入第一个槽位，擦除后掉电
+        flash.erase(0, 256).unwrap();
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 0).unwrap().offset, 2);
+
+        // 写入一半后掉电
+        flash.write(0, b"SH\x02\x01\x05\x08\x00\x00").unwrap();
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 0).unwrap().offset, 2);
+
+        // 下一次保存覆盖损坏的槽位
+        save_nor(&mut flash, 0, &Scale::new(3, 1.0)).unwrap();
+        assert_eq!(slot_offset(&mut flash, 0), 3);
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 0).unwrap().offset, 3);
+
+        // 两个槽位都损坏时返回错误
+        flash.erase(0, 512).unwrap();
+        flash.write(256, b"SH\x02\x01\x05\x08\x00\x00").unwrap();
+        assert!(matches!(
+            load_nor::<_, Scale>(&mut flash, 0),
+            Err(Error::Crc)
+        ));
+    }
+
+    #[test]
+    fn nor_save_keeps_slots_on_read_error() {
+        let mut flash = MockFlash::new();
+        save_nor(&mut flash, 0, &Scale::new(1, 1.0)).unwrap();
+        save_nor(&mut flash, 0, &Scale::new(2, 1.0)).unwrap();
+        assert_eq!(flash.erases(), 2);
+
+        // 无法判断哪个槽位有效时不擦除任何槽位
+        flash.set_fail_reads(true);
+        assert!(matches!(
+            save_nor(&mut flash, 0, &Scale::new(3, 1.0)),
+            Err(Error::Storage(NorFlashErrorKind::Other))
+        ));
+        assert_eq!(flash.erases(), 2);
+
+        flash.set_fail_reads(false);
+        assert_eq!(slot_offset(&mut flash, 0), 1);
+        assert_eq!(load_nor::<_, Scale>(&mut flash, 0).unwrap().offset, 2);
+    }
+
+    #[test]
+    fn rejects_corrupt_or_foreign_records() {
+        let mut eeprom = MockEeprom::<64>::new();
+        save(&mut eeprom, 0, &record()).unwrap();
+
+        assert!(matches!(
+            load::<_, Scale>(&mut eeprom, 0),
+            Err(Error::Kind(kind)) if kind == Record::KIND
+        ));
+
+        // 损坏的类型字节应报告CRC错误
+        eeprom.write(2, &[Scale::KIND]).unwrap();
+        assert!(matches!(load::<_, Scale>(&mut eeprom, 0), Err(Error::Crc)));
+        eeprom.write(2, &[Record::KIND]).unwrap();
+
+        // 修改负载中的一个字节
+        eeprom.write(10, &[0x42]).unwrap();
+        assert!(matches!(load::<_, Record>(&mut eeprom, 0), Err(Error::Crc)));
+
+        // 未知版本且CRC正确
+        let mut raw = *b"SH\x01\x09\x00\x00\x00\x00";
+        let crc = crc16(&raw[..6]).to_le_bytes();
+        raw[6..].copy_from_slice(&crc);
+        eeprom.write(0, &raw).unwrap();
+        assert!(matches!(
+            load::<_, Record>(&mut eeprom, 0),
+            Err(Error::Version(9))
+        ));
+    }
+
+    #[test]
+    fn storage_errors_are_returned() {
+        let mut eeprom = MockEeprom::<16>::new();
+        assert!(matches!(
+            save(&mut eeprom, 0, &record()),
+            Err(Error::Storage(_))
+        ));
+        assert!(matches!(
+            load::<_, Record>(&mut eeprom, 14),
+            Err(Error::Storage(_))
+        ));
+    }
+}
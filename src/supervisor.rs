@@ -0,0 +1,363 @@
+//! Sensor health monitoring with automatic recovery
+//!
+//! The [`Supervisor`] wraps a driver, counts its consecutive read errors and runs the
+//! driver's [`Recover`] action when they pile up, e.g. re-initializing an AHT30 that keeps
+//! reporting busy instead of rebooting the board.
+
+use embedded_hal::{
+    digital::{InputPin, OutputPin},
+    i2c::{I2c, SevenBitAddress},
+};
+use embedded_timers::clock::Clock;
+
+use crate::{aht30, bme280, hx711};
+
+/// Driver with a recovery action
+pub trait Recover {
+    /// Error of the recovery action
+    type Error;
+
+    /// Bring a failing sensor back into a working state
+    fn recover(&mut self) -> Result<(), Self::Error>;
+}
+
+impl<T: Recover> Recover for &mut T {
+    type Error = T::Error;
+
+    fn recover(&mut self) -> Result<(), Self::Error> {
+        T::recover(self)
+    }
+}
+
+/// Re-initializes the sensor with the 0xBE command
+impl<C: Clock, B: I2c<SevenBitAddress>> Recover for aht30::OwnedDriver<'_, C, B> {
+    type Error = aht30::Error<B>;
+
+    fn recover(&mut self) -> Result<(), Self::Error> {
+        self.initialize()
+    }
+}
+
+/// Soft resets the sensor, re-reads the calibration params from its NVM and restores the
+/// oversampling and mode configuration
+impl<C: Clock, B: I2c<SevenBitAddress>> Recover for bme280::OwnedDriver<'_, C, B> {
+    type Error = B::Error;
+
+    fn recover(&mut self) -> Result<(), Self::Error> {
+        // 重置时会重新读取 NVM 中的校准参数
+        self.reset()?;
+        // 软重置后传感器处于睡眠模式，需要重新配置
+        self.configure()
+    }
+}
+
+/// Power cycles the ADC
+impl<C: Clock, I: InputPin, O: OutputPin> Recover for hx711::Driver<'_, C, I, O> {
+    type Error = O::Error;
+
+    fn recover(&mut self) -> Result<(), Self::Error> {
+        self.reset()
+    }
+}
+
+/// Supervisor config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// Consecutive errors before a recovery is attempted, repeated every this many errors
+    pub recover_after: u32,
+    /// Consecutive errors before the sensor is considered failed
+    pub failed_after: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            recover_after: 3,
+            failed_after: 10,
+        }
+    }
+}
+
+/// Sensor health
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Health {
+    /// The latest read succeeded
+    Ok,
+    /// The latest reads failed
+    Degraded,
+    /// At least [`Config::failed_after`] consecutive reads failed
+    Failed,
+}
+
+/// Supervisor counters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counters {
+    /// Number of reads
+    pub reads: u32,
+    /// Number of failed reads
+    pub errors: u32,
+    /// Failed reads since the last successful one
+    pub consecutive_errors: u32,
+    /// Number of recovery attempts
+    pub recoveries: u32,
+    /// Number of recovery attempts that returned an error
+    pub failed_recoveries: u32,
+}
+
+/// Health supervisor of a driver
+pub struct Supervisor<D: Recover> {
+    /// Supervised driver
+    driver: D,
+    /// Supervisor config
+    config: Config,
+    /// Current health
+    health: Health,
+    /// Read and recovery counters
+    counters: Counters,
+}
+
+impl<D: Recover> Supervisor<D> {
+    /// Supervise a driver, it starts out healthy
+    pub fn new(driver: D, config: Config) -> Self {
+        Self {
+            driver,
+            config,
+            health: Health::Ok,
+            counters: Counters::default(),
+        }
+    }
+
+    /// Run a read of the driver and track its result
+    ///
+    /// ```ignore
+    /// let (temperature, humidity) = supervisor.read(|aht30| aht30.read())?;
+    /// ```
+    ///
+    /// Note: The recovery runs after the failed read, the error of that read is still returned
+    // is_multiple_of 需要 Rust 1.87，保持对旧工具链的支持
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn read<T, E>(&mut self, read: impl FnOnce(&mut D) -> Result<T, E>) -> Result<T, E> {
+        self.counters.reads = self.counters.reads.wrapping_add(1);
+        let result = read(&mut self.driver);
+        match result {
+            Ok(_) => {
+                self.counters.consecutive_errors = 0;
+                self.health = Health::Ok;
+            }
+            Err(_) => {
+                self.counters.errors = self.counters.errors.wrapping_add(1);
+                self.counters.consecutive_errors =
+                    self.counters.consecutive_errors.saturating_add(1);
+                let consecutive = self.counters.consecutive_errors;
+                self.health = if consecutive >= self.config.failed_after {
+                    Health::Failed
+                } else {
+                    Health::Degraded
+                };
+                // 连续错误达到阈值时尝试恢复，失败后每隔相同次数再次尝试
+                if self.config.recover_after > 0 && consecutive % self.config.recover_after == 0 {
+                    // 恢复结果已记录在计数器中，读取错误优先返回
+                    let _ = self.recover();
+                }
+            }
+        }
+        result
+    }
+
+    /// Run the recovery action now
+    pub fn recover(&mut self) -> Result<(), D::Error> {
+        self.counters.recoveries = self.counters.recoveries.wrapping_add(1);
+        let result = self.driver.recover();
+        if result.is_err() {
+            self.counters.failed_recoveries = self.counters.failed_recoveries.wrapping_add(1);
+        }
+        result
+    }
+
+    /// Get the current health
+    pub fn health(&self) -> Health {
+        self.health
+    }
+
+    /// Get the counters
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    /// Clear the counters, the health is kept
+    pub fn reset_counters(&mut self) {
+        self.counters = Counters {
+            consecutive_errors: self.counters.consecutive_errors,
+            ..Counters::default()
+        };
+    }
+
+    /// Get the supervisor config
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Change the supervisor config
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Get the supervised driver
+    pub fn driver(&self) -> &D {
+        &self.driver
+    }
+
+    /// Get the supervised driver, e.g. to change its settings
+    pub fn driver_mut(&mut self) -> &mut D {
+        &mut self.driver
+    }
+
+    /// Release the driver
+    pub fn release(self) -> D {
+        self.driver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Counters, Health, Supervisor};
+    use crate::{
+        aht30, hx711,
+        mock::{Aht30Device, Hx711Device, MockClock},
+    };
+
+    #[test]
+    fn recovers_hx711_after_consecutive_errors() {
+        let clock = MockClock::new();
+        let device = Hx711Device::new(&clock);
+        device.set_value(1000);
+        let driver = hx711::Driver::new(
+            &clock,
+            device.clock_pin(),
+            device.data_pin(),
+            hx711::ChannelGain::ChannelA128,
+        )
+        .unwrap();
+        let mut supervisor = Supervisor::new(driver, Config::default());
+
+        assert_eq!(supervisor.read(|hx711| hx711.read()).unwrap(), 1000);
+        assert_eq!(supervisor.health(), Health::Ok);
+
+        device.set_ready(false);
+        for _ in 0..2 {
+            assert!(supervisor.read(|hx711| hx711.read()).is_err());
+        }
+        assert_eq!(supervisor.health(), Health::Degraded);
+        assert_eq!(device.power_downs(), 0);
+
+        // 第3次连续错误触发重置
+        assert!(supervisor.read(|hx711| hx711.read()).is_err());
+        assert_eq!(device.power_downs(), 1);
+
+        device.set_ready(true);
+        assert_eq!(supervisor.read(|hx711| hx711.read()).unwrap(), 1000);
+        assert_eq!(supervisor.health(), Health::Ok);
+        assert_eq!(
+            *supervisor.counters(),
+            Counters {
+                reads: 5,
+                errors: 3,
+                consecutive_errors: 0,
+                recoveries: 1,
+                failed_recoveries: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn aht30_fails_after_repeated_crc_errors() {
+        let clock = MockClock::new();
+        let mut device = Aht30Device::new();
+        device.set_corrupt_crc(true);
+        let driver = aht30::OwnedDriver::new(&clock, &mut device, None).unwrap();
+        let config = Config {
+            recover_after: 2,
+            failed_after: 5,
+        };
+        let mut supervisor = Supervisor::new(driver, config);
+
+        for _ in 0..4 {
+            let result = supervisor.read(|aht30| aht30.read());
+            assert!(matches!(result, Err(aht30::Error::Crc)));
+        }
+        assert_eq!(supervisor.health(), Health::Degraded);
+        supervisor.read(|aht30| aht30.read()).unwrap_err();
+        assert_eq!(supervisor.health(), Health::Failed);
+        assert_eq!(supervisor.counters().recoveries, 2);
+
+        supervisor.reset_counters();
+        assert_eq!(supervisor.counters().errors, 0);
+        assert_eq!(supervisor.counters().consecutive_errors, 5);
+
+        // 每次恢复都重新发送 0xBE 初始化命令
+        let (_, device) = supervisor.release().release();
+        assert_eq!(device.inits(), 3);
+    }
+
+    #[cfg(feature = "embedded-hal-bus")]
+    #[test]
+    fn bme280_recovery_reloads_calibration() {
+        use core::cell::RefCell;
+
+        use crate::{
+            bme280,
+            mock::{Bme280Calibration, Bme280Device, BME280_CALIBRATION},
+        };
+
+        let clock = MockClock::new();
+        let bus = RefCell::new(Bme280Device::new());
+        let driver = bme280::OwnedDriver::new_ref_cell(&clock, &bus, None).unwrap();
+        let mut supervisor = Supervisor::new(driver, Config::default());
+        assert_eq!(supervisor.driver().calibration().dig_t1, 27504);
+
+        bus.borrow_mut().set_calibration(&Bme280Calibration {
+            t: (28000, 26435, -1000),
+            ..BME280_CALIBRATION
+        });
+        supervisor.recover().unwrap();
+        assert_eq!(bus.borrow().resets(), 1);
+        assert_eq!(supervisor.driver().calibration().dig_t1, 28000);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn bme280_measures_again_after_recovery() {
+        use crate::{
+            bme280,
+            sim::{Bme280Device, Bme280Fault, SimClock},
+        };
+
+        let clock = SimClock::virtual_time();
+        let device = Bme280Device::new(&clock);
+        device.set_temperature(21.5);
+        let driver = bme280::OwnedDriver::new(&clock, device.clone(), None).unwrap();
+        let mut supervisor = Supervisor::new(driver, Config::default());
+
+        device.inject_times(Bme280Fault::Nack, 3);
+        for _ in 0..3 {
+            assert!(supervisor.read(|bme280| bme280.read()).is_err());
+        }
+        assert_eq!(supervisor.counters().recoveries, 1);
+        assert_eq!(supervisor.counters().failed_recoveries, 0);
+        // 恢复后传感器应回到正常模式
+        assert_eq!(device.register(0xF2), 0x01);
+        assert_eq!(device.register(0xF4), 0x27);
+
+        let (temperature, pressure, humidity) = supervisor.read(|bme280| bme280.read()).unwrap();
+        assert!((temperature - 21.5).abs() < 0.01);
+        assert!((pressure - 101_325.0).abs() < 1.0);
+        assert!((humidity - 50.0).abs() < 0.1);
+        assert_eq!(supervisor.health(), Health::Ok);
+    }
+}
@@ -0,0 +1,448 @@
+//! Threshold alarms with hysteresis
+//!
+//! The [`Controller`] compares each sensor reading against high and low setpoints and drives
+//! an on/off [`Switch`] actuator, e.g. "turn the fan relay on when the humidity exceeds 70%RH".
+
+use core::time::Duration;
+
+use embedded_timers::{clock::Clock, instant::Instant};
+
+use crate::actuator::Switch;
+
+/// Threshold controller config
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// The alarm is raised when the reading rises above this setpoint
+    pub high: Option<f32>,
+    /// The alarm is raised when the reading falls below this setpoint
+    pub low: Option<f32>,
+    /// Hysteresis, the alarm clears only once the reading is back inside the setpoints
+    /// by at least this amount
+    pub deadband: f32,
+    /// Minimum time the actuator stays on once switched on
+    pub min_on_time: Duration,
+    /// Minimum time the actuator stays off once switched off
+    pub min_off_time: Duration,
+    /// Keep the actuator on after the alarm clears until [`Controller::acknowledge`] is called
+    pub latch: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            high: None,
+            low: None,
+            deadband: 0.0,
+            min_on_time: Duration::ZERO,
+            min_off_time: Duration::ZERO,
+            latch: false,
+        }
+    }
+}
+
+impl Config {
+    /// Alarm above `setpoint`, e.g. a fan turned on when the humidity is too high
+    pub fn above(setpoint: f32, deadband: f32) -> Self {
+        Self {
+            high: Some(setpoint),
+            deadband,
+            ..Self::default()
+        }
+    }
+
+    /// Alarm below `setpoint`, e.g. a heater turned on when the temperature is too low
+    pub fn below(setpoint: f32, deadband: f32) -> Self {
+        Self {
+            low: Some(setpoint),
+            deadband,
+            ..Self::default()
+        }
+    }
+
+    /// Alarm outside the `low` ~ `high` range
+    pub fn outside(low: f32, high: f32, deadband: f32) -> Self {
+        Self {
+            high: Some(high),
+            low: Some(low),
+            deadband,
+            ..Self::default()
+        }
+    }
+}
+
+/// Reading level relative to the setpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Level {
+    /// Inside the setpoints
+    Normal,
+    /// Above the high setpoint
+    High,
+    /// Below the low setpoint
+    Low,
+}
+
+/// Threshold controller with hysteresis, minimum hold times and alarm latching
+///
+/// Call [`update`](Self::update) with every new reading. Each call also updates the actuator,
+/// so deferred changes of a protected relay or the hold duty cycle of an economizer are applied.
+pub struct Controller<'a, C: Clock, A: Switch> {
+    /// Driven actuator
+    actuator: A,
+    /// Controller config
+    config: Config,
+    /// Level of the latest reading
+    level: Level,
+    /// Whether a latched alarm is waiting for acknowledgement
+    latched: bool,
+    /// State last requested from the actuator, which may defer the change
+    requested: bool,
+    /// Time of the last actuator change
+    last_switch: Option<C::Instant>,
+    /// External clock implementation
+    clock_impl: &'a C,
+}
+
+impl<'a, C: Clock, A: Switch> Controller<'a, C, A> {
+    /// Create a threshold controller
+    ///
+    /// Note: The actuator is switched off when the controller is created
+    pub fn new(clock: &'a C, mut actuator: A, config: Config) -> Result<Self, A::Error> {
+        // 确保执行器处于关闭状态
+        actuator.switch(false)?;
+        // OK
+        Ok(Self {
+            actuator,
+            config,
+            level: Level::Normal,
+            latched: false,
+            requested: false,
+            last_switch: None,
+            clock_impl: clock,
+        })
+    }
+
+    /// Feed a new reading
+    ///
+    /// - True: The actuator has been switched
+    /// - False: Nothing has changed
+    ///
+    /// Note: A change blocked by the minimum hold time is applied by a later call
+    pub fn update(&mut self, reading: f32) -> Result<bool, A::Error> {
+        self.level = self.classify(reading);
+        if self.level != Level::Normal && self.config.latch {
+            self.latched = true;
+        }
+        self.apply()
+    }
+
+    /// Acknowledge a latched alarm
+    ///
+    /// The actuator is switched off by this or a later call once the reading is back to normal
+    /// and the minimum on-time has elapsed.
+    pub fn acknowledge(&mut self) -> Result<bool, A::Error> {
+        self.latched = false;
+        self.apply()
+    }
+
+    /// Level of the latest reading
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Whether a latched alarm is waiting for [`acknowledge`](Self::acknowledge)
+    pub fn is_latched(&self) -> bool {
+        self.latched
+    }
+
+    /// Get the actuator state
+    pub fn is_on(&self) -> bool {
+        self.actuator.is_on()
+    }
+
+    /// Get the actuator
+    pub fn actuator(&self) -> &A {
+        &self.actuator
+    }
+
+    /// Get the actuator mutably, e.g. to read the counters of a protected relay
+    ///
+    /// Note: Switching the actuator directly bypasses the controller
+    pub fn actuator_mut(&mut self) -> &mut A {
+        &mut self.actuator
+    }
+
+    /// Get the controller config
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Change the controller config, applied from the next reading
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    /// Release the actuator
+    pub fn release(self) -> A {
+        self.actuator
+    }
+
+    /// Compare a reading against the setpoints, keeping the current level inside the deadband
+    fn classify(&self, reading: f32) -> Level {
+        let deadband = self.config.deadband.abs();
+        if let Some(high) = self.config.high {
+            if reading > high || (self.level == Level::High && reading > high - deadband) {
+                return Level::High;
+            }
+        }
+        if let Some(low) = self.config.low {
+            if reading < low || (self.level == Level::Low && reading < low + deadband) {
+                return Level::Low;
+            }
+        }
+        Level::Normal
+    }
+
+    /// Update the actuator and switch it to the wanted state once the minimum hold time allows it
+    ///
+    /// Returns whether the actuator state has changed
+    fn apply(&mut self) -> Result<bool, A::Error> {
+        let was_on = self.actuator.is_on();
+        // 执行器可能在更新时应用延迟或定时的切换
+        self.actuator.update()?;
+        self.track(was_on);
+
+        let target = self.level != Level::Normal || self.latched;
+        let is_on = self.actuator.is_on();
+        // 回到当前状态时取消执行器中等待的切换，不受保持时间限制
+        if target != self.requested && (target == is_on || self.hold_elapsed(is_on)) {
+            self.actuator.switch(target)?;
+            self.requested = target;
+            self.track(is_on);
+        }
+        Ok(self.actuator.is_on() != was_on)
+    }
+
+    /// Whether the minimum hold time of the current actuator state has elapsed
+    fn hold_elapsed(&self, is_on: bool) -> bool {
+        let Some(last) = self.last_switch else {
+            return true;
+        };
+        let hold = if is_on {
+            self.config.min_on_time
+        } else {
+            self.config.min_off_time
+        };
+        self.clock_impl.now().duration_since(last) >= hold
+    }
+
+    /// Record the time of an actuator change
+    fn track(&mut self, was_on: bool) {
+        if self.actuator.is_on() != was_on {
+            self.last_switch = Some(self.clock_impl.now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use embedded_hal::digital::PinState;
+
+    use super::{Config, Controller, Level};
+    use crate::{
+        dc_relay::{self, CoilState, Protection, ProtectionMode},
+        led,
+        mock::{MockClock, OutputState, PwmState},
+    };
+
+    #[test]
+    fn switches_with_hysteresis() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = dc_relay::Driver::new(state.pin(), PinState::High, false).unwrap();
+        let mut controller = Controller::new(&clock, relay, Config::above(70.0, 5.0)).unwrap();
+
+        assert!(!controller.update(69.0).unwrap());
+        assert!(controller.update(71.0).unwrap());
+        assert!(state.is_high());
+        assert_eq!(controller.level(), Level::High);
+
+        // 死区内保持开启
+        assert!(!controller.update(66.0).unwrap());
+        assert!(state.is_high());
+        assert!(controller.update(64.9).unwrap());
+        assert!(!state.is_high());
+        assert_eq!(controller.level(), Level::Normal);
+    }
+
+    #[test]
+    fn low_and_high_setpoints() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let led = led::Driver::new(state.pin(), PinState::Low);
+        let mut controller =
+            Controller::new(&clock, led, Config::outside(18.0, 26.0, 1.0)).unwrap();
+        assert!(state.is_high());
+
+        controller.update(17.5).unwrap();
+        assert_eq!(controller.level(), Level::Low);
+        assert!(!state.is_high());
+        controller.update(18.5).unwrap();
+        assert_eq!(controller.level(), Level::Low);
+        controller.update(19.5).unwrap();
+        assert_eq!(controller.level(), Level::Normal);
+        assert!(state.is_high());
+
+        controller.update(26.5).unwrap();
+        assert_eq!(controller.level(), Level::High);
+        assert!(controller.is_on());
+    }
+
+    #[test]
+    fn respects_minimum_hold_times() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = dc_relay::Driver::new(state.pin(), PinState::High, false).unwrap();
+        let config = Config {
+            min_on_time: Duration::from_secs(60),
+            min_off_time: Duration::from_secs(30),
+            ..Config::above(70.0, 0.0)
+        };
+        let mut controller = Controller::new(&clock, relay, config).unwrap();
+
+        assert!(controller.update(75.0).unwrap());
+        clock.advance(Duration::from_secs(10));
+        assert!(!controller.update(60.0).unwrap());
+        assert!(controller.is_on());
+
+        clock.advance(Duration::from_secs(50));
+        assert!(controller.update(60.0).unwrap());
+        assert!(!controller.is_on());
+
+        clock.advance(Duration::from_secs(10));
+        assert!(!controller.update(75.0).unwrap());
+        clock.advance(Duration::from_secs(20));
+        assert!(controller.update(75.0).unwrap());
+        assert!(state.is_high());
+    }
+
+    #[test]
+    fn latched_alarm_needs_acknowledge() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = dc_relay::Driver::new(state.pin(), PinState::High, false).unwrap();
+        let config = Config {
+            latch: true,
+            ..Config::below(5.0, 0.5)
+        };
+        let mut controller = Controller::new(&clock, relay, config).unwrap();
+
+        controller.update(4.0).unwrap();
+        controller.update(10.0).unwrap();
+        assert_eq!(controller.level(), Level::Normal);
+        assert!(controller.is_latched());
+        assert!(state.is_high());
+
+        assert!(controller.acknowledge().unwrap());
+        assert!(!controller.is_latched());
+        assert!(!state.is_high());
+    }
+
+    #[test]
+    fn acknowledge_while_alarm_active_keeps_output() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = dc_relay::Driver::new(state.pin(), PinState::High, false).unwrap();
+        let config = Config {
+            latch: true,
+            ..Config::above(30.0, 0.0)
+        };
+        let mut controller = Controller::new(&clock, relay, config).unwrap();
+
+        controller.update(35.0).unwrap();
+        assert!(!controller.acknowledge().unwrap());
+        assert!(state.is_high());
+        // 报警仍然存在，再次锁存
+        controller.update(35.0).unwrap();
+        assert!(controller.is_latched());
+    }
+
+    #[test]
+    fn economizer_drops_to_hold_duty() {
+        let clock = MockClock::manual();
+        let pwm = PwmState::new(1000);
+        let coil = dc_relay::EconomizerDriver::new(
+            &clock,
+            dc_relay::PwmDriver::new(pwm.pwm()),
+            Duration::from_millis(50),
+            30,
+        )
+        .unwrap();
+        let mut controller = Controller::new(&clock, coil, Config::above(70.0, 0.0)).unwrap();
+
+        assert!(controller.update(75.0).unwrap());
+        assert_eq!(pwm.duty(), 1000);
+        // 每次更新读数时也更新执行器
+        clock.advance(Duration::from_millis(50));
+        assert!(!controller.update(75.0).unwrap());
+        assert_eq!(controller.actuator().state(), CoilState::Hold);
+        assert_eq!(pwm.duty(), 300);
+
+        assert!(controller.update(60.0).unwrap());
+        assert_eq!(pwm.duty(), 0);
+    }
+
+    #[test]
+    fn applies_deferred_relay_changes() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = dc_relay::Driver::new(state.pin(), PinState::High, false).unwrap();
+        let protection = Protection {
+            min_on_time: Duration::from_secs(10),
+            mode: ProtectionMode::Defer,
+            ..Default::default()
+        };
+        let relay = dc_relay::ProtectedDriver::new(&clock, relay, protection);
+        let mut controller = Controller::new(&clock, relay, Config::above(70.0, 0.0)).unwrap();
+
+        assert!(controller.update(75.0).unwrap());
+        // 继电器推迟关闭，控制器报告真实状态
+        assert!(!controller.update(60.0).unwrap());
+        assert!(controller.is_on());
+        assert_eq!(controller.actuator().pending(), Some(false));
+
+        // 读数恢复时取消等待中的关闭
+        assert!(!controller.update(75.0).unwrap());
+        assert_eq!(controller.actuator().pending(), None);
+        clock.advance(Duration::from_secs(10));
+        assert!(!controller.update(75.0).unwrap());
+        assert!(state.is_high());
+
+        controller.update(60.0).unwrap();
+        assert!(!state.is_high());
+        assert!(controller.update(75.0).unwrap());
+        assert!(!controller.update(60.0).unwrap());
+        clock.advance(Duration::from_secs(10));
+        assert!(controller.update(60.0).unwrap());
+        assert!(!controller.is_on());
+        assert!(!state.is_high());
+        assert_eq!(controller.actuator_mut().cycles(), 2);
+    }
+
+    #[test]
+    fn actuator_errors_are_returned() {
+        let clock = MockClock::manual();
+        let state = OutputState::new();
+        let relay = dc_relay::Driver::new(state.pin(), PinState::High, false).unwrap();
+        let mut controller = Controller::new(&clock, relay, Config::above(1.0, 0.0)).unwrap();
+
+        state.set_fail(true);
+        assert!(controller.update(2.0).is_err());
+        assert!(!controller.is_on());
+        state.set_fail(false);
+        assert!(controller.update(2.0).unwrap());
+    }
+}